@@ -14,6 +14,7 @@ pub struct GroveDBPathQueryMethodVersions {
     pub terminal_keys: FeatureVersion,
     pub merge: FeatureVersion,
     pub query_items_at_path: FeatureVersion,
+    pub after_cursor: FeatureVersion,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -63,6 +64,7 @@ pub struct GroveDBOperationsGetVersions {
     pub worst_case_for_get_raw: FeatureVersion,
     pub worst_case_for_get: FeatureVersion,
     pub is_empty_tree: FeatureVersion,
+    pub diff: FeatureVersion,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -77,6 +79,7 @@ pub struct GroveDBOperationsProofVersions {
     pub verify_query_with_absence_proof: FeatureVersion,
     pub verify_subset_query_with_absence_proof: FeatureVersion,
     pub verify_query_with_chained_path_queries: FeatureVersion,
+    pub verify_query_aggregates: FeatureVersion,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -89,6 +92,12 @@ pub struct GroveDBOperationsQueryVersions {
     pub query_item_value_or_sum: FeatureVersion,
     pub query_sums: FeatureVersion,
     pub query_raw: FeatureVersion,
+    pub query_with_cursor: FeatureVersion,
+    pub query_raw_with_cursor: FeatureVersion,
+    pub query_iter: FeatureVersion,
+    pub query_raw_iter: FeatureVersion,
+    pub query_item_value_iter: FeatureVersion,
+    pub query_aggregates: FeatureVersion,
     pub query_keys_optional: FeatureVersion,
     pub query_raw_keys_optional: FeatureVersion,
     pub follow_element: FeatureVersion,
@@ -208,6 +217,7 @@ pub struct GroveDBElementMethodVersions {
     pub get_query_values: FeatureVersion,
     pub get_query_apply_function: FeatureVersion,
     pub get_path_query: FeatureVersion,
+    pub get_path_query_with_cursor: FeatureVersion,
     pub get_sized_query: FeatureVersion,
     pub path_query_push: FeatureVersion,
     pub query_item: FeatureVersion,
@@ -223,4 +233,9 @@ pub struct GroveDBReplicationVersions {
     pub fetch_chunk: FeatureVersion,
     pub start_snapshot_syncing: FeatureVersion,
     pub apply_chunk: FeatureVersion,
+    pub fetch_multi_chunk: FeatureVersion,
+    pub apply_multi_chunk: FeatureVersion,
+    pub apply_chunks: FeatureVersion,
+    pub apply_logged_batch: FeatureVersion,
+    pub catch_up_from_op_log: FeatureVersion,
 }
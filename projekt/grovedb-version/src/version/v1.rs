@@ -58,6 +58,7 @@ pub const GROVE_V1: GroveVersion = GroveVersion {
             get_query_values: 0,
             get_query_apply_function: 0,
             get_path_query: 0,
+            get_path_query_with_cursor: 0,
             get_sized_query: 0,
             path_query_push: 0,
             query_item: 0,
@@ -86,6 +87,7 @@ pub const GROVE_V1: GroveVersion = GroveVersion {
                 worst_case_for_get_raw: 0,
                 worst_case_for_get: 0,
                 is_empty_tree: 0,
+                diff: 0,
             },
             insert: GroveDBOperationsInsertVersions {
                 insert: 0,
@@ -125,6 +127,12 @@ pub const GROVE_V1: GroveVersion = GroveVersion {
                 query_item_value_or_sum: 0,
                 query_sums: 0,
                 query_raw: 0,
+                query_with_cursor: 0,
+                query_raw_with_cursor: 0,
+                query_iter: 0,
+                query_raw_iter: 0,
+                query_item_value_iter: 0,
+                query_aggregates: 0,
                 query_keys_optional: 0,
                 query_raw_keys_optional: 0,
                 follow_element: 0,
@@ -140,6 +148,7 @@ pub const GROVE_V1: GroveVersion = GroveVersion {
                 verify_query_with_absence_proof: 0,
                 verify_subset_query_with_absence_proof: 0,
                 verify_query_with_chained_path_queries: 0,
+                verify_query_aggregates: 0,
             },
             average_case: GroveDBOperationsAverageCaseVersions {
                 add_average_case_get_merk_at_path: 0,
@@ -175,12 +184,18 @@ pub const GROVE_V1: GroveVersion = GroveVersion {
             terminal_keys: 0,
             merge: 0,
             query_items_at_path: 0,
+            after_cursor: 0,
         },
         replication: GroveDBReplicationVersions {
             get_subtrees_metadata: 0,
             fetch_chunk: 0,
             start_snapshot_syncing: 0,
             apply_chunk: 0,
+            fetch_multi_chunk: 0,
+            apply_multi_chunk: 0,
+            apply_chunks: 0,
+            apply_logged_batch: 0,
+            catch_up_from_op_log: 0,
         },
     },
     merk_versions: MerkVersions {},
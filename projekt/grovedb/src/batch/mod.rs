@@ -34,6 +34,7 @@ use std::{
     vec::IntoIter,
 };
 
+use bincode::{Decode, Encode};
 #[cfg(feature = "estimated_costs")]
 use estimated_costs::{
     average_case_costs::AverageCaseTreeCacheKnownPaths,
@@ -83,7 +84,7 @@ use crate::{
 };
 
 /// Operations
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Encode, Decode)]
 pub enum GroveOp {
     /// Replace tree root key
     ReplaceTreeRootKey {
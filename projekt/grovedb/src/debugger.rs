@@ -330,6 +330,7 @@ fn query_to_grovedb(query: Query) -> crate::Query {
             query.conditional_subquery_branches,
         ),
         left_to_right: query.left_to_right,
+        value_filter: None,
     }
 }
 
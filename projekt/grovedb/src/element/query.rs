@@ -11,7 +11,7 @@ use grovedb_costs::{
 #[cfg(feature = "full")]
 use grovedb_merk::proofs::query::query_item::QueryItem;
 #[cfg(feature = "full")]
-use grovedb_merk::proofs::query::SubqueryBranch;
+use grovedb_merk::proofs::query::{SubqueryBranch, ValueFilter};
 #[cfg(feature = "full")]
 use grovedb_merk::proofs::Query;
 #[cfg(feature = "full")]
@@ -38,7 +38,7 @@ use crate::{
         },
     },
     util::{merk_optional_tx, merk_optional_tx_internal_error, storage_context_optional_tx},
-    Error, PathQuery, TransactionArg,
+    Error, PathQuery, QueryCursor, TransactionArg,
 };
 #[cfg(feature = "full")]
 use crate::{query_result_type::Path, SizedQuery};
@@ -104,11 +104,15 @@ where
     pub subquery_path: Option<Path>,
     pub subquery: Option<Query>,
     pub left_to_right: bool,
+    /// Filter on the values of the elements pushed as results
+    pub value_filter: Option<&'a ValueFilter>,
     pub query_options: QueryOptions,
     pub result_type: QueryResultType,
     pub results: &'a mut Vec<QueryResultElement>,
     pub limit: &'a mut Option<u16>,
     pub offset: &'a mut Option<u16>,
+    /// Set to the position of the element that brings the limit down to 0
+    pub cursor: &'a mut Option<QueryCursor>,
 }
 
 #[cfg(feature = "full")]
@@ -142,6 +146,9 @@ fn format_query(query: &Query, indent: usize) -> String {
     }
 
     output += &format!("{}  left_to_right: {}\n", indent_str, query.left_to_right);
+    if let Some(value_filter) = &query.value_filter {
+        output += &format!("{}  value_filter: {}\n", indent_str, value_filter);
+    }
     output += &format!("{}}}", indent_str);
 
     output
@@ -212,6 +219,12 @@ where
                 .map_or("None".to_string(), |q| format!("\n{}", format_query(q, 4)))
         )?;
         writeln!(f, "  left_to_right: {}", self.left_to_right)?;
+        writeln!(
+            f,
+            "  value_filter: {}",
+            self.value_filter
+                .map_or("None".to_string(), |value_filter| value_filter.to_string())
+        )?;
         writeln!(f, "  query_options: {}", self.query_options)?;
         writeln!(f, "  result_type: {}", self.result_type)?;
         writeln!(
@@ -225,6 +238,7 @@ where
         )?;
         writeln!(f, "  limit: {:?}", self.limit)?;
         writeln!(f, "  offset: {:?}", self.offset)?;
+        writeln!(f, "  cursor: {:?}", self.cursor)?;
         write!(f, "}}")
     }
 }
@@ -318,6 +332,33 @@ impl Element {
                 .get_query_apply_function
         );
 
+        Self::query_apply_function_with_cursor(
+            storage,
+            path,
+            sized_query,
+            query_options,
+            result_type,
+            transaction,
+            add_element_function,
+            &mut None,
+            grove_version,
+        )
+    }
+
+    #[cfg(feature = "full")]
+    // Applies the query like `get_query_apply_function`, also returning in
+    // `cursor` the position of the last element if the limit was reached
+    fn query_apply_function_with_cursor(
+        storage: &RocksDbStorage,
+        path: &[&[u8]],
+        sized_query: &SizedQuery,
+        query_options: QueryOptions,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+        add_element_function: fn(PathQueryPushArgs, &GroveVersion) -> CostResult<(), Error>,
+        cursor: &mut Option<QueryCursor>,
+        grove_version: &GroveVersion,
+    ) -> CostResult<(QueryResultElements, u16), Error> {
         let mut cost = OperationCost::default();
 
         let mut results = Vec::new();
@@ -342,6 +383,7 @@ impl Element {
                         query_options,
                         result_type,
                         add_element_function,
+                        cursor,
                         grove_version,
                     )
                 );
@@ -365,6 +407,7 @@ impl Element {
                         query_options,
                         result_type,
                         add_element_function,
+                        cursor,
                         grove_version,
                     )
                 );
@@ -374,6 +417,12 @@ impl Element {
             }
         }
 
+        // The query ran out of elements before reaching the limit, there is
+        // nothing to continue from
+        if limit != Some(0) {
+            *cursor = None;
+        }
+
         let skipped = if let Some(original_offset_unwrapped) = original_offset {
             original_offset_unwrapped - offset.unwrap()
         } else {
@@ -398,12 +447,67 @@ impl Element {
             grove_version.grovedb_versions.element.get_path_query
         );
 
+        Element::path_query_with_cursor(
+            storage,
+            path_query,
+            query_options,
+            result_type,
+            transaction,
+            &mut None,
+            grove_version,
+        )
+    }
+
+    #[cfg(feature = "full")]
+    /// Returns a vector of elements excluding trees, the number of skipped
+    /// elements, and the cursor of the last element if the limit of the path
+    /// query was reached. `PathQuery::after_cursor` turns the cursor into the
+    /// path query of the next page.
+    pub fn get_path_query_with_cursor(
+        storage: &RocksDbStorage,
+        path_query: &PathQuery,
+        query_options: QueryOptions,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> CostResult<(QueryResultElements, u16, Option<QueryCursor>), Error> {
+        check_grovedb_v0_with_cost!(
+            "get_path_query_with_cursor",
+            grove_version
+                .grovedb_versions
+                .element
+                .get_path_query_with_cursor
+        );
+
+        let mut cursor = None;
+        Element::path_query_with_cursor(
+            storage,
+            path_query,
+            query_options,
+            result_type,
+            transaction,
+            &mut cursor,
+            grove_version,
+        )
+        .map_ok(|(elements, skipped)| (elements, skipped, cursor))
+    }
+
+    #[cfg(feature = "full")]
+    fn path_query_with_cursor(
+        storage: &RocksDbStorage,
+        path_query: &PathQuery,
+        query_options: QueryOptions,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+        cursor: &mut Option<QueryCursor>,
+        grove_version: &GroveVersion,
+    ) -> CostResult<(QueryResultElements, u16), Error> {
         let path_slices = path_query
             .path
             .iter()
             .map(|x| x.as_slice())
             .collect::<Vec<_>>();
-        Element::get_query_apply_function(
+        Element::query_apply_function_with_cursor(
             storage,
             path_slices.as_slice(),
             &path_query.query,
@@ -411,6 +515,7 @@ impl Element {
             result_type,
             transaction,
             Element::path_query_push,
+            cursor,
             grove_version,
         )
     }
@@ -467,11 +572,13 @@ impl Element {
             subquery_path,
             subquery,
             left_to_right,
+            value_filter,
             query_options,
             result_type,
             results,
             limit,
             offset,
+            cursor,
         } = args;
         let QueryOptions {
             allow_get_raw,
@@ -498,14 +605,16 @@ impl Element {
                 let path_vec_owned = path_vec.iter().map(|x| x.to_vec()).collect();
                 let inner_path_query = PathQuery::new(path_vec_owned, inner_query);
 
+                *cursor = None;
                 let (mut sub_elements, skipped) = cost_return_on_error!(
                     &mut cost,
-                    Element::get_path_query(
+                    Element::path_query_with_cursor(
                         storage,
                         &inner_path_query,
                         query_options,
                         result_type,
                         transaction,
+                        cursor,
                         grove_version,
                     )
                 );
@@ -517,113 +626,103 @@ impl Element {
                     } else {
                         *limit = limit.saturating_sub(sub_elements.len() as u16);
                     }
+                    // Unless the subquery stopped within the subtree, the
+                    // subtree itself is the last element
+                    if *limit == 0 && cursor.is_none() {
+                        *cursor = Some(QueryCursor::new(
+                            path.iter().map(|p| p.to_vec()).collect(),
+                            key.to_vec(),
+                        ));
+                    }
                 }
                 if let Some(offset) = offset {
                     *offset = offset.saturating_sub(skipped);
                 }
                 results.append(&mut sub_elements.elements);
             } else if let Some(subquery_path) = subquery_path {
-                if offset.unwrap_or(0) == 0 {
-                    if let Some((subquery_path_last_key, subquery_path_front_keys)) =
-                        &subquery_path.split_last()
-                    {
-                        path_vec.extend(subquery_path_front_keys.iter().map(|k| k.as_slice()));
+                // With a value filter the element is needed to know whether it
+                // counts toward the offset
+                if offset.unwrap_or(0) == 0 || value_filter.is_some() {
+                    let Some((subquery_path_last_key, subquery_path_front_keys)) =
+                        subquery_path.split_last()
+                    else {
+                        return Err(Error::CorruptedCodeExecution(
+                            "subquery_paths can not be empty",
+                        ))
+                        .wrap_with_cost(cost);
+                    };
+                    path_vec.extend(subquery_path_front_keys.iter().map(|k| k.as_slice()));
 
-                        let subtree_path: SubtreePath<_> = path_vec.as_slice().into();
+                    let subtree_path: SubtreePath<_> = path_vec.as_slice().into();
 
-                        match result_type {
-                            QueryElementResultType => {
-                                merk_optional_tx!(
-                                    &mut cost,
-                                    storage,
-                                    subtree_path,
-                                    None,
-                                    transaction,
-                                    subtree,
+                    let mut subquery_path_element = None;
+                    merk_optional_tx!(
+                        &mut cost,
+                        storage,
+                        subtree_path,
+                        None,
+                        transaction,
+                        subtree,
+                        grove_version,
+                        {
+                            subquery_path_element = Some(cost_return_on_error!(
+                                &mut cost,
+                                Element::get_with_absolute_refs(
+                                    &subtree,
+                                    path_vec.as_slice(),
+                                    subquery_path_last_key.as_slice(),
+                                    allow_cache,
                                     grove_version,
-                                    {
-                                        results.push(QueryResultElement::ElementResultItem(
-                                            cost_return_on_error!(
-                                                &mut cost,
-                                                Element::get_with_absolute_refs(
-                                                    &subtree,
-                                                    path_vec.as_slice(),
-                                                    subquery_path_last_key.as_slice(),
-                                                    allow_cache,
-                                                    grove_version,
-                                                )
-                                            ),
-                                        ));
-                                    }
-                                );
+                                )
+                            ));
+                        }
+                    );
+                    let element = cost_return_on_error_no_add!(
+                        &cost,
+                        subquery_path_element.ok_or(Error::CorruptedCodeExecution(
+                            "subquery path element must be fetched",
+                        ))
+                    );
+
+                    // Filtered out elements are not results, they neither count
+                    // toward the limit nor the offset
+                    if let Some(value_filter) = value_filter {
+                        if !element.matches_value_filter(value_filter) {
+                            return Ok(()).wrap_with_cost(cost);
+                        }
+                    }
+
+                    if offset.unwrap_or(0) == 0 {
+                        results.push(match result_type {
+                            QueryElementResultType => {
+                                QueryResultElement::ElementResultItem(element)
                             }
                             QueryKeyElementPairResultType => {
-                                merk_optional_tx!(
-                                    &mut cost,
-                                    storage,
-                                    subtree_path,
-                                    None,
-                                    transaction,
-                                    subtree,
-                                    grove_version,
-                                    {
-                                        results.push(QueryResultElement::KeyElementPairResultItem(
-                                            (
-                                                subquery_path_last_key.to_vec(),
-                                                cost_return_on_error!(
-                                                    &mut cost,
-                                                    Element::get_with_absolute_refs(
-                                                        &subtree,
-                                                        path_vec.as_slice(),
-                                                        subquery_path_last_key.as_slice(),
-                                                        allow_cache,
-                                                        grove_version,
-                                                    )
-                                                ),
-                                            ),
-                                        ));
-                                    }
-                                );
+                                QueryResultElement::KeyElementPairResultItem((
+                                    subquery_path_last_key.to_vec(),
+                                    element,
+                                ))
                             }
                             QueryPathKeyElementTrioResultType => {
-                                merk_optional_tx!(
-                                    &mut cost,
-                                    storage,
-                                    subtree_path,
-                                    None,
-                                    transaction,
-                                    subtree,
-                                    grove_version,
-                                    {
-                                        results.push(
-                                            QueryResultElement::PathKeyElementTrioResultItem((
-                                                path_vec.iter().map(|p| p.to_vec()).collect(),
-                                                subquery_path_last_key.to_vec(),
-                                                cost_return_on_error!(
-                                                    &mut cost,
-                                                    Element::get_with_absolute_refs(
-                                                        &subtree,
-                                                        path_vec.as_slice(),
-                                                        subquery_path_last_key.as_slice(),
-                                                        allow_cache,
-                                                        grove_version,
-                                                    )
-                                                ),
-                                            )),
-                                        );
-                                    }
-                                );
+                                QueryResultElement::PathKeyElementTrioResultItem((
+                                    path_vec.iter().map(|p| p.to_vec()).collect(),
+                                    subquery_path_last_key.to_vec(),
+                                    element,
+                                ))
+                            }
+                        });
+
+                        if let Some(limit) = limit {
+                            *limit -= 1;
+                            if *limit == 0 {
+                                *cursor = Some(QueryCursor::new(
+                                    path_vec.iter().map(|p| p.to_vec()).collect(),
+                                    subquery_path_last_key.to_vec(),
+                                ));
                             }
                         }
-                    } else {
-                        return Err(Error::CorruptedCodeExecution(
-                            "subquery_paths can not be empty",
-                        ))
-                        .wrap_with_cost(cost);
-                    };
-
-                    if let Some(limit) = limit {
-                        *limit -= 1;
+                    } else if let Some(offset) = offset {
+                        *offset -= 1;
                     }
                 } else if let Some(offset) = offset {
                     *offset -= 1;
@@ -641,11 +740,13 @@ impl Element {
                             subquery_path,
                             subquery,
                             left_to_right,
+                            value_filter,
                             query_options,
                             result_type,
                             results,
                             limit,
                             offset,
+                            cursor,
                         },
                         grove_version
                     )
@@ -671,11 +772,13 @@ impl Element {
                         subquery_path,
                         subquery,
                         left_to_right,
+                        value_filter,
                         query_options,
                         result_type,
                         results,
                         limit,
                         offset,
+                        cursor,
                     },
                     grove_version
                 )
@@ -739,6 +842,7 @@ impl Element {
         query_options: QueryOptions,
         result_type: QueryResultType,
         add_element_function: fn(PathQueryPushArgs, &GroveVersion) -> CostResult<(), Error>,
+        cursor: &mut Option<QueryCursor>,
         grove_version: &GroveVersion,
     ) -> CostResult<(), Error> {
         check_grovedb_v0_with_cost!(
@@ -780,11 +884,13 @@ impl Element {
                                 subquery_path,
                                 subquery,
                                 left_to_right: sized_query.query.left_to_right,
+                                value_filter: sized_query.query.value_filter.as_ref(),
                                 query_options,
                                 result_type,
                                 results,
                                 limit,
                                 offset,
+                                cursor,
                             },
                             grove_version,
                         )
@@ -858,11 +964,13 @@ impl Element {
                             subquery_path,
                             subquery,
                             left_to_right: sized_query.query.left_to_right,
+                            value_filter: sized_query.query.value_filter.as_ref(),
                             query_options,
                             result_type,
                             results,
                             limit,
                             offset,
+                            cursor,
                         },
                         grove_version,
                     );
@@ -894,6 +1002,17 @@ impl Element {
         .wrap_with_cost(cost)
     }
 
+    #[cfg(feature = "full")]
+    /// Whether the element matches the value filter, only items and sum items
+    /// can match
+    pub fn matches_value_filter(&self, value_filter: &ValueFilter) -> bool {
+        match self {
+            Element::Item(value, _) => value_filter.matches_item_value(value),
+            Element::SumItem(value, _) => value_filter.matches_sum_value(*value),
+            _ => false,
+        }
+    }
+
     #[cfg(feature = "full")]
     fn basic_push(args: PathQueryPushArgs, grove_version: &GroveVersion) -> Result<(), Error> {
         check_grovedb_v0!(
@@ -906,13 +1025,23 @@ impl Element {
             path,
             key,
             element,
+            value_filter,
             result_type,
             results,
             limit,
             offset,
+            cursor,
             ..
         } = args;
 
+        // Filtered out elements are not results, they neither count toward the
+        // limit nor the offset
+        if let Some(value_filter) = value_filter {
+            if !element.matches_value_filter(value_filter) {
+                return Ok(());
+            }
+        }
+
         let element = element.convert_if_reference_to_absolute_reference(path, key)?;
 
         if offset.unwrap_or(0) == 0 {
@@ -943,6 +1072,12 @@ impl Element {
             }
             if let Some(limit) = limit {
                 *limit -= 1;
+                if *limit == 0 {
+                    *cursor = Some(QueryCursor::new(
+                        path.iter().map(|p| p.to_vec()).collect(),
+                        key.map(|key| key.to_vec()).unwrap_or_default(),
+                    ));
+                }
             }
         } else if let Some(offset) = offset {
             *offset -= 1;
@@ -73,6 +73,9 @@ pub enum Error {
     #[error("invalid query: {0}")]
     /// Invalid query
     InvalidQuery(&'static str),
+    #[error("query syntax error: {0}")]
+    /// Query syntax error
+    QuerySyntaxError(String),
     #[error("missing parameter: {0}")]
     /// Missing parameter
     MissingParameter(&'static str),
@@ -153,6 +156,17 @@ pub enum Error {
     /// Version error
     VersionError(grovedb_version::error::GroveVersionError),
 
+    // Replication errors
+    #[cfg(feature = "full")]
+    #[error(transparent)]
+    /// Replication error
+    ReplicationError(crate::replication::ReplicationError),
+
+    #[cfg(feature = "full")]
+    #[error("synced state failed validation: {0}")]
+    /// The state restored by a state sync failed its validation
+    SyncValidationFailed(crate::replication::SyncValidationReport),
+
     #[error("cyclic error")]
     /// Cyclic reference
     CyclicError(&'static str),
@@ -203,8 +203,10 @@ use grovedb_storage::{Storage, StorageContext};
 use grovedb_version::version::GroveVersion;
 #[cfg(feature = "full")]
 use grovedb_visualize::DebugByteVectors;
+#[cfg(feature = "full")]
+pub use operations::get::{QueryItemValueIterator, QueryIterator};
 #[cfg(any(feature = "full", feature = "verify"))]
-pub use query::{PathQuery, SizedQuery};
+pub use query::{PathQuery, QueryCursor, SizedQuery};
 #[cfg(feature = "full")]
 use reference_path::path_from_reference_path_type;
 #[cfg(feature = "grovedbg")]
@@ -0,0 +1,239 @@
+//! Diff operation
+//!
+//! `GroveDb::diff` compares two GroveDBs subtree by subtree, starting at the
+//! root. Subtrees with equal root hashes are identical and are skipped along
+//! with all their descendants, so only the subtrees that actually differ are
+//! read.
+
+use std::{cmp::Ordering, fmt};
+
+use grovedb_merk::{KVIterator, Merk};
+use grovedb_path::SubtreePath;
+use grovedb_storage::{RawIterator, StorageContext};
+use grovedb_version::{check_grovedb_v0, error::GroveVersionError, version::GroveVersion};
+
+use crate::{
+    element::helpers::raw_decode, operations::proof::util::hex_to_ascii, Element, Error, GroveDb,
+    Query,
+};
+
+/// Entry differing between two GroveDBs
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffEntry {
+    /// The entry only exists in the other GroveDB
+    Added {
+        /// Path of the subtree holding the entry
+        path: Vec<Vec<u8>>,
+        /// Key of the entry
+        key: Vec<u8>,
+        /// Element of the other GroveDB
+        element: Element,
+    },
+    /// The entry only exists in this GroveDB
+    Removed {
+        /// Path of the subtree holding the entry
+        path: Vec<Vec<u8>>,
+        /// Key of the entry
+        key: Vec<u8>,
+        /// Element of this GroveDB
+        element: Element,
+    },
+    /// The entry exists in both GroveDBs with different elements
+    Changed {
+        /// Path of the subtree holding the entry
+        path: Vec<Vec<u8>>,
+        /// Key of the entry
+        key: Vec<u8>,
+        /// Element of this GroveDB
+        old_element: Element,
+        /// Element of the other GroveDB
+        new_element: Element,
+    },
+}
+
+impl DiffEntry {
+    /// Returns the path of the subtree holding the entry
+    pub fn path(&self) -> &[Vec<u8>] {
+        match self {
+            DiffEntry::Added { path, .. }
+            | DiffEntry::Removed { path, .. }
+            | DiffEntry::Changed { path, .. } => path,
+        }
+    }
+
+    /// Returns the key of the entry
+    pub fn key(&self) -> &[u8] {
+        match self {
+            DiffEntry::Added { key, .. }
+            | DiffEntry::Removed { key, .. }
+            | DiffEntry::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location: String = self
+            .path()
+            .iter()
+            .map(|segment| segment.as_slice())
+            .chain([self.key()])
+            .map(|segment| format!("/{}", hex_to_ascii(segment)))
+            .collect();
+        match self {
+            DiffEntry::Added { element, .. } => write!(f, "+ {}: {}", location, element),
+            DiffEntry::Removed { element, .. } => write!(f, "- {}: {}", location, element),
+            DiffEntry::Changed {
+                old_element,
+                new_element,
+                ..
+            } => write!(f, "~ {}: {} -> {}", location, old_element, new_element),
+        }
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Returns the entries differing between this GroveDB and `other`, in key
+    /// order within every subtree. Entries only `other` has are reported as
+    /// added, entries only this GroveDB has as removed. A subtree existing in
+    /// only one of the GroveDBs is reported by its tree element alone, without
+    /// its contents. Only subtrees whose root hashes differ are descended into.
+    pub fn diff(
+        &self,
+        other: &GroveDb,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<DiffEntry>, Error> {
+        check_grovedb_v0!("diff", grove_version.grovedb_versions.operations.get.diff);
+        let mut entries = vec![];
+        self.diff_subtrees(other, &SubtreePath::empty(), &mut entries, grove_version)?;
+        Ok(entries)
+    }
+
+    // Appends the entries differing in the subtrees at the given path, and in
+    // their descendants, to entries
+    fn diff_subtrees<B: AsRef<[u8]>>(
+        &self,
+        other: &GroveDb,
+        path: &SubtreePath<B>,
+        entries: &mut Vec<DiffEntry>,
+        grove_version: &GroveVersion,
+    ) -> Result<(), Error> {
+        let merk = self
+            .open_non_transactional_merk_at_path(path.clone(), None, grove_version)
+            .unwrap()?;
+        let other_merk = other
+            .open_non_transactional_merk_at_path(path.clone(), None, grove_version)
+            .unwrap()?;
+        if merk.root_hash().unwrap() == other_merk.root_hash().unwrap() {
+            return Ok(());
+        }
+
+        let child_keys = diff_merks(&merk, &other_merk, path, entries, grove_version)?;
+        drop(merk);
+        drop(other_merk);
+
+        for key in child_keys {
+            let child_path = path.derive_owned_with_child(key);
+            self.diff_subtrees(
+                other,
+                &SubtreePath::from(&child_path),
+                entries,
+                grove_version,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Compares the elements of both merks in key order, appending the differing
+// ones to entries. Returns the keys of the child subtrees existing in both
+// merks, which are compared by their own contents rather than by their
+// elements. Tree elements differing in tree type or flags are reported as
+// changed as well.
+fn diff_merks<'db, B: AsRef<[u8]>, S: StorageContext<'db>>(
+    merk: &Merk<S>,
+    other_merk: &Merk<S>,
+    path: &SubtreePath<B>,
+    entries: &mut Vec<DiffEntry>,
+    grove_version: &GroveVersion,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let mut all_query = Query::new();
+    all_query.insert_all();
+
+    let mut iterator = KVIterator::new(merk.storage.raw_iter(), &all_query).unwrap();
+    let mut other_iterator = KVIterator::new(other_merk.storage.raw_iter(), &all_query).unwrap();
+    let mut next = next_element(&mut iterator, grove_version)?;
+    let mut other_next = next_element(&mut other_iterator, grove_version)?;
+
+    let mut child_keys = vec![];
+    loop {
+        let ordering = match (&next, &other_next) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((key, _)), Some((other_key, _))) => key.cmp(other_key),
+        };
+        match ordering {
+            Ordering::Less => {
+                let (key, element) = next.take().expect("element must exist");
+                entries.push(DiffEntry::Removed {
+                    path: path.to_vec(),
+                    key,
+                    element,
+                });
+                next = next_element(&mut iterator, grove_version)?;
+            }
+            Ordering::Greater => {
+                let (key, element) = other_next.take().expect("element must exist");
+                entries.push(DiffEntry::Added {
+                    path: path.to_vec(),
+                    key,
+                    element,
+                });
+                other_next = next_element(&mut other_iterator, grove_version)?;
+            }
+            Ordering::Equal => {
+                let (key, old_element) = next.take().expect("element must exist");
+                let (_, new_element) = other_next.take().expect("element must exist");
+                if old_element.is_any_tree() && new_element.is_any_tree() {
+                    // The root key and sum of a tree element follow from the
+                    // contents of its subtree, which are compared separately,
+                    // so only the tree type and flags are compared here
+                    if old_element.is_sum_tree() != new_element.is_sum_tree()
+                        || old_element.get_flags() != new_element.get_flags()
+                    {
+                        entries.push(DiffEntry::Changed {
+                            path: path.to_vec(),
+                            key: key.clone(),
+                            old_element,
+                            new_element,
+                        });
+                    }
+                    child_keys.push(key);
+                } else if old_element != new_element {
+                    entries.push(DiffEntry::Changed {
+                        path: path.to_vec(),
+                        key,
+                        old_element,
+                        new_element,
+                    });
+                }
+                next = next_element(&mut iterator, grove_version)?;
+                other_next = next_element(&mut other_iterator, grove_version)?;
+            }
+        }
+    }
+    Ok(child_keys)
+}
+
+fn next_element<I: RawIterator>(
+    iterator: &mut KVIterator<I>,
+    grove_version: &GroveVersion,
+) -> Result<Option<(Vec<u8>, Element)>, Error> {
+    iterator
+        .next_kv()
+        .unwrap()
+        .map(|(key, value)| Ok((key, raw_decode(&value, grove_version)?)))
+        .transpose()
+}
@@ -6,6 +6,10 @@ mod average_case;
 mod query;
 #[cfg(feature = "full")]
 pub use query::QueryItemOrSumReturnType;
+#[cfg(feature = "full")]
+mod query_iterator;
+#[cfg(feature = "full")]
+pub use query_iterator::{QueryItemValueIterator, QueryIterator};
 #[cfg(feature = "estimated_costs")]
 mod worst_case;
 
@@ -19,9 +19,11 @@ use crate::{
 };
 #[cfg(feature = "full")]
 use crate::{
-    query_result_type::{QueryResultElement, QueryResultElements, QueryResultType},
+    query_result_type::{
+        QueryAggregates, QueryResultElement, QueryResultElements, QueryResultType,
+    },
     reference_path::ReferencePathType,
-    Element, Error, GroveDb, PathQuery, TransactionArg,
+    Element, Error, GroveDb, PathQuery, QueryCursor, TransactionArg,
 };
 
 #[cfg(feature = "full")]
@@ -177,7 +179,7 @@ where {
         }
     }
 
-    fn follow_element(
+    pub(crate) fn follow_element(
         &self,
         element: Element,
         allow_cache: bool,
@@ -257,17 +259,122 @@ where {
             )
         );
 
-        let results_wrapped = elements
+        let results_wrapped =
+            self.follow_elements(elements, allow_cache, &mut cost, transaction, grove_version);
+
+        let results = cost_return_on_error_no_add!(&cost, results_wrapped);
+        Ok((results, skipped)).wrap_with_cost(cost)
+    }
+
+    /// Returns the result set after applying a path query, and the cursor of
+    /// the last element if the limit of the path query was reached. The next
+    /// page is the result set of the path query `PathQuery::after_cursor`
+    /// returns for the cursor.
+    pub fn query_with_cursor(
+        &self,
+        path_query: &PathQuery,
+        allow_cache: bool,
+        decrease_limit_on_range_with_no_sub_elements: bool,
+        error_if_intermediate_path_tree_not_present: bool,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> CostResult<(QueryResultElements, u16, Option<QueryCursor>), Error> {
+        check_grovedb_v0_with_cost!(
+            "query_with_cursor",
+            grove_version
+                .grovedb_versions
+                .operations
+                .query
+                .query_with_cursor
+        );
+        let mut cost = OperationCost::default();
+
+        let (elements, skipped, cursor) = cost_return_on_error!(
+            &mut cost,
+            self.query_raw_with_cursor(
+                path_query,
+                allow_cache,
+                decrease_limit_on_range_with_no_sub_elements,
+                error_if_intermediate_path_tree_not_present,
+                result_type,
+                transaction,
+                grove_version
+            )
+        );
+
+        let results_wrapped =
+            self.follow_elements(elements, allow_cache, &mut cost, transaction, grove_version);
+
+        let results = cost_return_on_error_no_add!(&cost, results_wrapped);
+        Ok((results, skipped, cursor)).wrap_with_cost(cost)
+    }
+
+    // Follows the references among query results to the elements they point to
+    fn follow_elements(
+        &self,
+        elements: QueryResultElements,
+        allow_cache: bool,
+        cost: &mut OperationCost,
+        transaction: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> Result<QueryResultElements, Error> {
+        elements
             .into_iterator()
             .map(|result_item| {
                 result_item.map_element(|element| {
-                    self.follow_element(element, allow_cache, &mut cost, transaction, grove_version)
+                    self.follow_element(element, allow_cache, cost, transaction, grove_version)
                 })
             })
-            .collect::<Result<Vec<QueryResultElement>, Error>>();
+            .collect::<Result<Vec<QueryResultElement>, Error>>()
+            .map(|elements| QueryResultElements { elements })
+    }
 
-        let results = cost_return_on_error_no_add!(&cost, results_wrapped);
-        Ok((QueryResultElements { elements: results }, skipped)).wrap_with_cost(cost)
+    // Returns the value of an item, or of the item a reference points to. Sum
+    // items are encoded as var vec.
+    pub(crate) fn item_value(
+        &self,
+        element: Element,
+        allow_cache: bool,
+        cost: &mut OperationCost,
+        transaction: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        match element {
+            Element::Reference(reference_path, ..) => {
+                match reference_path {
+                    ReferencePathType::AbsolutePathReference(absolute_path) => {
+                        // While `map` on iterator is lazy, we should accumulate costs
+                        // even if `collect` will
+                        // end in `Err`, so we'll use
+                        // external costs accumulator instead of
+                        // returning costs from `map` call.
+                        let maybe_item = self
+                            .follow_reference(
+                                absolute_path.as_slice().into(),
+                                allow_cache,
+                                transaction,
+                                grove_version,
+                            )
+                            .unwrap_add_cost(cost)?;
+
+                        match maybe_item {
+                            Element::Item(item, _) => Ok(item),
+                            Element::SumItem(item, _) => Ok(item.encode_var_vec()),
+                            _ => Err(Error::InvalidQuery("the reference must result in an item")),
+                        }
+                    }
+                    _ => Err(Error::CorruptedCodeExecution(
+                        "reference after query must have absolute paths",
+                    )),
+                }
+            }
+            Element::Item(item, _) => Ok(item),
+            Element::SumItem(item, _) => Ok(item.encode_var_vec()),
+            Element::Tree(..) | Element::SumTree(..) => Err(Error::InvalidQuery(
+                "path_queries can only refer to items and references",
+            )),
+        }
     }
 
     /// Queries the backing store and returns element items by their value,
@@ -308,43 +415,7 @@ where {
             .into_iterator()
             .map(|result_item| match result_item {
                 QueryResultElement::ElementResultItem(element) => {
-                    match element {
-                        Element::Reference(reference_path, ..) => {
-                            match reference_path {
-                                ReferencePathType::AbsolutePathReference(absolute_path) => {
-                                    // While `map` on iterator is lazy, we should accumulate costs
-                                    // even if `collect` will
-                                    // end in `Err`, so we'll use
-                                    // external costs accumulator instead of
-                                    // returning costs from `map` call.
-                                    let maybe_item = self
-                                        .follow_reference(
-                                            absolute_path.as_slice().into(),
-                                            allow_cache,
-                                            transaction,
-                                            grove_version,
-                                        )
-                                        .unwrap_add_cost(&mut cost)?;
-
-                                    match maybe_item {
-                                        Element::Item(item, _) => Ok(item),
-                                        Element::SumItem(item, _) => Ok(item.encode_var_vec()),
-                                        _ => Err(Error::InvalidQuery(
-                                            "the reference must result in an item",
-                                        )),
-                                    }
-                                }
-                                _ => Err(Error::CorruptedCodeExecution(
-                                    "reference after query must have absolute paths",
-                                )),
-                            }
-                        }
-                        Element::Item(item, _) => Ok(item),
-                        Element::SumItem(item, _) => Ok(item.encode_var_vec()),
-                        Element::Tree(..) | Element::SumTree(..) => Err(Error::InvalidQuery(
-                            "path_queries can only refer to items and references",
-                        )),
-                    }
+                    self.item_value(element, allow_cache, &mut cost, transaction, grove_version)
                 }
                 _ => Err(Error::CorruptedCodeExecution(
                     "query returned incorrect result type",
@@ -538,6 +609,63 @@ where {
         Ok((results, skipped)).wrap_with_cost(cost)
     }
 
+    /// Returns the aggregates of the result set of a path query, computed
+    /// while walking the query without collecting the results. References are
+    /// followed to the items they point to.
+    pub fn query_aggregates(
+        &self,
+        path_query: &PathQuery,
+        allow_cache: bool,
+        decrease_limit_on_range_with_no_sub_elements: bool,
+        error_if_intermediate_path_tree_not_present: bool,
+        transaction: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> CostResult<QueryAggregates, Error> {
+        check_grovedb_v0_with_cost!(
+            "query_aggregates",
+            grove_version
+                .grovedb_versions
+                .operations
+                .query
+                .query_aggregates
+        );
+        let mut cost = OperationCost::default();
+
+        let mut iterator = cost_return_on_error_no_add!(
+            &cost,
+            self.query_raw_iter(
+                path_query,
+                allow_cache,
+                decrease_limit_on_range_with_no_sub_elements,
+                error_if_intermediate_path_tree_not_present,
+                QueryResultType::QueryElementResultType,
+                transaction,
+                grove_version,
+            )
+        );
+
+        let mut aggregates = QueryAggregates::default();
+        let result = iterator.by_ref().try_for_each(|result_item| {
+            let element = match result_item? {
+                QueryResultElement::ElementResultItem(element) => element,
+                _ => {
+                    return Err(Error::CorruptedCodeExecution(
+                        "query returned incorrect result type",
+                    ))
+                }
+            };
+            let element = if let Element::Reference(..) = element {
+                self.follow_element(element, allow_cache, &mut cost, transaction, grove_version)?
+            } else {
+                element
+            };
+            aggregates.add_result(&element)
+        });
+        cost += iterator.cost().clone();
+
+        result.map(|_| aggregates).wrap_with_cost(cost)
+    }
+
     /// Returns result elements and number of elements skipped given path query
     pub fn query_raw(
         &self,
@@ -568,6 +696,41 @@ where {
         )
     }
 
+    /// Returns result elements, the number of elements skipped and the cursor
+    /// of the last element if the limit was reached, given path query
+    pub fn query_raw_with_cursor(
+        &self,
+        path_query: &PathQuery,
+        allow_cache: bool,
+        decrease_limit_on_range_with_no_sub_elements: bool,
+        error_if_intermediate_path_tree_not_present: bool,
+        result_type: QueryResultType,
+        transaction: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> CostResult<(QueryResultElements, u16, Option<QueryCursor>), Error> {
+        check_grovedb_v0_with_cost!(
+            "query_raw_with_cursor",
+            grove_version
+                .grovedb_versions
+                .operations
+                .query
+                .query_raw_with_cursor
+        );
+        Element::get_path_query_with_cursor(
+            &self.db,
+            path_query,
+            QueryOptions {
+                allow_get_raw: true,
+                allow_cache,
+                decrease_limit_on_range_with_no_sub_elements,
+                error_if_intermediate_path_tree_not_present,
+            },
+            result_type,
+            transaction,
+            grove_version,
+        )
+    }
+
     /// Splits the result set of a path query by query path.
     /// If max_results is exceeded we return an error.
     pub fn query_keys_optional(
@@ -0,0 +1,604 @@
+//! Streaming queries
+//!
+//! `QueryIterator` yields the results of a path query one at a time instead
+//! of collecting them into `QueryResultElements`. It keeps one `KVIterator`
+//! per subtree being walked, from the subtree of the path query down to the
+//! subtree of the current subquery, so its memory use depends on the depth of
+//! the query and not on the number of results. Limit, offset, subqueries and
+//! subquery paths behave as in `GroveDb::query_raw`.
+//!
+//! `Element::iterator` and its `ElementsIterator` can't walk a level: they
+//! only go forward over a whole subtree, from its first key, while a query
+//! seeks to every one of its items and may go right to left. That is what
+//! merk's `KVIterator` does, over the raw iterator of a storage context with or
+//! without a transaction (`SubtreeRawIterator`, as both have different types).
+//! Neither can the results be produced by `Element::get_path_query`, which
+//! descends into subqueries recursively and collects every result before
+//! returning. The limit, offset and subquery path handling of its push
+//! functions is mirrored here instead, one result at a time.
+
+use grovedb_costs::{CostContext, OperationCost};
+use grovedb_merk::{proofs::Query, KVIterator};
+use grovedb_path::SubtreePath;
+use grovedb_storage::{
+    rocksdb_storage::{PrefixedRocksDbStorageContext, PrefixedRocksDbTransactionContext},
+    RawIterator, Storage, StorageContext,
+};
+use grovedb_version::{check_grovedb_v0, error::GroveVersionError, version::GroveVersion};
+
+use crate::{
+    element::{helpers::raw_decode, QueryOptions},
+    query::subquery_branch_for_key,
+    query_result_type::{QueryResultElement, QueryResultType},
+    Element, Error, GroveDb, PathQuery, TransactionArg,
+};
+
+// Raw iterator over a subtree, with or without a transaction
+enum SubtreeRawIterator<'db> {
+    NonTransactional(<PrefixedRocksDbStorageContext<'db> as StorageContext<'db>>::RawIterator),
+    Transactional(<PrefixedRocksDbTransactionContext<'db> as StorageContext<'db>>::RawIterator),
+}
+
+impl<'db> RawIterator for SubtreeRawIterator<'db> {
+    fn seek_to_first(&mut self) -> CostContext<()> {
+        match self {
+            SubtreeRawIterator::NonTransactional(iter) => iter.seek_to_first(),
+            SubtreeRawIterator::Transactional(iter) => iter.seek_to_first(),
+        }
+    }
+
+    fn seek_to_last(&mut self) -> CostContext<()> {
+        match self {
+            SubtreeRawIterator::NonTransactional(iter) => iter.seek_to_last(),
+            SubtreeRawIterator::Transactional(iter) => iter.seek_to_last(),
+        }
+    }
+
+    fn seek<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
+        match self {
+            SubtreeRawIterator::NonTransactional(iter) => iter.seek(key),
+            SubtreeRawIterator::Transactional(iter) => iter.seek(key),
+        }
+    }
+
+    fn seek_for_prev<K: AsRef<[u8]>>(&mut self, key: K) -> CostContext<()> {
+        match self {
+            SubtreeRawIterator::NonTransactional(iter) => iter.seek_for_prev(key),
+            SubtreeRawIterator::Transactional(iter) => iter.seek_for_prev(key),
+        }
+    }
+
+    fn next(&mut self) -> CostContext<()> {
+        match self {
+            SubtreeRawIterator::NonTransactional(iter) => iter.next(),
+            SubtreeRawIterator::Transactional(iter) => iter.next(),
+        }
+    }
+
+    fn prev(&mut self) -> CostContext<()> {
+        match self {
+            SubtreeRawIterator::NonTransactional(iter) => iter.prev(),
+            SubtreeRawIterator::Transactional(iter) => iter.prev(),
+        }
+    }
+
+    fn value(&self) -> CostContext<Option<&[u8]>> {
+        match self {
+            SubtreeRawIterator::NonTransactional(iter) => iter.value(),
+            SubtreeRawIterator::Transactional(iter) => iter.value(),
+        }
+    }
+
+    fn key(&self) -> CostContext<Option<&[u8]>> {
+        match self {
+            SubtreeRawIterator::NonTransactional(iter) => iter.key(),
+            SubtreeRawIterator::Transactional(iter) => iter.key(),
+        }
+    }
+
+    fn valid(&self) -> CostContext<bool> {
+        match self {
+            SubtreeRawIterator::NonTransactional(iter) => iter.valid(),
+            SubtreeRawIterator::Transactional(iter) => iter.valid(),
+        }
+    }
+}
+
+// Subtree being walked by a query iterator
+struct QueryLevel<'db> {
+    path: Vec<Vec<u8>>,
+    query: &'db Query,
+    kv_iterator: KVIterator<'db, SubtreeRawIterator<'db>>,
+    // Limit left for this subtree, as in `Element::get_path_query`
+    limit: Option<u16>,
+    // Number of results yielded from this subtree and the subtrees below it
+    result_count: u16,
+}
+
+/// Iterator over the results of a path query, walking subtrees and
+/// subqueries lazily. Costs are accumulated as it goes, see `cost`.
+pub struct QueryIterator<'db> {
+    db: &'db GroveDb,
+    transaction: TransactionArg<'db, 'db>,
+    query_options: QueryOptions,
+    result_type: QueryResultType,
+    follow_references: bool,
+    levels: Vec<QueryLevel<'db>>,
+    offset: Option<u16>,
+    skipped: u16,
+    cost: OperationCost,
+    grove_version: &'db GroveVersion,
+}
+
+impl<'db> QueryIterator<'db> {
+    fn new(
+        db: &'db GroveDb,
+        path_query: &'db PathQuery,
+        query_options: QueryOptions,
+        result_type: QueryResultType,
+        follow_references: bool,
+        transaction: TransactionArg<'db, 'db>,
+        grove_version: &'db GroveVersion,
+    ) -> Result<Self, Error> {
+        let mut iterator = QueryIterator {
+            db,
+            transaction,
+            query_options,
+            result_type,
+            follow_references,
+            levels: vec![],
+            offset: path_query.query.offset,
+            skipped: 0,
+            cost: OperationCost::default(),
+            grove_version,
+        };
+        iterator.push_level(
+            path_query.path.clone(),
+            &path_query.query.query,
+            path_query.query.limit,
+        )?;
+        Ok(iterator)
+    }
+
+    /// Returns the costs of the results yielded so far
+    pub fn cost(&self) -> &OperationCost {
+        &self.cost
+    }
+
+    /// Returns the number of results skipped because of the offset so far
+    pub fn skipped(&self) -> u16 {
+        self.skipped
+    }
+
+    // Enters the subtree at the path. As in `query_raw`, a missing subtree
+    // has no results unless intermediate trees are required to be present.
+    fn push_level(
+        &mut self,
+        path: Vec<Vec<u8>>,
+        query: &'db Query,
+        limit: Option<u16>,
+    ) -> Result<(), Error> {
+        self.check_parent_element(&path)?;
+        let subtree_path: SubtreePath<_> = path.as_slice().into();
+        let raw_iterator = match self.transaction {
+            Some(transaction) => self
+                .db
+                .db
+                .get_transactional_storage_context(subtree_path, None, transaction)
+                .map(|context| SubtreeRawIterator::Transactional(context.raw_iter())),
+            None => self
+                .db
+                .db
+                .get_storage_context(subtree_path, None)
+                .map(|context| SubtreeRawIterator::NonTransactional(context.raw_iter())),
+        }
+        .unwrap_add_cost(&mut self.cost);
+        let kv_iterator = KVIterator::new(raw_iterator, query).unwrap_add_cost(&mut self.cost);
+        self.levels.push(QueryLevel {
+            path,
+            query,
+            kv_iterator,
+            limit,
+            result_count: 0,
+        });
+        Ok(())
+    }
+
+    // Checks the element of the subtree at the path in its parent subtree,
+    // failing like `storage_context_with_parent_optional_tx`
+    fn check_parent_element(&mut self, path: &[Vec<u8>]) -> Result<(), Error> {
+        let Some((parent_key, parent_path)) = path.split_last() else {
+            return Ok(());
+        };
+        let parent_path: SubtreePath<_> = parent_path.into();
+        let element = match self.transaction {
+            Some(transaction) => {
+                let parent_storage = self
+                    .db
+                    .db
+                    .get_transactional_storage_context(parent_path, None, transaction)
+                    .unwrap_add_cost(&mut self.cost);
+                Element::get_from_storage(&parent_storage, parent_key, self.grove_version)
+            }
+            None => {
+                let parent_storage = self
+                    .db
+                    .db
+                    .get_storage_context(parent_path, None)
+                    .unwrap_add_cost(&mut self.cost);
+                Element::get_from_storage(&parent_storage, parent_key, self.grove_version)
+            }
+        }
+        .unwrap_add_cost(&mut self.cost);
+        match element {
+            Ok(Element::Tree(..)) | Ok(Element::SumTree(..)) => Ok(()),
+            Ok(_) => Err(Error::CorruptedData("parent is not a tree".to_owned())),
+            Err(_)
+                if !self
+                    .query_options
+                    .error_if_intermediate_path_tree_not_present =>
+            {
+                Ok(())
+            }
+            Err(e) => Err(Error::PathParentLayerNotFound(format!(
+                "could not get key for parent of subtree: {}",
+                e
+            ))),
+        }
+    }
+
+    // Leaves the deepest subtree. An empty subtree counts as one result for
+    // the limit of the subtree above it, unless configured otherwise.
+    fn pop_level(&mut self) {
+        let Some(level) = self.levels.pop() else {
+            return;
+        };
+        if level.result_count == 0
+            && self
+                .query_options
+                .decrease_limit_on_range_with_no_sub_elements
+        {
+            if let Some(parent) = self.levels.last_mut() {
+                parent.limit = parent.limit.map(|limit| limit.saturating_sub(1));
+            }
+        }
+    }
+
+    // Visits the next key of the deepest subtree, returning the result it
+    // yields if any
+    fn visit(
+        &mut self,
+        key: Vec<u8>,
+        element: Element,
+    ) -> Result<Option<QueryResultElement>, Error> {
+        let level = self
+            .levels
+            .last()
+            .expect("visited keys belong to a subtree");
+        let query = level.query;
+        let mut path = level.path.clone();
+        if !element.is_any_tree() {
+            return self.push_result(path, key, element);
+        }
+
+        let subquery_branch = subquery_branch_for_key(query, &key);
+        match (&subquery_branch.subquery_path, &subquery_branch.subquery) {
+            (subquery_path, Some(subquery)) => {
+                path.push(key);
+                path.extend(subquery_path.iter().flatten().cloned());
+                let limit = level.limit;
+                self.push_level(path, subquery, limit)?;
+                Ok(None)
+            }
+            (Some(subquery_path), None) => {
+                let Some((subquery_key, subquery_path_front)) = subquery_path.split_last() else {
+                    return Err(Error::CorruptedCodeExecution(
+                        "subquery_paths can not be empty",
+                    ));
+                };
+                // With a value filter the element is needed to know whether it
+                // counts toward the offset
+                if self.offset.unwrap_or(0) > 0 && query.value_filter.is_none() {
+                    self.skip();
+                    return Ok(None);
+                }
+                path.push(key);
+                path.extend(subquery_path_front.iter().cloned());
+                match self.get_element(&path, subquery_key) {
+                    Ok(element) => self.push_result(path, subquery_key.clone(), element),
+                    Err(Error::PathKeyNotFound(_))
+                        if !self
+                            .query_options
+                            .error_if_intermediate_path_tree_not_present =>
+                    {
+                        Ok(None)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            (None, None) if self.query_options.allow_get_raw => {
+                self.push_result(path, key, element)
+            }
+            (None, None) => Err(Error::InvalidPath(
+                "you must provide a subquery or a subquery_path when interacting with a Tree of \
+                 trees"
+                    .to_owned(),
+            )),
+        }
+    }
+
+    // Reads the element at the path and key, with an absolute reference if it
+    // is a reference
+    fn get_element(&mut self, path: &[Vec<u8>], key: &[u8]) -> Result<Element, Error> {
+        let subtree_path: SubtreePath<_> = path.into();
+        let value = match self.transaction {
+            Some(transaction) => self
+                .db
+                .db
+                .get_transactional_storage_context(subtree_path, None, transaction)
+                .unwrap_add_cost(&mut self.cost)
+                .get(key),
+            None => self
+                .db
+                .db
+                .get_storage_context(subtree_path, None)
+                .unwrap_add_cost(&mut self.cost)
+                .get(key),
+        }
+        .unwrap_add_cost(&mut self.cost)?
+        .ok_or_else(|| {
+            Error::PathKeyNotFound(format!(
+                "key not found in Merk for get: {}",
+                hex::encode(key)
+            ))
+        })?;
+        let element = raw_decode(&value, self.grove_version)?;
+        let path_slices: Vec<&[u8]> = path.iter().map(|segment| segment.as_slice()).collect();
+        element.convert_if_reference_to_absolute_reference(&path_slices, Some(key))
+    }
+
+    fn skip(&mut self) {
+        if let Some(offset) = self.offset.as_mut() {
+            *offset -= 1;
+            self.skipped += 1;
+        }
+    }
+
+    // Turns an element into a result, unless it is filtered out by the value
+    // filter of the deepest subtree's query or skipped because of the offset
+    fn push_result(
+        &mut self,
+        path: Vec<Vec<u8>>,
+        key: Vec<u8>,
+        element: Element,
+    ) -> Result<Option<QueryResultElement>, Error> {
+        let value_filter = self
+            .levels
+            .last()
+            .and_then(|level| level.query.value_filter.as_ref());
+        if let Some(value_filter) = value_filter {
+            if !element.matches_value_filter(value_filter) {
+                return Ok(None);
+            }
+        }
+        let path_slices: Vec<&[u8]> = path.iter().map(|segment| segment.as_slice()).collect();
+        let element = element
+            .convert_if_reference_to_absolute_reference(&path_slices, Some(key.as_slice()))?;
+        if self.offset.unwrap_or(0) > 0 {
+            self.skip();
+            return Ok(None);
+        }
+        for level in self.levels.iter_mut() {
+            level.limit = level.limit.map(|limit| limit.saturating_sub(1));
+            level.result_count += 1;
+        }
+        let element = if self.follow_references {
+            self.db.follow_element(
+                element,
+                self.query_options.allow_cache,
+                &mut self.cost,
+                self.transaction,
+                self.grove_version,
+            )?
+        } else {
+            element
+        };
+        Ok(Some(match self.result_type {
+            QueryResultType::QueryElementResultType => {
+                QueryResultElement::ElementResultItem(element)
+            }
+            QueryResultType::QueryKeyElementPairResultType => {
+                QueryResultElement::KeyElementPairResultItem((key, element))
+            }
+            QueryResultType::QueryPathKeyElementTrioResultType => {
+                QueryResultElement::PathKeyElementTrioResultItem((path, key, element))
+            }
+        }))
+    }
+}
+
+impl<'db> Iterator for QueryIterator<'db> {
+    type Item = Result<QueryResultElement, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let level = self.levels.last_mut()?;
+            if level.limit == Some(0) {
+                self.pop_level();
+                continue;
+            }
+            let Some((key, value)) = level.kv_iterator.next_kv().unwrap_add_cost(&mut self.cost)
+            else {
+                self.pop_level();
+                continue;
+            };
+            let result =
+                raw_decode(&value, self.grove_version).and_then(|element| self.visit(key, element));
+            match result {
+                Ok(Some(result)) => return Some(Ok(result)),
+                Ok(None) => {}
+                Err(e) => {
+                    // Nothing is yielded after an error
+                    self.levels.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Iterator over the item values of the results of a path query, as returned
+/// by `GroveDb::query_item_value`
+pub struct QueryItemValueIterator<'db> {
+    query_iterator: QueryIterator<'db>,
+}
+
+impl<'db> QueryItemValueIterator<'db> {
+    /// Returns the costs of the values yielded so far
+    pub fn cost(&self) -> &OperationCost {
+        self.query_iterator.cost()
+    }
+
+    /// Returns the number of results skipped because of the offset so far
+    pub fn skipped(&self) -> u16 {
+        self.query_iterator.skipped()
+    }
+}
+
+impl<'db> Iterator for QueryItemValueIterator<'db> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let QueryIterator {
+            db,
+            transaction,
+            query_options,
+            grove_version,
+            ..
+        } = self.query_iterator;
+        let result = match self.query_iterator.next()? {
+            Ok(QueryResultElement::ElementResultItem(element)) => db.item_value(
+                element,
+                query_options.allow_cache,
+                &mut self.query_iterator.cost,
+                transaction,
+                grove_version,
+            ),
+            Ok(_) => Err(Error::CorruptedCodeExecution(
+                "query returned incorrect result type",
+            )),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            self.query_iterator.levels.clear();
+        }
+        Some(result)
+    }
+}
+
+impl GroveDb {
+    /// Returns an iterator over the result set of a path query, following
+    /// references like `query`
+    pub fn query_iter<'db>(
+        &'db self,
+        path_query: &'db PathQuery,
+        allow_cache: bool,
+        decrease_limit_on_range_with_no_sub_elements: bool,
+        error_if_intermediate_path_tree_not_present: bool,
+        result_type: QueryResultType,
+        transaction: TransactionArg<'db, 'db>,
+        grove_version: &'db GroveVersion,
+    ) -> Result<QueryIterator<'db>, Error> {
+        check_grovedb_v0!(
+            "query_iter",
+            grove_version.grovedb_versions.operations.query.query_iter
+        );
+        QueryIterator::new(
+            self,
+            path_query,
+            QueryOptions {
+                allow_get_raw: true,
+                allow_cache,
+                decrease_limit_on_range_with_no_sub_elements,
+                error_if_intermediate_path_tree_not_present,
+            },
+            result_type,
+            true,
+            transaction,
+            grove_version,
+        )
+    }
+
+    /// Returns an iterator over the result elements of a path query, like
+    /// `query_raw`
+    pub fn query_raw_iter<'db>(
+        &'db self,
+        path_query: &'db PathQuery,
+        allow_cache: bool,
+        decrease_limit_on_range_with_no_sub_elements: bool,
+        error_if_intermediate_path_tree_not_present: bool,
+        result_type: QueryResultType,
+        transaction: TransactionArg<'db, 'db>,
+        grove_version: &'db GroveVersion,
+    ) -> Result<QueryIterator<'db>, Error> {
+        check_grovedb_v0!(
+            "query_raw_iter",
+            grove_version
+                .grovedb_versions
+                .operations
+                .query
+                .query_raw_iter
+        );
+        QueryIterator::new(
+            self,
+            path_query,
+            QueryOptions {
+                allow_get_raw: true,
+                allow_cache,
+                decrease_limit_on_range_with_no_sub_elements,
+                error_if_intermediate_path_tree_not_present,
+            },
+            result_type,
+            false,
+            transaction,
+            grove_version,
+        )
+    }
+
+    /// Returns an iterator over the item values of a path query, like
+    /// `query_item_value`
+    pub fn query_item_value_iter<'db>(
+        &'db self,
+        path_query: &'db PathQuery,
+        allow_cache: bool,
+        decrease_limit_on_range_with_no_sub_elements: bool,
+        error_if_intermediate_path_tree_not_present: bool,
+        transaction: TransactionArg<'db, 'db>,
+        grove_version: &'db GroveVersion,
+    ) -> Result<QueryItemValueIterator<'db>, Error> {
+        check_grovedb_v0!(
+            "query_item_value_iter",
+            grove_version
+                .grovedb_versions
+                .operations
+                .query
+                .query_item_value_iter
+        );
+        Ok(QueryItemValueIterator {
+            query_iterator: QueryIterator::new(
+                self,
+                path_query,
+                QueryOptions {
+                    allow_get_raw: true,
+                    allow_cache,
+                    decrease_limit_on_range_with_no_sub_elements,
+                    error_if_intermediate_path_tree_not_present,
+                },
+                QueryResultType::QueryElementResultType,
+                false,
+                transaction,
+                grove_version,
+            )?,
+        })
+    }
+}
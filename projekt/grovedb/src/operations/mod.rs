@@ -5,6 +5,8 @@ pub(crate) mod auxiliary;
 #[cfg(feature = "full")]
 pub mod delete;
 #[cfg(feature = "full")]
+pub mod diff;
+#[cfg(feature = "full")]
 pub(crate) mod get;
 #[cfg(feature = "full")]
 pub mod insert;
@@ -121,6 +121,13 @@ impl GroveDb {
             .wrap_with_cost(cost);
         }
 
+        if path_query.query.query.has_value_filter() {
+            return Err(Error::InvalidQuery(
+                "proved path queries can not have value filters",
+            ))
+            .wrap_with_cost(cost);
+        }
+
         #[cfg(feature = "proof_debug")]
         {
             // we want to query raw because we want the references to not be resolved at
@@ -22,7 +22,7 @@ use crate::{
         util::{ProvedPathKeyOptionalValue, ProvedPathKeyValues},
         GroveDBProof, GroveDBProofV0, LayerProof, ProveOptions,
     },
-    query_result_type::PathKeyOptionalElementTrio,
+    query_result_type::{PathKeyOptionalElementTrio, QueryAggregates},
     Element, Error, GroveDb, PathQuery,
 };
 
@@ -407,6 +407,34 @@ impl GroveDb {
         )
     }
 
+    /// Verifies a proof of the path query, as generated by `prove_query`, and
+    /// returns the root hash with the aggregates of the proved results, the
+    /// same as `query_aggregates` returns for the path query
+    pub fn verify_query_aggregates(
+        proof: &[u8],
+        query: &PathQuery,
+        grove_version: &GroveVersion,
+    ) -> Result<(CryptoHash, QueryAggregates), Error> {
+        check_grovedb_v0!(
+            "verify_query_aggregates",
+            grove_version
+                .grovedb_versions
+                .operations
+                .proof
+                .verify_query_aggregates
+        );
+        let (root_hash, results) = Self::verify_query(proof, query, grove_version)?;
+        let mut aggregates = QueryAggregates::default();
+        for (_, _, element) in results {
+            // References were replaced by the elements they point to when
+            // proving
+            if let Some(element) = element {
+                aggregates.add_result(&element)?;
+            }
+        }
+        Ok((root_hash, aggregates))
+    }
+
     pub fn verify_subset_query(
         proof: &[u8],
         query: &PathQuery,
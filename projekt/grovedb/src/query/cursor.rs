@@ -0,0 +1,159 @@
+//! Query cursors
+//!
+//! A query with a limit stops at the last element it visited. `QueryCursor`
+//! records the position of that element, its path and key, and
+//! `PathQuery::after_cursor` narrows a path query to the elements following
+//! it, at every level of subqueries. Unlike an offset, a cursor doesn't
+//! re-scan the elements of previous pages, and elements inserted before it in
+//! the meantime don't shift the next page. The narrowed path query is an
+//! ordinary path query, so its results can be proved and verified as well.
+
+use bincode::{Decode, Encode};
+use grovedb_merk::proofs::{
+    query::{query_item::QueryItem, SubqueryBranch},
+    Query,
+};
+use grovedb_version::{check_grovedb_v0, error::GroveVersionError, version::GroveVersion};
+use indexmap::IndexMap;
+
+use super::subquery_branch_for_key;
+use crate::{Error, PathQuery, SizedQuery};
+
+/// Position of the last element visited by a query with a limit, from which
+/// the next page of results follows
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct QueryCursor {
+    path: Vec<Vec<u8>>,
+    key: Vec<u8>,
+}
+
+impl QueryCursor {
+    /// New cursor at the element with the given path and key, e.g. the last
+    /// result of a verified proof
+    pub fn new(path: Vec<Vec<u8>>, key: Vec<u8>) -> Self {
+        QueryCursor { path, key }
+    }
+
+    /// Returns the path of the subtree holding the element
+    pub fn path(&self) -> &[Vec<u8>] {
+        &self.path
+    }
+
+    /// Returns the key of the element
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// Encodes the cursor into opaque bytes, e.g. to hand it to a client
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let config = bincode::config::standard()
+            .with_big_endian()
+            .with_no_limit();
+        bincode::encode_to_vec(self, config)
+            .map_err(|e| Error::CorruptedData(format!("unable to encode query cursor: {}", e)))
+    }
+
+    /// Decodes a cursor encoded by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let config = bincode::config::standard()
+            .with_big_endian()
+            .with_no_limit();
+        let (cursor, _) = bincode::decode_from_slice(bytes, config)
+            .map_err(|e| Error::CorruptedData(format!("unable to decode query cursor: {}", e)))?;
+        Ok(cursor)
+    }
+}
+
+impl PathQuery {
+    /// Returns the path query of the elements following the cursor, in the
+    /// direction of the query, with the same limit and no offset. The cursor
+    /// must be within the path of the path query, typically the cursor
+    /// returned by `GroveDb::query_with_cursor` for it, or the last result of
+    /// a verified proof of it.
+    pub fn after_cursor(
+        &self,
+        cursor: &QueryCursor,
+        grove_version: &GroveVersion,
+    ) -> Result<PathQuery, Error> {
+        check_grovedb_v0!(
+            "after_cursor",
+            grove_version
+                .grovedb_versions
+                .path_query_methods
+                .after_cursor
+        );
+        let Some(relative_path) = cursor.path.strip_prefix(self.path.as_slice()) else {
+            return Err(Error::InvalidParameter(
+                "cursor is not within the path of the path query",
+            ));
+        };
+        let mut position = relative_path.to_vec();
+        position.push(cursor.key.clone());
+        Ok(PathQuery::new(
+            self.path.clone(),
+            SizedQuery::new(
+                query_after_position(&self.query.query, &position),
+                self.query.limit,
+                None,
+            ),
+        ))
+    }
+}
+
+// Narrows the query to the elements following the position, given as the key
+// at the level of the query followed by the path below it. The key is kept if
+// the position is inside of its subquery, with the subquery narrowed as well.
+fn query_after_position(query: &Query, position: &[Vec<u8>]) -> Query {
+    let Some((key, below_key)) = position.split_first() else {
+        return query.clone();
+    };
+    let subquery_branch = subquery_branch_for_key(query, key);
+    let remaining_subquery_branch =
+        match (&subquery_branch.subquery_path, &subquery_branch.subquery) {
+            (Some(subquery_path), Some(subquery))
+                if below_key.len() > subquery_path.len()
+                    && below_key.starts_with(subquery_path) =>
+            {
+                Some(SubqueryBranch {
+                    subquery_path: Some(subquery_path.clone()),
+                    subquery: Some(Box::new(query_after_position(
+                        subquery,
+                        &below_key[subquery_path.len()..],
+                    ))),
+                })
+            }
+            (None, Some(subquery)) if !below_key.is_empty() => Some(SubqueryBranch {
+                subquery_path: None,
+                subquery: Some(Box::new(query_after_position(subquery, below_key))),
+            }),
+            _ => None,
+        };
+
+    let bound = match (query.left_to_right, remaining_subquery_branch.is_some()) {
+        (true, true) => QueryItem::RangeFrom(key.clone()..),
+        (true, false) => QueryItem::RangeAfter(key.clone()..),
+        (false, true) => QueryItem::RangeToInclusive(..=key.clone()),
+        (false, false) => QueryItem::RangeTo(..key.clone()),
+    };
+    let mut narrowed_query = query.clone();
+    narrowed_query.items = query
+        .items
+        .iter()
+        .filter_map(|item| item.intersect(&bound).in_both)
+        .collect();
+
+    // The narrowed subquery branch of the key must take precedence over the
+    // conditional subquery branches that also match it
+    if let Some(remaining_subquery_branch) = remaining_subquery_branch {
+        let key_item = QueryItem::Key(key.clone());
+        let mut conditional_subquery_branches = IndexMap::new();
+        conditional_subquery_branches.insert(key_item.clone(), remaining_subquery_branch);
+        for (item, subquery_branch) in query.conditional_subquery_branches.iter().flatten() {
+            if *item != key_item {
+                conditional_subquery_branches.insert(item.clone(), subquery_branch.clone());
+            }
+        }
+        narrowed_query.conditional_subquery_branches = Some(conditional_subquery_branches);
+    }
+    narrowed_query
+}
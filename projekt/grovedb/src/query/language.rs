@@ -0,0 +1,703 @@
+//! Query language
+//!
+//! Path queries can be written as text and parsed with `str::parse`, and the
+//! `Display` implementation of `PathQuery` writes any path query back as text:
+//!
+//! ```text
+//! SELECT * FROM /student WHERE KEY BETWEEN 0x01 AND 0x10 LIMIT 10 SUBQUERY /grades
+//! ```
+//!
+//! A path query is `SELECT * FROM <path>` followed, in this order, by the
+//! optional clauses `WHERE <condition>`, `ORDER BY KEY ASC|DESC`,
+//! `LIMIT <n>` and `OFFSET <n>`, then by any number of subquery branches:
+//! - `SUBQUERY <path>` sets the subquery path
+//! - `SUBQUERY (SELECT * ...)` sets the subquery, written like a path query
+//!   without `FROM`, `LIMIT` and `OFFSET`
+//! - `SUBQUERY <path> (SELECT * ...)` sets both
+//! - `SUBQUERY WHEN <key condition> ...` adds a conditional subquery branch
+//!
+//! A condition selects keys with key conditions joined by `OR`, optionally
+//! followed by `AND <value condition>`, or is a value condition alone. Key
+//! conditions are `KEY = k`, `KEY IN (k, ...)`, `KEY BETWEEN a AND b`,
+//! `KEY < b`, `KEY <= b`, `KEY > a`, `KEY >= a`, the last two optionally
+//! followed by `AND KEY < b` or `AND KEY <= b`, and `ANY KEY`. Without
+//! `WHERE` every key is selected. Value conditions are `VALUE = v`,
+//! `VALUE STARTS WITH v` and numeric comparisons such as `VALUE >= 10`.
+//!
+//! Keys and values are written as quoted text (`"alice"`, with `\"` and `\\`
+//! escapes) or as `0x` followed by hex digits. Paths are `/` separated
+//! segments, written as plain text, quoted text or hex (`/student/0x01`), and
+//! `/` alone is the root path. Keywords are case-insensitive.
+
+use std::{
+    fmt,
+    iter::Peekable,
+    str::{CharIndices, FromStr},
+};
+
+use grovedb_merk::proofs::{
+    query::{NumericComparison, QueryItem, SubqueryBranch, ValueFilter},
+    Query,
+};
+
+use crate::{Error, PathQuery, SizedQuery};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    // Keyword, in upper case
+    Word(String),
+    Bytes(Vec<u8>),
+    Integer(i128),
+    Path(Vec<Vec<u8>>),
+    // One of `*`, `(`, `)`, `,`, `=`, `<`, `<=`, `>` and `>=`
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Bytes(bytes) => write!(f, "`{}`", FormattedBytes(bytes)),
+            Token::Integer(integer) => write!(f, "`{}`", integer),
+            Token::Path(path) => write!(f, "`{}`", FormattedPath(path)),
+            Token::Symbol(symbol) => write!(f, "`{}`", symbol),
+        }
+    }
+}
+
+fn syntax_error(position: usize, message: String) -> Error {
+    Error::QuerySyntaxError(format!("{} at position {}", message, position))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_path_segment_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+fn read_while(chars: &mut Peekable<CharIndices>, accept: fn(char) -> bool) -> String {
+    let mut text = String::new();
+    while let Some((_, c)) = chars.next_if(|&(_, c)| accept(c)) {
+        text.push(c);
+    }
+    text
+}
+
+fn decode_hex(digits: &str, position: usize) -> Result<Vec<u8>, Error> {
+    hex::decode(digits)
+        .map_err(|e| syntax_error(position, format!("invalid hex `0x{}`: {}", digits, e)))
+}
+
+// Reads quoted text starting at the opening quote
+fn read_quoted(chars: &mut Peekable<CharIndices>, position: usize) -> Result<Vec<u8>, Error> {
+    chars.next();
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '"')) => return Ok(text.into_bytes()),
+            Some((_, '\\')) => match chars.next() {
+                Some((_, escaped @ ('"' | '\\'))) => text.push(escaped),
+                Some((escape_position, _)) => {
+                    return Err(syntax_error(
+                        escape_position,
+                        "invalid escape, expected `\\\"` or `\\\\`".to_string(),
+                    ))
+                }
+                None => break,
+            },
+            Some((_, c)) => text.push(c),
+            None => break,
+        }
+    }
+    Err(syntax_error(position, "unterminated quote".to_string()))
+}
+
+// Reads a path starting at its first `/`
+fn read_path(chars: &mut Peekable<CharIndices>) -> Result<Vec<Vec<u8>>, Error> {
+    let mut path = vec![];
+    while let Some((slash_position, _)) = chars.next_if(|&(_, c)| c == '/') {
+        let segment = match chars.peek() {
+            Some(&(position, '"')) => read_quoted(chars, position)?,
+            _ => {
+                let segment = read_while(chars, is_path_segment_char);
+                if segment.is_empty() {
+                    if path.is_empty() {
+                        // The root path
+                        break;
+                    }
+                    return Err(syntax_error(
+                        slash_position,
+                        "empty path segment".to_string(),
+                    ));
+                }
+                match segment.strip_prefix("0x") {
+                    Some(digits) => decode_hex(digits, slash_position + 1)?,
+                    None => segment.into_bytes(),
+                }
+            }
+        };
+        path.push(segment);
+    }
+    Ok(path)
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(position, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '*' | '(' | ')' | ',' | '=' => {
+                chars.next();
+                Token::Symbol(match c {
+                    '*' => "*",
+                    '(' => "(",
+                    ')' => ")",
+                    ',' => ",",
+                    _ => "=",
+                })
+            }
+            '<' | '>' => {
+                chars.next();
+                let or_equal = chars.next_if(|&(_, c)| c == '=').is_some();
+                Token::Symbol(match (c, or_equal) {
+                    ('<', false) => "<",
+                    ('<', true) => "<=",
+                    ('>', false) => ">",
+                    _ => ">=",
+                })
+            }
+            '"' => Token::Bytes(read_quoted(&mut chars, position)?),
+            '/' => Token::Path(read_path(&mut chars)?),
+            '-' | '0'..='9' => {
+                chars.next();
+                let literal = format!("{}{}", c, read_while(&mut chars, is_word_char));
+                match literal.strip_prefix("0x") {
+                    Some(digits) => Token::Bytes(decode_hex(digits, position)?),
+                    None => Token::Integer(literal.parse().map_err(|e| {
+                        syntax_error(position, format!("invalid integer `{}`: {}", literal, e))
+                    })?),
+                }
+            }
+            c if is_word_char(c) => {
+                Token::Word(read_while(&mut chars, is_word_char).to_ascii_uppercase())
+            }
+            c => {
+                return Err(syntax_error(
+                    position,
+                    format!("unexpected character `{}`", c),
+                ))
+            }
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+// Recursive descent parser over the tokens of a path query
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).map(|(_, token)| token.clone());
+        self.next += 1;
+        token
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map_or(self.end, |(position, _)| *position)
+    }
+
+    fn error(&self, expected: &str) -> Error {
+        match self.peek() {
+            Some(token) => syntax_error(
+                self.position(),
+                format!("expected {}, found {}", expected, token),
+            ),
+            None => syntax_error(
+                self.end,
+                format!("expected {}, found the end of the query", expected),
+            ),
+        }
+    }
+
+    fn is_keyword_at(&self, offset: usize, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.next + offset), Some((_, Token::Word(word))) if word == keyword)
+    }
+
+    // Consumes the keyword if it is next
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword_at(0, keyword);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{}`", keyword)))
+        }
+    }
+
+    // Consumes the symbol if it is next
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(next)) if *next == symbol);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        if self.symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{}`", symbol)))
+        }
+    }
+
+    fn expect_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        match self.peek() {
+            Some(Token::Bytes(bytes)) => {
+                let bytes = bytes.clone();
+                self.next += 1;
+                Ok(bytes)
+            }
+            _ => Err(self.error("quoted text or 0x-prefixed hex")),
+        }
+    }
+
+    fn expect_u16(&mut self) -> Result<u16, Error> {
+        match self.peek() {
+            Some(Token::Integer(integer)) if u16::try_from(*integer).is_ok() => {
+                let integer = *integer as u16;
+                self.next += 1;
+                Ok(integer)
+            }
+            _ => Err(self.error("an integer from 0 to 65535")),
+        }
+    }
+
+    fn path(&mut self) -> Option<Vec<Vec<u8>>> {
+        match self.peek() {
+            Some(Token::Path(path)) => {
+                let path = path.clone();
+                self.next += 1;
+                Some(path)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect_comparison(&mut self) -> Result<NumericComparison, Error> {
+        let comparison = match self.peek() {
+            Some(Token::Symbol("=")) => NumericComparison::Equal,
+            Some(Token::Symbol("<")) => NumericComparison::LessThan,
+            Some(Token::Symbol("<=")) => NumericComparison::LessThanOrEqual,
+            Some(Token::Symbol(">")) => NumericComparison::GreaterThan,
+            Some(Token::Symbol(">=")) => NumericComparison::GreaterThanOrEqual,
+            _ => return Err(self.error("`=`, `<`, `<=`, `>` or `>=`")),
+        };
+        self.next += 1;
+        Ok(comparison)
+    }
+
+    fn path_query(&mut self) -> Result<PathQuery, Error> {
+        self.expect_keyword("SELECT")?;
+        self.expect_symbol("*")?;
+        self.expect_keyword("FROM")?;
+        let path = self.path().ok_or_else(|| self.error("a path"))?;
+        let mut query = self.conditions()?;
+        let limit = if self.keyword("LIMIT") {
+            Some(self.expect_u16()?)
+        } else {
+            None
+        };
+        let offset = if self.keyword("OFFSET") {
+            Some(self.expect_u16()?)
+        } else {
+            None
+        };
+        self.subquery_branches(&mut query)?;
+        if self.peek().is_some() {
+            return Err(self.error("`SUBQUERY` or the end of the query"));
+        }
+        Ok(PathQuery::new(path, SizedQuery::new(query, limit, offset)))
+    }
+
+    // Parses a subquery after its opening parenthesis
+    fn subquery(&mut self) -> Result<Query, Error> {
+        self.expect_keyword("SELECT")?;
+        self.expect_symbol("*")?;
+        let mut query = self.conditions()?;
+        self.subquery_branches(&mut query)?;
+        self.expect_symbol(")")?;
+        Ok(query)
+    }
+
+    // Parses the `WHERE` and `ORDER BY` clauses into a query
+    fn conditions(&mut self) -> Result<Query, Error> {
+        let mut query = Query::new();
+        if !self.keyword("WHERE") {
+            query.insert_all();
+        } else if self.is_keyword_at(0, "VALUE") {
+            query.insert_all();
+            query.value_filter = Some(self.value_condition()?);
+        } else {
+            loop {
+                query.insert_items(self.key_condition()?);
+                if !self.keyword("OR") {
+                    break;
+                }
+            }
+            if self.keyword("AND") {
+                query.value_filter = Some(self.value_condition()?);
+            }
+        }
+        if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            self.expect_keyword("KEY")?;
+            if self.keyword("DESC") {
+                query.left_to_right = false;
+            } else if !self.keyword("ASC") {
+                return Err(self.error("`ASC` or `DESC`"));
+            }
+        }
+        Ok(query)
+    }
+
+    fn key_condition(&mut self) -> Result<Vec<QueryItem>, Error> {
+        if self.keyword("ANY") {
+            self.expect_keyword("KEY")?;
+            return Ok(vec![QueryItem::RangeFull(..)]);
+        }
+        self.expect_keyword("KEY")?;
+        if self.keyword("IN") {
+            self.expect_symbol("(")?;
+            let mut keys = vec![];
+            if !self.symbol(")") {
+                loop {
+                    keys.push(QueryItem::Key(self.expect_bytes()?));
+                    if self.symbol(")") {
+                        break;
+                    }
+                    self.expect_symbol(",")?;
+                }
+            }
+            return Ok(keys);
+        }
+        if self.keyword("BETWEEN") {
+            let start = self.expect_bytes()?;
+            self.expect_keyword("AND")?;
+            let end = self.expect_bytes()?;
+            return Ok(vec![QueryItem::RangeInclusive(start..=end)]);
+        }
+        let item = match self.expect_comparison()? {
+            NumericComparison::Equal => QueryItem::Key(self.expect_bytes()?),
+            NumericComparison::LessThan => QueryItem::RangeTo(..self.expect_bytes()?),
+            NumericComparison::LessThanOrEqual => {
+                QueryItem::RangeToInclusive(..=self.expect_bytes()?)
+            }
+            comparison => {
+                let start = self.expect_bytes()?;
+                let after = comparison == NumericComparison::GreaterThan;
+                // `AND KEY` bounds the range, while `AND VALUE` starts the
+                // value condition
+                if self.is_keyword_at(0, "AND") && self.is_keyword_at(1, "KEY") {
+                    self.next += 2;
+                    let end_position = self.next;
+                    let end_comparison = self.expect_comparison()?;
+                    let end = self.expect_bytes()?;
+                    match (after, end_comparison) {
+                        (false, NumericComparison::LessThan) => QueryItem::Range(start..end),
+                        (false, NumericComparison::LessThanOrEqual) => {
+                            QueryItem::RangeInclusive(start..=end)
+                        }
+                        (true, NumericComparison::LessThan) => QueryItem::RangeAfterTo(start..end),
+                        (true, NumericComparison::LessThanOrEqual) => {
+                            QueryItem::RangeAfterToInclusive(start..=end)
+                        }
+                        _ => {
+                            self.next = end_position;
+                            return Err(self.error("`<` or `<=` as the end of the key range"));
+                        }
+                    }
+                } else if after {
+                    QueryItem::RangeAfter(start..)
+                } else {
+                    QueryItem::RangeFrom(start..)
+                }
+            }
+        };
+        Ok(vec![item])
+    }
+
+    fn value_condition(&mut self) -> Result<ValueFilter, Error> {
+        self.expect_keyword("VALUE")?;
+        if self.keyword("STARTS") {
+            self.expect_keyword("WITH")?;
+            return Ok(ValueFilter::Prefix(self.expect_bytes()?));
+        }
+        let comparison = self.expect_comparison()?;
+        match self.peek() {
+            Some(Token::Integer(integer)) => {
+                let integer = *integer;
+                self.next += 1;
+                Ok(ValueFilter::Compare(comparison, integer))
+            }
+            Some(Token::Bytes(_)) if comparison == NumericComparison::Equal => {
+                Ok(ValueFilter::Equal(self.expect_bytes()?))
+            }
+            _ if comparison == NumericComparison::Equal => {
+                Err(self.error("an integer, quoted text or 0x-prefixed hex"))
+            }
+            _ => Err(self.error("an integer")),
+        }
+    }
+
+    fn subquery_branches(&mut self, query: &mut Query) -> Result<(), Error> {
+        while self.is_keyword_at(0, "SUBQUERY") {
+            let position = self.position();
+            self.next += 1;
+            let condition = if self.keyword("WHEN") {
+                let condition_position = self.position();
+                let mut items = self.key_condition()?;
+                if items.len() != 1 {
+                    return Err(syntax_error(
+                        condition_position,
+                        "expected a single key or range after `WHEN`".to_string(),
+                    ));
+                }
+                items.pop()
+            } else {
+                None
+            };
+            let subquery_path = self.path();
+            let subquery = if self.symbol("(") {
+                Some(self.subquery()?)
+            } else {
+                None
+            };
+            match condition {
+                Some(item) => query.add_conditional_subquery(item, subquery_path, subquery),
+                None if subquery_path.is_none() && subquery.is_none() => {
+                    return Err(self.error("a subquery path or `(`"));
+                }
+                None if query.default_subquery_branch.subquery_path.is_some()
+                    || query.default_subquery_branch.subquery.is_some() =>
+                {
+                    return Err(syntax_error(
+                        position,
+                        "the default subquery is already set".to_string(),
+                    ));
+                }
+                None => {
+                    query.default_subquery_branch = SubqueryBranch {
+                        subquery_path,
+                        subquery: subquery.map(Box::new),
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PathQuery {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Parser {
+            tokens: tokenize(text)?,
+            next: 0,
+            end: text.len(),
+        }
+        .path_query()
+    }
+}
+
+// Bytes written as quoted text when they are text, in hex otherwise
+struct FormattedBytes<'a>(&'a [u8]);
+
+impl fmt::Display for FormattedBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match std::str::from_utf8(self.0) {
+            Ok(text) if !text.chars().any(char::is_control) => {
+                write!(f, "\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            _ => write!(f, "0x{}", hex::encode(self.0)),
+        }
+    }
+}
+
+struct FormattedPath<'a>(&'a [Vec<u8>]);
+
+impl fmt::Display for FormattedPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "/");
+        }
+        for segment in self.0 {
+            let plain_text = std::str::from_utf8(segment).ok().filter(|text| {
+                !text.is_empty()
+                    && !text.starts_with("0x")
+                    && text.chars().all(is_path_segment_char)
+            });
+            match plain_text {
+                Some(text) => write!(f, "/{}", text)?,
+                None => write!(f, "/{}", FormattedBytes(segment))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_key_condition(f: &mut fmt::Formatter<'_>, item: &QueryItem) -> fmt::Result {
+    match item {
+        QueryItem::Key(key) => write!(f, "KEY = {}", FormattedBytes(key)),
+        QueryItem::Range(range) => write!(
+            f,
+            "KEY >= {} AND KEY < {}",
+            FormattedBytes(&range.start),
+            FormattedBytes(&range.end)
+        ),
+        QueryItem::RangeInclusive(range) => write!(
+            f,
+            "KEY BETWEEN {} AND {}",
+            FormattedBytes(range.start()),
+            FormattedBytes(range.end())
+        ),
+        QueryItem::RangeFull(_) => write!(f, "ANY KEY"),
+        QueryItem::RangeFrom(range) => write!(f, "KEY >= {}", FormattedBytes(&range.start)),
+        QueryItem::RangeTo(range) => write!(f, "KEY < {}", FormattedBytes(&range.end)),
+        QueryItem::RangeToInclusive(range) => write!(f, "KEY <= {}", FormattedBytes(&range.end)),
+        QueryItem::RangeAfter(range) => write!(f, "KEY > {}", FormattedBytes(&range.start)),
+        QueryItem::RangeAfterTo(range) => write!(
+            f,
+            "KEY > {} AND KEY < {}",
+            FormattedBytes(&range.start),
+            FormattedBytes(&range.end)
+        ),
+        QueryItem::RangeAfterToInclusive(range) => write!(
+            f,
+            "KEY > {} AND KEY <= {}",
+            FormattedBytes(range.start()),
+            FormattedBytes(range.end())
+        ),
+    }
+}
+
+fn write_value_condition(f: &mut fmt::Formatter<'_>, value_filter: &ValueFilter) -> fmt::Result {
+    match value_filter {
+        ValueFilter::Equal(value) => write!(f, "VALUE = {}", FormattedBytes(value)),
+        ValueFilter::Prefix(prefix) => write!(f, "VALUE STARTS WITH {}", FormattedBytes(prefix)),
+        ValueFilter::Compare(NumericComparison::Equal, operand) => {
+            write!(f, "VALUE = {}", operand)
+        }
+        ValueFilter::Compare(comparison, operand) => write!(f, "VALUE {} {}", comparison, operand),
+    }
+}
+
+// Writes the `WHERE` and `ORDER BY` clauses of the query
+fn write_conditions(f: &mut fmt::Formatter<'_>, query: &Query) -> fmt::Result {
+    let all_keys = matches!(query.items.as_slice(), [QueryItem::RangeFull(_)]);
+    if !all_keys {
+        write!(f, " WHERE ")?;
+        if query.items.len() > 1 || query.items.is_empty() {
+            if query
+                .items
+                .iter()
+                .all(|item| matches!(item, QueryItem::Key(_)))
+            {
+                write!(f, "KEY IN (")?;
+                for (i, item) in query.items.iter().enumerate() {
+                    if let QueryItem::Key(key) = item {
+                        let separator = if i > 0 { ", " } else { "" };
+                        write!(f, "{}{}", separator, FormattedBytes(key))?;
+                    }
+                }
+                write!(f, ")")?;
+            } else {
+                for (i, item) in query.items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " OR ")?;
+                    }
+                    write_key_condition(f, item)?;
+                }
+            }
+        } else {
+            write_key_condition(f, &query.items[0])?;
+        }
+    }
+    if let Some(value_filter) = &query.value_filter {
+        write!(f, "{}", if all_keys { " WHERE " } else { " AND " })?;
+        write_value_condition(f, value_filter)?;
+    }
+    if !query.left_to_right {
+        write!(f, " ORDER BY KEY DESC")?;
+    }
+    Ok(())
+}
+
+fn write_subquery_branch(
+    f: &mut fmt::Formatter<'_>,
+    subquery_branch: &SubqueryBranch,
+) -> fmt::Result {
+    if let Some(subquery_path) = &subquery_branch.subquery_path {
+        write!(f, " {}", FormattedPath(subquery_path))?;
+    }
+    if let Some(subquery) = &subquery_branch.subquery {
+        write!(f, " (SELECT *")?;
+        write_conditions(f, subquery)?;
+        write_subquery_branches(f, subquery)?;
+        write!(f, ")")?;
+    }
+    Ok(())
+}
+
+fn write_subquery_branches(f: &mut fmt::Formatter<'_>, query: &Query) -> fmt::Result {
+    let default_subquery_branch = &query.default_subquery_branch;
+    if default_subquery_branch.subquery_path.is_some() || default_subquery_branch.subquery.is_some()
+    {
+        write!(f, " SUBQUERY")?;
+        write_subquery_branch(f, default_subquery_branch)?;
+    }
+    for (item, subquery_branch) in query.conditional_subquery_branches.iter().flatten() {
+        write!(f, " SUBQUERY WHEN ")?;
+        write_key_condition(f, item)?;
+        write_subquery_branch(f, subquery_branch)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for PathQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SELECT * FROM {}", FormattedPath(&self.path))?;
+        write_conditions(f, &self.query.query)?;
+        if let Some(limit) = self.query.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        if let Some(offset) = self.query.offset {
+            write!(f, " OFFSET {}", offset)?;
+        }
+        write_subquery_branches(f, &self.query.query)
+    }
+}
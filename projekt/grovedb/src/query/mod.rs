@@ -21,6 +21,13 @@ use crate::query_result_type::PathKey;
 #[cfg(any(feature = "full", feature = "verify"))]
 use crate::Error;
 
+#[cfg(any(feature = "full", feature = "verify"))]
+mod cursor;
+#[cfg(any(feature = "full", feature = "verify"))]
+pub use cursor::QueryCursor;
+#[cfg(any(feature = "full", feature = "verify"))]
+mod language;
+
 #[cfg(any(feature = "full", feature = "verify"))]
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 /// Path query
@@ -34,20 +41,6 @@ pub struct PathQuery {
     pub query: SizedQuery,
 }
 
-#[cfg(any(feature = "full", feature = "verify"))]
-impl fmt::Display for PathQuery {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "PathQuery {{ path: [")?;
-        for (i, path_element) in self.path.iter().enumerate() {
-            if i > 0 {
-                write!(f, ", ")?;
-            }
-            write!(f, "{}", hex_to_ascii(path_element))?;
-        }
-        write!(f, "], query: {} }}", self.query)
-    }
-}
-
 #[cfg(any(feature = "full", feature = "verify"))]
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 /// Holds a query to apply to a tree and an optional limit/offset value.
@@ -201,6 +194,11 @@ impl PathQuery {
                         .to_string(),
                 ));
             }
+            if path_query.query.query.has_value_filter() {
+                return Err(Error::NotSupported(
+                    "can not merge pathqueries with value filters".to_string(),
+                ));
+            }
             path_query
                 .to_subquery_branch_with_offset_start_index(next_index)
                 .map(|unsized_path_query| {
@@ -580,6 +578,20 @@ impl<'a> SinglePathSubquery<'a> {
     }
 }
 
+#[cfg(any(feature = "full", feature = "verify"))]
+/// Returns the subquery branch applying to the key: the first conditional
+/// subquery branch matching it, the default subquery branch otherwise
+pub(crate) fn subquery_branch_for_key<'a>(query: &'a Query, key: &[u8]) -> &'a SubqueryBranch {
+    query
+        .conditional_subquery_branches
+        .iter()
+        .flatten()
+        .find(|(item, _)| item.contains(key))
+        .map_or(&query.default_subquery_branch, |(_, subquery_branch)| {
+            subquery_branch
+        })
+}
+
 #[cfg(feature = "full")]
 #[cfg(test)]
 mod tests {
@@ -1285,6 +1297,7 @@ mod tests {
                 subquery: None,
             },
             left_to_right: true,
+            value_filter: None,
             conditional_subquery_branches: None,
         };
 
@@ -1302,6 +1315,7 @@ mod tests {
                         subquery: Some(Box::new(subquery)),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -1466,6 +1480,7 @@ mod tests {
                         subquery: None,
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(100),
@@ -1537,6 +1552,7 @@ mod tests {
                         subquery: None,
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: Some(conditional_subquery_branches),
                 },
                 limit: Some(100),
@@ -1586,6 +1602,7 @@ mod tests {
                         subquery: None,
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 })),
             },
@@ -1603,6 +1620,7 @@ mod tests {
                             subquery: None,
                         },
                         left_to_right: true,
+                        value_filter: None,
                         conditional_subquery_branches: None,
                     })),
                 },
@@ -1621,6 +1639,7 @@ mod tests {
                             inner_conditional_subquery_branches.clone(),
                         ),
                         left_to_right: true,
+                        value_filter: None,
                     })),
                 },
             ),
@@ -1637,6 +1656,7 @@ mod tests {
                     },
                     conditional_subquery_branches: Some(conditional_subquery_branches.clone()),
                     left_to_right: true,
+                    value_filter: None,
                 },
                 limit: Some(100),
                 offset: None,
@@ -1765,6 +1785,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: None,
                     left_to_right: true,
+                    value_filter: None,
                 },
                 limit: None,
                 offset: None,
@@ -1787,6 +1808,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: None,
                     left_to_right: false,
+                    value_filter: None,
                 },
                 limit: Some(10),
                 offset: Some(2),
@@ -1809,6 +1831,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: None,
                     left_to_right: true,
+                    value_filter: None,
                 },
                 limit: Some(5),
                 offset: None,
@@ -1840,6 +1863,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: Some(conditional_branches),
                     left_to_right: true,
+                    value_filter: None,
                 },
                 limit: None,
                 offset: None,
@@ -1883,6 +1907,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: None,
                     left_to_right: true,
+                    value_filter: None,
                 },
                 limit: None,
                 offset: None,
@@ -1905,6 +1930,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: None,
                     left_to_right: false,
+                    value_filter: None,
                 },
                 limit: Some(100),
                 offset: Some(10),
@@ -1929,6 +1955,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: None,
                     left_to_right: true,
+                    value_filter: None,
                 })),
             },
         );
@@ -1941,6 +1968,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: None,
                     left_to_right: false,
+                    value_filter: None,
                 })),
             },
         );
@@ -1953,6 +1981,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: Some(conditional_branches),
                     left_to_right: true,
+                    value_filter: None,
                 },
                 limit: Some(50),
                 offset: Some(5),
@@ -1979,10 +2008,12 @@ mod tests {
                             default_subquery_branch: SubqueryBranch::default(),
                             conditional_subquery_branches: None,
                             left_to_right: true,
+                            value_filter: None,
                         })),
                     },
                     conditional_subquery_branches: None,
                     left_to_right: true,
+                    value_filter: None,
                 },
                 limit: None,
                 offset: None,
@@ -2005,6 +2036,7 @@ mod tests {
                     default_subquery_branch: SubqueryBranch::default(),
                     conditional_subquery_branches: None,
                     left_to_right: true,
+                    value_filter: None,
                 },
                 limit: Some(20),
                 offset: None,
@@ -10,6 +10,7 @@ pub use grovedb_merk::proofs::query::{Key, Path, PathKey};
 use grovedb_version::{version::GroveVersion, TryFromVersioned};
 
 use crate::{
+    element::SumValue,
     operations::proof::util::{
         hex_to_ascii, path_hex_to_ascii, ProvedPathKeyOptionalValue, ProvedPathKeyValue,
     },
@@ -540,6 +541,58 @@ impl TryFromVersioned<ProvedPathKeyOptionalValue> for PathKeyOptionalElementTrio
     }
 }
 
+#[cfg(any(feature = "full", feature = "verify"))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+/// Aggregates of the results of a path query
+///
+/// Every result is counted. Sum items and sum trees, including the sum items
+/// references point to, also contribute their value to the sum, minimum and
+/// maximum.
+pub struct QueryAggregates {
+    /// Number of results
+    pub count: u64,
+    /// Sum of the sum values
+    pub sum: SumValue,
+    /// Smallest sum value, `None` if no result has one
+    pub min: Option<SumValue>,
+    /// Largest sum value, `None` if no result has one
+    pub max: Option<SumValue>,
+}
+
+#[cfg(any(feature = "full", feature = "verify"))]
+impl QueryAggregates {
+    /// Adds a result, with references already followed
+    pub(crate) fn add_result(&mut self, element: &Element) -> Result<(), Error> {
+        self.count += 1;
+        let sum_value = match element {
+            Element::SumItem(sum_value, _) | Element::SumTree(_, sum_value, _) => *sum_value,
+            _ => return Ok(()),
+        };
+        self.sum = self.sum.checked_add(sum_value).ok_or(Error::MerkError(
+            grovedb_merk::error::Error::Overflow("sum of the query results overflows"),
+        ))?;
+        self.min = Some(self.min.map_or(sum_value, |min| min.min(sum_value)));
+        self.max = Some(self.max.map_or(sum_value, |max| max.max(sum_value)));
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "full", feature = "verify"))]
+impl fmt::Display for QueryAggregates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let optional =
+            |value: Option<SumValue>| value.map_or("none".to_string(), |v| v.to_string());
+        write!(
+            f,
+            "count: {}, sum: {}, min: {}, max: {}",
+            self.count,
+            self.sum,
+            optional(self.min),
+            optional(self.max)
+        )
+    }
+}
+
 #[cfg(feature = "full")]
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,256 @@
+//! Snapshot archives
+//!
+//! A snapshot archive is a single file holding a whole snapshot, for shipping
+//! a replica without live chunk transfers. It starts with `ARCHIVE_MAGIC`,
+//! followed by the bincode encoded `ArchiveHeader` (format and state sync
+//! protocol versions, app hash and manifest) and by one `ArchiveChunk` per
+//! chunk of the snapshot. Chunk payloads are the ones served by `fetch_chunk`,
+//! i.e. proof operators encoded with `util_encode_vec_ops`.
+//!
+//! Importing an archive restores it like any other `ChunkSource`, so every
+//! chunk goes through the same `Restorer` verification as in a live state
+//! sync, against an app hash obtained from a trusted party.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use bincode::{config, Decode, Encode};
+use grovedb_merk::tree::hash::CryptoHash;
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    replication::{ChunkSource, MultiStateSyncInfo, SnapshotManifest, CURRENT_STATE_SYNC_VERSION},
+    Error, GroveDb,
+};
+
+/// Magic bytes at the start of every snapshot archive
+const ARCHIVE_MAGIC: &[u8; 8] = b"GROVESNP";
+
+/// Version of the archive format
+const ARCHIVE_FORMAT_VERSION: u16 = 1;
+
+#[derive(Encode, Decode)]
+struct ArchiveHeader {
+    // Version of the archive format
+    format_version: u16,
+    // Version of the state sync protocol the chunk ids are encoded in
+    version: u16,
+    app_hash: CryptoHash,
+    // Encoded manifest and its hash
+    manifest: Vec<u8>,
+    manifest_hash: CryptoHash,
+    // Number of chunks following the header
+    num_chunks: u64,
+}
+
+#[derive(Encode)]
+struct ArchiveChunk {
+    global_chunk_id: Vec<u8>,
+    chunk: Vec<u8>,
+}
+
+/// Snapshot archive opened for reading, serving its chunks as a
+/// `ChunkSource`
+pub struct SnapshotArchive {
+    file: Mutex<BufReader<File>>,
+    version: u16,
+    app_hash: CryptoHash,
+    manifest: SnapshotManifest,
+    // Global chunk id -> (Offset, Length) of the chunk payload in the file
+    chunk_locations: BTreeMap<Vec<u8>, (u64, usize)>,
+}
+
+impl SnapshotArchive {
+    /// Opens a snapshot archive, indexing the location of its chunks
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let config = config::standard().with_big_endian().with_no_limit();
+        let mut file = BufReader::new(File::open(path.as_ref()).map_err(io_error)?);
+
+        let mut magic = [0u8; ARCHIVE_MAGIC.len()];
+        file.read_exact(&mut magic).map_err(io_error)?;
+        if &magic != ARCHIVE_MAGIC {
+            return Err(Error::CorruptedData(
+                "file is not a snapshot archive".to_string(),
+            ));
+        }
+        let header: ArchiveHeader = bincode::decode_from_std_read(&mut file, config)
+            .map_err(|e| Error::CorruptedData(format!("unable to decode archive header {}", e)))?;
+        if header.format_version != ARCHIVE_FORMAT_VERSION {
+            return Err(Error::CorruptedData(
+                "unsupported snapshot archive format version".to_string(),
+            ));
+        }
+        let manifest = SnapshotManifest::decode(&header.manifest, &header.manifest_hash)?;
+        if manifest.app_hash != header.app_hash {
+            return Err(Error::CorruptedData(
+                "snapshot archive manifest doesn't match its app_hash".to_string(),
+            ));
+        }
+
+        // Only the chunk ids are kept in memory, the payloads are read on demand
+        let mut chunk_locations = BTreeMap::new();
+        // An encoded ArchiveChunk is its encoded global chunk id followed by the
+        // length of the chunk payload (as a u64) and the payload itself
+        for _ in 0..header.num_chunks {
+            let global_chunk_id: Vec<u8> = bincode::decode_from_std_read(&mut file, config)
+                .map_err(|e| {
+                    Error::CorruptedData(format!("unable to decode archive chunk {}", e))
+                })?;
+            let chunk_len: u64 = bincode::decode_from_std_read(&mut file, config).map_err(|e| {
+                Error::CorruptedData(format!("unable to decode archive chunk {}", e))
+            })?;
+            let offset = file.stream_position().map_err(io_error)?;
+            file.seek_relative(chunk_len as i64).map_err(io_error)?;
+            chunk_locations.insert(global_chunk_id, (offset, chunk_len as usize));
+        }
+
+        Ok(SnapshotArchive {
+            file: Mutex::new(file),
+            version: header.version,
+            app_hash: header.app_hash,
+            manifest,
+            chunk_locations,
+        })
+    }
+
+    /// Returns the version of the state sync protocol the chunk ids of the
+    /// archive are encoded in
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+}
+
+impl ChunkSource for SnapshotArchive {
+    fn app_hash(&self, _grove_version: &GroveVersion) -> Result<CryptoHash, Error> {
+        Ok(self.app_hash)
+    }
+
+    fn manifest(&self, _grove_version: &GroveVersion) -> Result<SnapshotManifest, Error> {
+        Ok(self.manifest.clone())
+    }
+
+    fn fetch_chunk(
+        &self,
+        global_chunk_id: &[u8],
+        version: u16,
+        _grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        if version != self.version {
+            return Err(Error::CorruptedData(
+                "Unsupported state sync protocol version".to_string(),
+            ));
+        }
+        let (offset, len) = self
+            .chunk_locations
+            .get(global_chunk_id)
+            .ok_or_else(|| Error::CorruptedData("chunk not found in archive".to_string()))?;
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| Error::InternalError("snapshot archive poisoned".to_string()))?;
+        let mut chunk = vec![0u8; *len];
+        file.seek(SeekFrom::Start(*offset)).map_err(io_error)?;
+        file.read_exact(&mut chunk).map_err(io_error)?;
+        Ok(chunk)
+    }
+}
+
+/// Writes all chunks of the snapshot served by `source` to a snapshot archive
+/// at `path`, with chunk ids in the encoding of the given version of the state
+/// sync protocol. Returns the app hash of the snapshot.
+pub fn write_snapshot_archive<S: ChunkSource + ?Sized, P: AsRef<Path>>(
+    source: &S,
+    path: P,
+    version: u16,
+    grove_version: &GroveVersion,
+) -> Result<CryptoHash, Error> {
+    let config = config::standard().with_big_endian().with_no_limit();
+    let app_hash = source.app_hash(grove_version)?;
+    let manifest = source.manifest(grove_version)?;
+    if manifest.app_hash != app_hash {
+        return Err(Error::CorruptedData(
+            "snapshot manifest doesn't match the app_hash".to_string(),
+        ));
+    }
+    let global_chunk_ids = manifest.global_chunk_ids(version)?;
+    let header = ArchiveHeader {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        version,
+        app_hash,
+        manifest: manifest.encode()?,
+        manifest_hash: manifest.hash()?,
+        num_chunks: global_chunk_ids.len() as u64,
+    };
+
+    let mut file = BufWriter::new(File::create(path.as_ref()).map_err(io_error)?);
+    file.write_all(ARCHIVE_MAGIC).map_err(io_error)?;
+    bincode::encode_into_std_write(&header, &mut file, config)
+        .map_err(|e| Error::CorruptedData(format!("unable to encode archive header {}", e)))?;
+    for global_chunk_id in global_chunk_ids {
+        let chunk = source.fetch_chunk(&global_chunk_id, version, grove_version)?;
+        bincode::encode_into_std_write(
+            &ArchiveChunk {
+                global_chunk_id,
+                chunk,
+            },
+            &mut file,
+            config,
+        )
+        .map_err(|e| Error::CorruptedData(format!("unable to encode archive chunk {}", e)))?;
+    }
+    file.flush().map_err(io_error)?;
+    Ok(app_hash)
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Exports the current state of the GroveDB to a snapshot archive at
+    /// `path`. Returns the app hash of the exported snapshot. The GroveDB
+    /// should not be written to during the export (see
+    /// `start_snapshot_session` and `write_snapshot_archive` for exporting a
+    /// pinned state instead).
+    pub fn export_snapshot<P: AsRef<Path>>(
+        &self,
+        path: P,
+        grove_version: &GroveVersion,
+    ) -> Result<CryptoHash, Error> {
+        write_snapshot_archive(self, path, CURRENT_STATE_SYNC_VERSION, grove_version)
+    }
+
+    /// Imports the snapshot archive at `path` if its app hash is
+    /// `expected_app_hash`, verifying every chunk against it. The archive
+    /// itself isn't trusted: it is rejected before restoring anything if it
+    /// holds another snapshot, and the state is only committed once the whole
+    /// snapshot was restored.
+    pub fn import_snapshot<P: AsRef<Path>>(
+        &self,
+        path: P,
+        expected_app_hash: CryptoHash,
+        grove_version: &GroveVersion,
+    ) -> Result<(), Error> {
+        let archive = SnapshotArchive::open(path)?;
+        if archive.app_hash != expected_app_hash {
+            return Err(Error::CorruptedData(
+                "snapshot archive doesn't match the expected app_hash".to_string(),
+            ));
+        }
+        let tx = self.start_transaction();
+        let state_sync_info = self.sync_from_source(
+            &archive,
+            MultiStateSyncInfo::default().with_version(archive.version),
+            &tx,
+            grove_version,
+        )?;
+        drop(state_sync_info);
+        self.commit_transaction(tx).unwrap()
+    }
+}
+
+fn io_error(error: std::io::Error) -> Error {
+    Error::InternalError(format!("snapshot archive error: {}", error))
+}
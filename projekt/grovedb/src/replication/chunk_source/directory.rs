@@ -0,0 +1,122 @@
+//! Directory of chunk files
+//!
+//! Layout of the directory:
+//! - `version`: version of the state sync protocol the chunk ids are encoded
+//!   in (decimal)
+//! - `manifest`: encoded snapshot manifest
+//! - `chunks/<hex global chunk id>`: one file per chunk
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use grovedb_merk::tree::hash::CryptoHash;
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    replication::{chunk_source::ChunkSource, SnapshotManifest},
+    Error,
+};
+
+const VERSION_FILE: &str = "version";
+const MANIFEST_FILE: &str = "manifest";
+const CHUNKS_DIR: &str = "chunks";
+
+/// Chunk source reading the chunks of a snapshot from a directory written by
+/// `write_chunk_directory`
+pub struct DirectoryChunkSource {
+    dir: PathBuf,
+    version: u16,
+    manifest: SnapshotManifest,
+}
+
+impl DirectoryChunkSource {
+    /// Opens a directory of chunk files
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        let version = read_file(&dir.join(VERSION_FILE))?;
+        let version = std::str::from_utf8(&version)
+            .ok()
+            .and_then(|version| version.trim().parse().ok())
+            .ok_or_else(|| {
+                Error::CorruptedData("invalid state sync protocol version file".to_string())
+            })?;
+        let manifest = SnapshotManifest::decode_unverified(&read_file(&dir.join(MANIFEST_FILE))?)?;
+        Ok(DirectoryChunkSource {
+            dir,
+            version,
+            manifest,
+        })
+    }
+
+    /// Returns the version of the state sync protocol the chunk ids of the
+    /// directory are encoded in
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+}
+
+impl ChunkSource for DirectoryChunkSource {
+    fn app_hash(&self, _grove_version: &GroveVersion) -> Result<CryptoHash, Error> {
+        Ok(self.manifest.app_hash)
+    }
+
+    fn manifest(&self, _grove_version: &GroveVersion) -> Result<SnapshotManifest, Error> {
+        Ok(self.manifest.clone())
+    }
+
+    fn fetch_chunk(
+        &self,
+        global_chunk_id: &[u8],
+        version: u16,
+        _grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        if version != self.version {
+            return Err(Error::CorruptedData(
+                "Unsupported state sync protocol version".to_string(),
+            ));
+        }
+        read_file(&chunk_path(&self.dir, global_chunk_id))
+    }
+}
+
+/// Writes all chunks of the snapshot served by `source` to `dir` (which is
+/// created if needed), with chunk ids in the encoding of the given version of
+/// the state sync protocol
+pub fn write_chunk_directory<S: ChunkSource + ?Sized, P: AsRef<Path>>(
+    source: &S,
+    dir: P,
+    version: u16,
+    grove_version: &GroveVersion,
+) -> Result<(), Error> {
+    let dir = dir.as_ref();
+    let manifest = source.manifest(grove_version)?;
+    fs::create_dir_all(dir.join(CHUNKS_DIR)).map_err(|e| io_error(dir, e))?;
+    for global_chunk_id in manifest.global_chunk_ids(version)? {
+        let chunk = source.fetch_chunk(&global_chunk_id, version, grove_version)?;
+        write_file(&chunk_path(dir, &global_chunk_id), &chunk)?;
+    }
+    write_file(&dir.join(MANIFEST_FILE), &manifest.encode()?)?;
+    write_file(&dir.join(VERSION_FILE), version.to_string().as_bytes())
+}
+
+fn chunk_path(dir: &Path, global_chunk_id: &[u8]) -> PathBuf {
+    dir.join(CHUNKS_DIR).join(hex::encode(global_chunk_id))
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|e| io_error(path, e))
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    fs::write(path, contents).map_err(|e| io_error(path, e))
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> Error {
+    Error::InternalError(format!(
+        "unable to access chunk directory file {}: {}",
+        path.display(),
+        error
+    ))
+}
@@ -0,0 +1,132 @@
+//! Chunk sources
+//!
+//! A `ChunkSource` serves the chunks of one snapshot, along with its app hash
+//! and manifest. `GroveDb::sync_from_source` restores the snapshot from any
+//! implementation: a `GroveDb` or `SnapshotSession` in the same process, a
+//! directory of chunk files (see `DirectoryChunkSource`) or a remote process
+//! over TCP (see `TcpChunkSource`). `GroveDb::sync_from_sources` restores it
+//! from several untrusted sources at once.
+
+mod directory;
+mod multi_source;
+mod tcp;
+
+use grovedb_merk::tree::hash::CryptoHash;
+use grovedb_version::version::GroveVersion;
+
+pub use self::{
+    directory::{write_chunk_directory, DirectoryChunkSource},
+    tcp::{serve_chunks, serve_chunks_connection, TcpChunkSource, DEFAULT_CHUNK_SOURCE_TIMEOUT},
+};
+use crate::{
+    replication::{MultiStateSyncInfo, SnapshotManifest, SnapshotSession},
+    Error, GroveDb, Transaction,
+};
+
+/// Source serving the chunks of a snapshot
+pub trait ChunkSource {
+    /// Returns the app hash of the snapshot
+    fn app_hash(&self, grove_version: &GroveVersion) -> Result<CryptoHash, Error>;
+
+    /// Returns the manifest of the snapshot
+    fn manifest(&self, grove_version: &GroveVersion) -> Result<SnapshotManifest, Error>;
+
+    /// Fetches a chunk by global chunk id, in the encoding of the given version
+    /// of the state sync protocol
+    fn fetch_chunk(
+        &self,
+        global_chunk_id: &[u8],
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error>;
+}
+
+impl ChunkSource for GroveDb {
+    fn app_hash(&self, grove_version: &GroveVersion) -> Result<CryptoHash, Error> {
+        self.root_hash(None, grove_version).unwrap()
+    }
+
+    fn manifest(&self, grove_version: &GroveVersion) -> Result<SnapshotManifest, Error> {
+        self.snapshot_manifest(None, grove_version)
+    }
+
+    fn fetch_chunk(
+        &self,
+        global_chunk_id: &[u8],
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        GroveDb::fetch_chunk(self, global_chunk_id, None, version, grove_version)
+    }
+}
+
+impl ChunkSource for SnapshotSession {
+    fn app_hash(&self, _grove_version: &GroveVersion) -> Result<CryptoHash, Error> {
+        Ok(SnapshotSession::app_hash(self))
+    }
+
+    fn manifest(&self, grove_version: &GroveVersion) -> Result<SnapshotManifest, Error> {
+        SnapshotSession::manifest(self, grove_version)
+    }
+
+    fn fetch_chunk(
+        &self,
+        global_chunk_id: &[u8],
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        SnapshotSession::fetch_chunk(self, global_chunk_id, version, grove_version)
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    // Restores the snapshot served by a chunk source Params:
+    // source: Source of the snapshot
+    // state_sync_info: Consumed MultiStateSyncInfo (carrying the observer and
+    // the version of the state sync protocol to use)
+    // tx: Transaction for the state sync
+    // Returns the MultiStateSyncInfo of the completed state sync. tx still has
+    // to be committed by the caller. State syncs made with_validation() fail if
+    // the restored GroveDB isn't valid.
+    // All pending chunks are fetched in one round and applied at once (see
+    // apply_chunks()), until no chunk is pending anymore. An interrupted state
+    // sync of the same snapshot persisted in tx is resumed.
+    pub fn sync_from_source<'db, S: ChunkSource + ?Sized>(
+        &'db self,
+        source: &S,
+        state_sync_info: MultiStateSyncInfo<'db>,
+        tx: &'db Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<MultiStateSyncInfo<'db>, Error> {
+        let version = state_sync_info.version;
+        let app_hash = source.app_hash(grove_version)?;
+        let manifest = source.manifest(grove_version)?;
+        let mut state_sync_info = self.start_snapshot_syncing(
+            state_sync_info.with_manifest(manifest),
+            app_hash,
+            tx,
+            version,
+            grove_version,
+        )?;
+
+        while !state_sync_info.is_sync_completed() {
+            let pending_chunk_ids = state_sync_info.pending_global_chunk_ids();
+            if pending_chunk_ids.is_empty() {
+                return Err(Error::InternalError(
+                    "State sync has no pending chunks left".to_string(),
+                ));
+            }
+            let mut chunks = Vec::with_capacity(pending_chunk_ids.len());
+            for global_chunk_id in pending_chunk_ids {
+                let chunk = source.fetch_chunk(&global_chunk_id, version, grove_version)?;
+                chunks.push((global_chunk_id, chunk));
+            }
+            state_sync_info = self
+                .apply_chunks(state_sync_info, chunks, tx, version, grove_version)
+                .map_err(|e| Error::ReplicationError(e.error))?
+                .1;
+        }
+        Ok(state_sync_info)
+    }
+}
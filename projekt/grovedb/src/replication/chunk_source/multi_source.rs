@@ -0,0 +1,164 @@
+//! State sync from several untrusted sources
+//!
+//! `GroveDb::sync_from_sources` spreads the pending chunks of every round over
+//! all sources that haven't misbehaved so far, and fetches them concurrently.
+//! A source serving a chunk that fails verification (or failing to serve it,
+//! e.g. because a remote source timed out) is marked faulty and not asked
+//! again. Its chunks stay pending in the state
+//! sync, and are fetched from the remaining sources in the next round.
+
+use std::{collections::BTreeSet, thread};
+
+use grovedb_merk::tree::hash::CryptoHash;
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    replication::{chunk_source::ChunkSource, MultiStateSyncInfo},
+    Error, GroveDb, Transaction,
+};
+
+// Chunks fetched from one source in a round: global chunk ids along with
+// their chunks, and whether the source failed to serve any of them
+struct SourceRound {
+    source: usize,
+    chunks: Vec<(Vec<u8>, Vec<u8>)>,
+    failed: bool,
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    // Restores the snapshot of the given app hash from several chunk sources
+    // Params:
+    // sources: Sources of the snapshot, none of them trusted
+    // app_hash: Trusted app hash of the snapshot
+    // state_sync_info: Consumed MultiStateSyncInfo (carrying the observer and
+    // the version of the state sync protocol to use)
+    // tx: Transaction for the state sync
+    // Returns the MultiStateSyncInfo of the completed state sync along with the
+    // indexes of the sources found faulty. tx still has to be committed by the
+    // caller. State syncs made with_validation() fail if the restored GroveDB
+    // isn't valid.
+    // Sources serving a different app hash are faulty from the start. The
+    // manifests of the sources are not used, as a bad manifest would abort the
+    // state sync. Fails only if all sources turn out faulty, or on errors
+    // unrelated to the chunks.
+    pub fn sync_from_sources<'db, S: ChunkSource + Sync + ?Sized>(
+        &'db self,
+        sources: &[&S],
+        app_hash: CryptoHash,
+        state_sync_info: MultiStateSyncInfo<'db>,
+        tx: &'db Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<(MultiStateSyncInfo<'db>, Vec<usize>), Error> {
+        let version = state_sync_info.version;
+        let mut faulty_sources = BTreeSet::new();
+        for (index, source) in sources.iter().enumerate() {
+            if source.app_hash(grove_version).ok() != Some(app_hash) {
+                faulty_sources.insert(index);
+            }
+        }
+        let mut state_sync_info =
+            self.start_snapshot_syncing(state_sync_info, app_hash, tx, version, grove_version)?;
+
+        let mut round = 0;
+        while !state_sync_info.is_sync_completed() {
+            let healthy_sources: Vec<usize> = (0..sources.len())
+                .filter(|index| !faulty_sources.contains(index))
+                .collect();
+            if healthy_sources.is_empty() {
+                return Err(Error::InternalError(
+                    "All chunk sources are faulty".to_string(),
+                ));
+            }
+            let pending_chunk_ids = state_sync_info.pending_global_chunk_ids();
+            if pending_chunk_ids.is_empty() {
+                return Err(Error::InternalError(
+                    "State sync has no pending chunks left".to_string(),
+                ));
+            }
+
+            // Rotate the assignment every round, so that a chunk is fetched
+            // from another source after a failure
+            let mut chunk_ids_per_source: Vec<Vec<Vec<u8>>> =
+                healthy_sources.iter().map(|_| vec![]).collect();
+            for (i, global_chunk_id) in pending_chunk_ids.into_iter().enumerate() {
+                chunk_ids_per_source[(i + round) % healthy_sources.len()].push(global_chunk_id);
+            }
+            round += 1;
+
+            let rounds: Vec<SourceRound> = thread::scope(|scope| {
+                let handles: Vec<_> = healthy_sources
+                    .iter()
+                    .zip(chunk_ids_per_source)
+                    .filter(|(_, chunk_ids)| !chunk_ids.is_empty())
+                    .map(|(&index, chunk_ids)| {
+                        let source = sources[index];
+                        scope.spawn(move || {
+                            fetch_chunks(index, source, chunk_ids, version, grove_version)
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle.join().map_err(|_| {
+                            Error::InternalError("chunk fetching thread panicked".to_string())
+                        })
+                    })
+                    .collect::<Result<_, _>>()
+            })?;
+
+            for source_round in rounds {
+                if source_round.failed {
+                    faulty_sources.insert(source_round.source);
+                }
+                if source_round.chunks.is_empty() {
+                    continue;
+                }
+                state_sync_info = match self.apply_chunks(
+                    state_sync_info,
+                    source_round.chunks,
+                    tx,
+                    version,
+                    grove_version,
+                ) {
+                    Ok((_, state_sync_info)) => state_sync_info,
+                    Err(error) if error.error.is_recoverable() => {
+                        faulty_sources.insert(source_round.source);
+                        error.state_sync_info
+                    }
+                    Err(error) => return Err(Error::ReplicationError(error.error)),
+                };
+            }
+        }
+        Ok((state_sync_info, faulty_sources.into_iter().collect()))
+    }
+}
+
+// Fetches the chunks from the source, stopping at the first failure
+fn fetch_chunks<S: ChunkSource + ?Sized>(
+    index: usize,
+    source: &S,
+    global_chunk_ids: Vec<Vec<u8>>,
+    version: u16,
+    grove_version: &GroveVersion,
+) -> SourceRound {
+    let mut chunks = Vec::with_capacity(global_chunk_ids.len());
+    for global_chunk_id in global_chunk_ids {
+        match source.fetch_chunk(&global_chunk_id, version, grove_version) {
+            Ok(chunk) => chunks.push((global_chunk_id, chunk)),
+            Err(_) => {
+                return SourceRound {
+                    source: index,
+                    chunks,
+                    failed: true,
+                }
+            }
+        }
+    }
+    SourceRound {
+        source: index,
+        chunks,
+        failed: false,
+    }
+}
@@ -0,0 +1,230 @@
+//! Chunk transfer over TCP
+//!
+//! Every request and response is sent as a frame: its length as a 4 byte big
+//! endian integer, followed by the bincode encoding of the message. A
+//! connection carries any number of requests, each answered by exactly one
+//! response. A `TcpChunkSource` gives up on a peer that doesn't answer within
+//! its timeout, so that a stalled peer fails like any other faulty source.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::Mutex,
+    time::Duration,
+};
+
+use bincode::{config, Decode, Encode};
+use grovedb_merk::tree::hash::CryptoHash;
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    replication::{chunk_source::ChunkSource, SnapshotManifest},
+    Error,
+};
+
+/// Maximum size of a frame
+const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default timeout of connecting to, reading from and writing to a peer
+pub const DEFAULT_CHUNK_SOURCE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Encode, Decode)]
+enum ChunkRequest {
+    AppHash,
+    Manifest,
+    FetchChunk {
+        global_chunk_id: Vec<u8>,
+        version: u16,
+    },
+}
+
+#[derive(Encode, Decode)]
+enum ChunkResponse {
+    AppHash(CryptoHash),
+    Manifest(Vec<u8>),
+    Chunk(Vec<u8>),
+    Error(String),
+}
+
+/// Chunk source fetching the chunks from a remote process serving them with
+/// `serve_chunks`
+pub struct TcpChunkSource {
+    stream: Mutex<TcpStream>,
+}
+
+impl TcpChunkSource {
+    /// Connects to a process serving chunks, with the default timeout
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, Error> {
+        Self::connect_with_timeout(addr, DEFAULT_CHUNK_SOURCE_TIMEOUT)
+    }
+
+    /// Connects to a process serving chunks. Connecting, and reading or writing
+    /// any frame afterwards, fails if it takes longer than `timeout`.
+    pub fn connect_with_timeout<A: ToSocketAddrs>(
+        addr: A,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let mut last_error = None;
+        for addr in addr.to_socket_addrs().map_err(io_error)? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => {
+                    stream
+                        .set_read_timeout(Some(timeout))
+                        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+                        .map_err(io_error)?;
+                    return Ok(TcpChunkSource {
+                        stream: Mutex::new(stream),
+                    });
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.map_or_else(
+            || Error::InternalError("chunk source address didn't resolve".to_string()),
+            io_error,
+        ))
+    }
+
+    // Sends the request and waits for its response
+    fn request(&self, request: ChunkRequest) -> Result<ChunkResponse, Error> {
+        let mut stream = self
+            .stream
+            .lock()
+            .map_err(|_| Error::InternalError("chunk source connection poisoned".to_string()))?;
+        write_frame(&mut *stream, &request)?;
+        match read_frame(&mut *stream)? {
+            Some(ChunkResponse::Error(message)) => Err(Error::InternalError(format!(
+                "chunk source error: {}",
+                message
+            ))),
+            Some(response) => Ok(response),
+            None => Err(Error::InternalError(
+                "chunk source closed the connection".to_string(),
+            )),
+        }
+    }
+}
+
+impl ChunkSource for TcpChunkSource {
+    fn app_hash(&self, _grove_version: &GroveVersion) -> Result<CryptoHash, Error> {
+        match self.request(ChunkRequest::AppHash)? {
+            ChunkResponse::AppHash(app_hash) => Ok(app_hash),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn manifest(&self, _grove_version: &GroveVersion) -> Result<SnapshotManifest, Error> {
+        match self.request(ChunkRequest::Manifest)? {
+            ChunkResponse::Manifest(manifest) => SnapshotManifest::decode_unverified(&manifest),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn fetch_chunk(
+        &self,
+        global_chunk_id: &[u8],
+        version: u16,
+        _grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        match self.request(ChunkRequest::FetchChunk {
+            global_chunk_id: global_chunk_id.to_vec(),
+            version,
+        })? {
+            ChunkResponse::Chunk(chunk) => Ok(chunk),
+            _ => Err(unexpected_response()),
+        }
+    }
+}
+
+/// Serves the chunks of `source` to every connection accepted by `listener`,
+/// one connection at a time. Only returns on a listener error.
+pub fn serve_chunks<S: ChunkSource + ?Sized>(
+    source: &S,
+    listener: &TcpListener,
+    grove_version: &GroveVersion,
+) -> Result<(), Error> {
+    loop {
+        let (stream, _) = listener.accept().map_err(io_error)?;
+        // A failing connection doesn't stop the server
+        let _ = serve_chunks_connection(source, stream, grove_version);
+    }
+}
+
+/// Serves the chunks of `source` on a single connection, until the peer
+/// closes it
+pub fn serve_chunks_connection<S: ChunkSource + ?Sized>(
+    source: &S,
+    mut stream: TcpStream,
+    grove_version: &GroveVersion,
+) -> Result<(), Error> {
+    while let Some(request) = read_frame(&mut stream)? {
+        let response = match request {
+            ChunkRequest::AppHash => source.app_hash(grove_version).map(ChunkResponse::AppHash),
+            ChunkRequest::Manifest => source
+                .manifest(grove_version)
+                .and_then(|manifest| manifest.encode())
+                .map(ChunkResponse::Manifest),
+            ChunkRequest::FetchChunk {
+                global_chunk_id,
+                version,
+            } => source
+                .fetch_chunk(&global_chunk_id, version, grove_version)
+                .map(ChunkResponse::Chunk),
+        };
+        let response = response.unwrap_or_else(|e| ChunkResponse::Error(e.to_string()));
+        write_frame(&mut stream, &response)?;
+    }
+    Ok(())
+}
+
+fn write_frame<W: Write, M: Encode>(writer: &mut W, message: &M) -> Result<(), Error> {
+    let config = config::standard().with_big_endian().with_no_limit();
+    let payload = bincode::encode_to_vec(message, config)
+        .map_err(|e| Error::CorruptedData(format!("unable to encode message {}", e)))?;
+    if payload.len() > MAX_FRAME_SIZE {
+        return Err(Error::InternalError(
+            "message exceeds frame size".to_string(),
+        ));
+    }
+    writer
+        .write_all(&(payload.len() as u32).to_be_bytes())
+        .and_then(|_| writer.write_all(&payload))
+        .and_then(|_| writer.flush())
+        .map_err(io_error)
+}
+
+// Reads the next frame, returns None if the peer closed the connection
+fn read_frame<R: Read, M: Decode>(reader: &mut R) -> Result<Option<M>, Error> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(io_error(e)),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_SIZE {
+        return Err(Error::CorruptedData(
+            "frame exceeds maximum size".to_string(),
+        ));
+    }
+    let mut payload = vec![0u8; length];
+    reader.read_exact(&mut payload).map_err(io_error)?;
+    let config = config::standard().with_big_endian().with_no_limit();
+    let (message, _) = bincode::decode_from_slice(&payload, config)
+        .map_err(|e| Error::CorruptedData(format!("unable to decode message {}", e)))?;
+    Ok(Some(message))
+}
+
+fn unexpected_response() -> Error {
+    Error::CorruptedData("unexpected chunk source response".to_string())
+}
+
+fn io_error(error: std::io::Error) -> Error {
+    match error.kind() {
+        // Timeouts surface as WouldBlock on some platforms
+        ErrorKind::TimedOut | ErrorKind::WouldBlock => {
+            Error::InternalError("chunk source timed out".to_string())
+        }
+        _ => Error::InternalError(format!("chunk source connection error: {}", error)),
+    }
+}
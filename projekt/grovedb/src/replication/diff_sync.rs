@@ -0,0 +1,181 @@
+//! Diff sync
+//!
+//! A regular state sync rebuilds the replica from an empty root. A diff sync
+//! (see `MultiStateSyncInfo::with_diff_sync`) instead reuses the subtrees the
+//! replica already has. It proceeds top-down like a regular state sync, but
+//! before a subtree is restored the value hashes of its child subtrees are
+//! read from the replica's storage. Once the subtree is restored from the
+//! source (and thus verified), a child subtree with the same value hash in the
+//! restored subtree is identical in both databases, and is skipped along with
+//! all its descendants. Only the chunks of subtrees whose hashes differ are
+//! transferred, and child subtrees that don't exist in the source anymore are
+//! deleted. The root hash of the replica is finally checked against the app
+//! hash.
+
+use std::collections::BTreeMap;
+
+use grovedb_merk::{
+    tree::{hash::CryptoHash, TreeNode},
+    Merk,
+};
+use grovedb_path::SubtreePath;
+use grovedb_storage::{
+    rocksdb_storage::{
+        storage_context::context_immediate::PrefixedRocksDbImmediateStorageContext, RocksDbStorage,
+    },
+    Batch, RawIterator, Storage, StorageContext,
+};
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    replication::{MultiStateSyncInfo, SubtreePrefix, SubtreesMetadata},
+    Element, Error, GroveDb, Transaction,
+};
+
+/// Child subtrees a subtree had in the replica before being restored by a
+/// diff sync: SubtreePrefix -> (Path, value hash of the child's element in the
+/// subtree)
+pub(crate) type ReplacedSubtrees = BTreeMap<SubtreePrefix, (Vec<Vec<u8>>, CryptoHash)>;
+
+impl<'db> MultiStateSyncInfo<'db> {
+    /// Makes the state sync a diff sync, reusing the subtrees the replica
+    /// already has instead of rebuilding it from an empty root. Only the
+    /// chunks of subtrees that differ from the source are fetched. A manifest
+    /// attached to the state sync is ignored, as identical subtrees are
+    /// neither fetched nor checked against it.
+    pub fn with_diff_sync(mut self) -> Self {
+        self.diff_sync = true;
+        self
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    // Prepares the merk of a subtree for its restoration by a diff sync:
+    // clears it and returns the child subtrees it had
+    pub(crate) fn prepare_diff_restoration(
+        &self,
+        path: &[Vec<u8>],
+        merk: &mut Merk<PrefixedRocksDbImmediateStorageContext>,
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<ReplacedSubtrees, Error> {
+        let replaced_subtrees = self.stored_child_subtrees(path, tx, grove_version)?;
+        merk.clear().unwrap().map_err(Error::MerkError)?;
+        Ok(replaced_subtrees)
+    }
+
+    // Deletes the replaced child subtrees of a restored subtree that it
+    // doesn't have anymore, along with all their descendants
+    // child_subtrees_metadata: Metadata of the child subtrees of the restored
+    // subtree
+    pub(crate) fn delete_replaced_subtrees(
+        &self,
+        replaced_subtrees: &ReplacedSubtrees,
+        child_subtrees_metadata: &SubtreesMetadata,
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<(), Error> {
+        for (prefix, (path, _)) in replaced_subtrees.iter() {
+            if !child_subtrees_metadata.data.contains_key(prefix) {
+                self.delete_subtree_storage(path, tx, grove_version)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Checks the root hash of the replica once a diff sync is completed
+    pub(crate) fn check_diff_synced_root_hash(
+        &self,
+        app_hash: &CryptoHash,
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<(), Error> {
+        let root_hash = self.root_hash(Some(tx), grove_version).unwrap()?;
+        if &root_hash != app_hash {
+            return Err(Error::CorruptedData(
+                "diff synced GroveDB doesn't match the app_hash".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Reads the child subtrees of the subtree at the given path straight from
+    // the storage, without going through its parent element (which may have
+    // been restored from the source already)
+    fn stored_child_subtrees(
+        &self,
+        path: &[Vec<u8>],
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<ReplacedSubtrees, Error> {
+        let subtree_path: Vec<&[u8]> = path.iter().map(|vec| vec.as_slice()).collect();
+        let storage = self
+            .db
+            .get_immediate_storage_context(subtree_path.as_slice().into(), tx)
+            .unwrap();
+
+        let mut child_subtrees = BTreeMap::new();
+        let mut iter = storage.raw_iter();
+        iter.seek_to_first().unwrap();
+        while iter.valid().unwrap() {
+            if let (Some(key), Some(bytes)) = (iter.key().unwrap(), iter.value().unwrap()) {
+                let node = TreeNode::decode_raw(
+                    bytes,
+                    key.to_vec(),
+                    Some(Element::value_defined_cost_for_serialized_value),
+                    grove_version,
+                )
+                .map_err(|e| Error::CorruptedData(e.to_string()))?;
+                if Element::deserialize(node.value_as_slice(), grove_version)?.is_any_tree() {
+                    let mut child_path = path.to_vec();
+                    child_path.push(key.to_vec());
+                    let child_subtree_path: Vec<&[u8]> =
+                        child_path.iter().map(|vec| vec.as_slice()).collect();
+                    let prefix =
+                        RocksDbStorage::build_prefix(child_subtree_path.as_slice().into()).unwrap();
+                    child_subtrees.insert(prefix, (child_path, *node.value_hash()));
+                }
+            }
+            iter.next().unwrap();
+        }
+        Ok(child_subtrees)
+    }
+
+    // Deletes the data of the subtree at the given path and of all its
+    // descendants
+    fn delete_subtree_storage(
+        &self,
+        path: &[Vec<u8>],
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<(), Error> {
+        for (child_path, _) in self
+            .stored_child_subtrees(path, tx, grove_version)?
+            .values()
+        {
+            self.delete_subtree_storage(child_path, tx, grove_version)?;
+        }
+
+        let subtree_path: Vec<&[u8]> = path.iter().map(|vec| vec.as_slice()).collect();
+        let subtree_path: SubtreePath<_> = subtree_path.as_slice().into();
+        let storage = self
+            .db
+            .get_immediate_storage_context(subtree_path, tx)
+            .unwrap();
+        let mut batch = storage.new_batch();
+        let mut iter = storage.raw_iter();
+        iter.seek_to_first().unwrap();
+        while iter.valid().unwrap() {
+            if let Some(key) = iter.key().unwrap() {
+                batch.delete(key, None);
+            }
+            iter.next().unwrap();
+        }
+        drop(iter);
+        storage
+            .commit_batch(batch)
+            .unwrap()
+            .map_err(Error::StorageError)
+    }
+}
@@ -0,0 +1,192 @@
+//! Replication errors
+//!
+//! Applying a chunk fails with a `ReplicationError` naming the global chunk id
+//! at fault and the reason. Unless the error isn't recoverable (see
+//! `ReplicationError::is_recoverable`), the state sync is left as it was before
+//! the chunk, and is handed back in the `ApplyChunkError` so that the chunk can
+//! be requested again (e.g. from another source).
+
+use std::fmt;
+
+use grovedb_merk::{error::Error as MerkError, proofs::chunk::error::ChunkError};
+
+use crate::{
+    replication::{MultiStateSyncInfo, StateSyncEvent, SyncValidationReport},
+    Error,
+};
+
+/// Reason why a chunk couldn't be applied
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ReplicationError {
+    /// The chunk doesn't match the hash it was expected to have
+    #[error(
+        "chunk {} doesn't match its expected hash",
+        hex::encode(global_chunk_id)
+    )]
+    HashMismatch {
+        /// Global chunk id
+        global_chunk_id: Vec<u8>,
+    },
+
+    /// The chunk (or its id) couldn't be decoded, or isn't a well-formed chunk
+    #[error("unable to decode chunk {}: {reason}", hex::encode(global_chunk_id))]
+    DecodeFailure {
+        /// Global chunk id (empty if a whole multi-chunk couldn't be decoded)
+        global_chunk_id: Vec<u8>,
+        /// Decoding error
+        reason: String,
+    },
+
+    /// The chunk isn't pending in the state sync
+    #[error("chunk {} isn't expected", hex::encode(global_chunk_id))]
+    UnexpectedChunk {
+        /// Global chunk id
+        global_chunk_id: Vec<u8>,
+    },
+
+    /// The chunk was given in a version of the state sync protocol that is
+    /// unsupported, or different from the one of the state sync
+    #[error(
+        "chunk {} has wrong state sync protocol version {version}",
+        hex::encode(global_chunk_id)
+    )]
+    WrongVersion {
+        /// Global chunk id
+        global_chunk_id: Vec<u8>,
+        /// Version the chunk was given in
+        version: u16,
+    },
+
+    /// The chunk completed the state sync, but the restored GroveDB failed its
+    /// validation (see `MultiStateSyncInfo::with_validation`). The state sync
+    /// can't be continued.
+    #[error(
+        "synced state failed validation after chunk {}: {report}",
+        hex::encode(global_chunk_id)
+    )]
+    ValidationFailed {
+        /// Global chunk id
+        global_chunk_id: Vec<u8>,
+        /// Issues found by the validation
+        report: SyncValidationReport,
+    },
+
+    /// Failure unrelated to the content of the chunk (e.g. a storage error)
+    /// while applying it. The state sync can't be continued.
+    #[error("unable to apply chunk {}: {reason}", hex::encode(global_chunk_id))]
+    Internal {
+        /// Global chunk id
+        global_chunk_id: Vec<u8>,
+        /// Underlying error
+        reason: String,
+    },
+}
+
+impl ReplicationError {
+    /// Returns the global chunk id of the chunk that couldn't be applied
+    pub fn global_chunk_id(&self) -> &[u8] {
+        match self {
+            ReplicationError::HashMismatch { global_chunk_id }
+            | ReplicationError::DecodeFailure {
+                global_chunk_id, ..
+            }
+            | ReplicationError::UnexpectedChunk { global_chunk_id }
+            | ReplicationError::WrongVersion {
+                global_chunk_id, ..
+            }
+            | ReplicationError::ValidationFailed {
+                global_chunk_id, ..
+            }
+            | ReplicationError::Internal {
+                global_chunk_id, ..
+            } => global_chunk_id,
+        }
+    }
+
+    /// Returns true if the state sync was left intact, i.e. if the chunk can
+    /// be requested again
+    pub fn is_recoverable(&self) -> bool {
+        !matches!(
+            self,
+            ReplicationError::ValidationFailed { .. } | ReplicationError::Internal { .. }
+        )
+    }
+
+    // Classifies an error of the chunk verification
+    pub(crate) fn from_verification_error(global_chunk_id: &[u8], error: MerkError) -> Self {
+        match error {
+            MerkError::ChunkRestoringError(ChunkError::ChunkHashMismatch) => {
+                ReplicationError::HashMismatch {
+                    global_chunk_id: global_chunk_id.to_vec(),
+                }
+            }
+            MerkError::ChunkRestoringError(ChunkError::UnexpectedChunk) => {
+                ReplicationError::UnexpectedChunk {
+                    global_chunk_id: global_chunk_id.to_vec(),
+                }
+            }
+            error => ReplicationError::DecodeFailure {
+                global_chunk_id: global_chunk_id.to_vec(),
+                reason: error.to_string(),
+            },
+        }
+    }
+
+    // Wraps an error unrelated to the content of the chunk
+    pub(crate) fn internal(global_chunk_id: &[u8], error: Error) -> Self {
+        match error {
+            Error::SyncValidationFailed(report) => ReplicationError::ValidationFailed {
+                global_chunk_id: global_chunk_id.to_vec(),
+                report,
+            },
+            error => ReplicationError::Internal {
+                global_chunk_id: global_chunk_id.to_vec(),
+                reason: error.to_string(),
+            },
+        }
+    }
+}
+
+/// Failure to apply a chunk, handing the state sync back to the caller
+pub struct ApplyChunkError<'db> {
+    /// Reason of the failure
+    pub error: ReplicationError,
+    /// State sync, left as it was before the failing chunk unless the error
+    /// isn't recoverable
+    pub state_sync_info: MultiStateSyncInfo<'db>,
+}
+
+impl fmt::Debug for ApplyChunkError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApplyChunkError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for ApplyChunkError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<'db> MultiStateSyncInfo<'db> {
+    // Hands the state sync back along with the error
+    pub(crate) fn fail(self, error: ReplicationError) -> ApplyChunkError<'db> {
+        ApplyChunkError {
+            error,
+            state_sync_info: self,
+        }
+    }
+
+    // Forwards the error to the observer (if any)
+    pub(crate) fn notify_error(&self, error: &ReplicationError) {
+        if self.observer.is_some() {
+            let event_error = Error::ReplicationError(error.clone());
+            self.notify(StateSyncEvent::Error {
+                global_chunk_id: Some(error.global_chunk_id()),
+                error: &event_error,
+            });
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Snapshot manifest
+//!
+//! A `SnapshotManifest` lists every subtree of a snapshot up front: its prefix,
+//! path, the value hashes of its element in the parent merk, its height and
+//! the number of chunks it consists of. A replica can use it to enumerate all
+//! global chunk ids before restoring anything (e.g. to schedule downloads from
+//! several sources in parallel) and to report accurate progress from the start.
+//!
+//! The manifest is identified by the hash of its encoding, which can be handed
+//! out along with the app hash. Its content is checked during the state sync:
+//! every subtree discovered while restoring must match its manifest entry, and
+//! all listed subtrees must have been restored once the state sync completes.
+
+use bincode::{config, Decode, Encode};
+use grovedb_merk::{
+    proofs::chunk::util::{generate_traversal_instruction_as_vec_bytes, number_of_chunks},
+    tree::hash::CryptoHash,
+};
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    replication::{util_encode_chunk_id, MultiStateSyncInfo, SubtreePrefix, SubtreesMetadata},
+    Error, GroveDb, TransactionArg,
+};
+
+/// Manifest entry of a single subtree
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct SubtreeManifestEntry {
+    /// Subtree prefix (Path digest)
+    pub prefix: SubtreePrefix,
+    /// Path of the subtree
+    pub path: Vec<Vec<u8>>,
+    /// Hash of the subtree's serialized element in the parent merk (all zeros
+    /// for the root subtree)
+    pub parent_value_hash: CryptoHash,
+    /// Value hash of the subtree's element in the parent merk (all zeros for
+    /// the root subtree)
+    pub elem_value_hash: CryptoHash,
+    /// Height of the subtree's merk (0 for an empty subtree)
+    pub height: u8,
+    /// Number of chunks the subtree consists of
+    pub chunk_count: u64,
+}
+
+/// List of all subtrees of a snapshot
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct SnapshotManifest {
+    /// Snapshot's app hash
+    pub app_hash: CryptoHash,
+    /// Entries of all subtrees, ordered by prefix
+    pub subtrees: Vec<SubtreeManifestEntry>,
+}
+
+/// Progress of a state sync, as estimated from the snapshot manifest
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateSyncProgress {
+    /// Number of restored subtrees
+    pub num_processed_subtrees: usize,
+    /// Number of subtrees in the snapshot
+    pub num_total_subtrees: usize,
+    /// Number of applied chunks
+    pub num_processed_chunks: u64,
+    /// Number of chunks in the snapshot
+    pub num_total_chunks: u64,
+}
+
+impl SnapshotManifest {
+    /// Encodes the manifest
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let config = config::standard().with_big_endian().with_no_limit();
+        bincode::encode_to_vec(self, config)
+            .map_err(|e| Error::CorruptedData(format!("unable to encode manifest {}", e)))
+    }
+
+    /// Decodes a manifest, checking that the hash of its encoding is
+    /// `expected_hash`
+    pub fn decode(bytes: &[u8], expected_hash: &CryptoHash) -> Result<Self, Error> {
+        if blake3::hash(bytes).as_bytes() != expected_hash {
+            return Err(Error::CorruptedData(
+                "snapshot manifest doesn't match the expected hash".to_string(),
+            ));
+        }
+        Self::decode_unverified(bytes)
+    }
+
+    // Decodes a manifest without checking its hash (for transports where the
+    // manifest isn't handed out along with its hash). Its content is still
+    // checked during the state sync.
+    pub(crate) fn decode_unverified(bytes: &[u8]) -> Result<Self, Error> {
+        let config = config::standard().with_big_endian().with_no_limit();
+        Ok(bincode::decode_from_slice(bytes, config)
+            .map_err(|e| Error::CorruptedData(format!("unable to decode manifest {}", e)))?
+            .0)
+    }
+
+    /// Returns the hash identifying the manifest (the hash of its encoding)
+    pub fn hash(&self) -> Result<CryptoHash, Error> {
+        Ok(*blake3::hash(&self.encode()?).as_bytes())
+    }
+
+    /// Returns the manifest entry of the subtree with the given prefix
+    pub fn subtree(&self, prefix: &SubtreePrefix) -> Option<&SubtreeManifestEntry> {
+        self.subtrees
+            .binary_search_by(|entry| entry.prefix.cmp(prefix))
+            .ok()
+            .map(|index| &self.subtrees[index])
+    }
+
+    /// Returns the total number of chunks of the snapshot
+    pub fn total_chunks(&self) -> u64 {
+        self.subtrees.iter().map(|entry| entry.chunk_count).sum()
+    }
+
+    /// Returns the global chunk ids of all chunks of the snapshot, in the
+    /// encoding of the given state sync protocol version. Chunks of a subtree
+    /// can only be applied once their subtree was discovered, and after the
+    /// chunks they descend from.
+    pub fn global_chunk_ids(&self, version: u16) -> Result<Vec<Vec<u8>>, Error> {
+        let mut res = vec![];
+        for entry in self.subtrees.iter() {
+            if entry.chunk_count == 0 {
+                // Empty subtrees are requested by their prefix alone, except
+                // for the root subtree identified by the app_hash
+                if entry.path.is_empty() {
+                    res.push(self.app_hash.to_vec());
+                } else {
+                    res.push(entry.prefix.to_vec());
+                }
+                continue;
+            }
+            for chunk_index in 1..=entry.chunk_count as usize {
+                let chunk_id =
+                    generate_traversal_instruction_as_vec_bytes(entry.height as usize, chunk_index)
+                        .map_err(|e| {
+                            Error::CorruptedData(format!("invalid manifest entry: {}", e))
+                        })?;
+                if entry.path.is_empty() && chunk_id.is_empty() {
+                    // The root chunk is identified by the app_hash
+                    res.push(self.app_hash.to_vec());
+                } else {
+                    let mut global_chunk_id = entry.prefix.to_vec();
+                    global_chunk_id.extend(util_encode_chunk_id(&chunk_id, version)?);
+                    res.push(global_chunk_id);
+                }
+            }
+        }
+        Ok(res)
+    }
+}
+
+impl<'db> MultiStateSyncInfo<'db> {
+    /// Attaches the manifest of the snapshot being synced. Discovered subtrees
+    /// are then checked against it, and the progress of the state sync can be
+    /// estimated. When resuming an interrupted state sync the manifest has to
+    /// be attached again.
+    pub fn with_manifest(mut self, manifest: SnapshotManifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Returns the progress of the state sync, if a manifest is attached
+    pub fn progress(&self) -> Option<StateSyncProgress> {
+        let entries = self.manifest_entries_in_scope()?;
+        let mut num_processed_chunks = 0;
+        for entry in entries.iter() {
+            if self.processed_prefixes.contains(&entry.prefix) {
+                num_processed_chunks += entry.chunk_count;
+            } else if let Some(subtree_state_sync) = self.current_prefixes.get(&entry.prefix) {
+                num_processed_chunks += entry
+                    .chunk_count
+                    .min(subtree_state_sync.num_processed_chunks as u64);
+            }
+        }
+        Some(StateSyncProgress {
+            num_processed_subtrees: self.processed_prefixes.len(),
+            num_total_subtrees: entries.len(),
+            num_processed_chunks,
+            num_total_chunks: entries.iter().map(|entry| entry.chunk_count).sum(),
+        })
+    }
+
+    // Returns the manifest entries of the subtrees within the scope of the
+    // state sync (None if no manifest is attached)
+    fn manifest_entries_in_scope(&self) -> Option<Vec<&SubtreeManifestEntry>> {
+        let manifest = self.manifest.as_ref()?;
+        let scope_path: &[Vec<u8>] = self
+            .subtree_scope
+            .as_ref()
+            .map(|subtree_scope| subtree_scope.path.as_slice())
+            .unwrap_or_default();
+        Some(
+            manifest
+                .subtrees
+                .iter()
+                .filter(|entry| entry.path.starts_with(scope_path))
+                .collect(),
+        )
+    }
+
+    // Checks the freshly discovered subtrees against the manifest (if any)
+    pub(crate) fn check_subtrees_against_manifest(
+        &self,
+        subtrees_metadata: &SubtreesMetadata,
+    ) -> Result<(), Error> {
+        let Some(manifest) = &self.manifest else {
+            return Ok(());
+        };
+        for (prefix, (path, actual_value_hash, elem_value_hash)) in subtrees_metadata.data.iter() {
+            let matches_manifest = manifest.subtree(prefix).is_some_and(|entry| {
+                &entry.path == path
+                    && &entry.parent_value_hash == actual_value_hash
+                    && &entry.elem_value_hash == elem_value_hash
+            });
+            if !matches_manifest {
+                return Err(Error::CorruptedData(
+                    "discovered subtree doesn't match the snapshot manifest".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Checks that all subtrees listed in the manifest (if any) within the scope
+    // of the state sync were restored
+    pub(crate) fn check_completed_against_manifest(&self) -> Result<(), Error> {
+        let Some(entries) = self.manifest_entries_in_scope() else {
+            return Ok(());
+        };
+        let all_restored = entries
+            .iter()
+            .all(|entry| self.processed_prefixes.contains(&entry.prefix));
+        if !all_restored || entries.len() != self.processed_prefixes.len() {
+            return Err(Error::CorruptedData(
+                "restored subtrees don't match the snapshot manifest".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    // Builds the manifest of the current state of the GroveDB
+    // tx: Transaction. Function returns the data by opening merks at given tx.
+    pub fn snapshot_manifest(
+        &self,
+        tx: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> Result<SnapshotManifest, Error> {
+        let app_hash = self.root_hash(tx, grove_version).value?;
+        let subtrees_metadata = self.get_subtrees_metadata(tx, grove_version)?;
+        self.build_snapshot_manifest(app_hash, &subtrees_metadata, tx, grove_version)
+    }
+
+    // Builds the manifest of the subtrees in the given subtrees metadata
+    pub(crate) fn build_snapshot_manifest(
+        &self,
+        app_hash: CryptoHash,
+        subtrees_metadata: &SubtreesMetadata,
+        tx: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> Result<SnapshotManifest, Error> {
+        let mut subtrees = vec![];
+        for (prefix, (path, actual_value_hash, elem_value_hash)) in subtrees_metadata.data.iter() {
+            let subtree_path: Vec<&[u8]> = path.iter().map(|vec| vec.as_slice()).collect();
+            let subtree_path: &[&[u8]] = &subtree_path;
+            let height = match tx {
+                None => self
+                    .open_non_transactional_merk_at_path(subtree_path.into(), None, grove_version)
+                    .value?
+                    .height(),
+                Some(t) => self
+                    .open_transactional_merk_at_path(subtree_path.into(), t, None, grove_version)
+                    .value?
+                    .height(),
+            }
+            .unwrap_or(0);
+            subtrees.push(SubtreeManifestEntry {
+                prefix: *prefix,
+                path: path.clone(),
+                parent_value_hash: *actual_value_hash,
+                elem_value_hash: *elem_value_hash,
+                height,
+                chunk_count: number_of_chunks(height as usize) as u64,
+            });
+        }
+        Ok(SnapshotManifest { app_hash, subtrees })
+    }
+}
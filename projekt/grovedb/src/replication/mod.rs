@@ -0,0 +1,1277 @@
+mod archive;
+mod chunk_source;
+mod diff_sync;
+mod error;
+mod manifest;
+mod multi_chunk;
+mod observer;
+mod op_log;
+mod parallel_restore;
+mod persistence;
+mod snapshot_session;
+mod stats;
+mod subtree_sync;
+mod validation;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, mem,
+    sync::Arc,
+    time::Instant,
+};
+
+use grovedb_merk::{
+    ed::Encode,
+    merk::restore::{Restorer, VerifiedChunk},
+    proofs::{
+        chunk::util::{compact_bytes_as_vec_bytes, vec_bytes_as_compact_bytes},
+        Decoder, Op, Query,
+    },
+    tree::{hash::CryptoHash, kv::ValueDefinedCostType, value_hash},
+    ChunkProducer, KVIterator,
+};
+use grovedb_path::SubtreePath;
+use grovedb_storage::{rocksdb_storage::RocksDbStorage, StorageContext};
+#[rustfmt::skip]
+use grovedb_storage::rocksdb_storage::storage_context::context_immediate::PrefixedRocksDbImmediateStorageContext;
+use grovedb_version::{check_grovedb_v0, error::GroveVersionError, version::GroveVersion};
+
+pub use self::{
+    archive::{write_snapshot_archive, SnapshotArchive},
+    chunk_source::{
+        serve_chunks, serve_chunks_connection, write_chunk_directory, ChunkSource,
+        DirectoryChunkSource, TcpChunkSource, DEFAULT_CHUNK_SOURCE_TIMEOUT,
+    },
+    error::{ApplyChunkError, ReplicationError},
+    manifest::{SnapshotManifest, StateSyncProgress, SubtreeManifestEntry},
+    observer::{StateSyncEvent, StateSyncObserver},
+    op_log::{LocalOpLogPrimary, OpLogCatchUp, OpLogEntry, OpLogHead, OpLogPrimary, OpLogSource},
+    snapshot_session::SnapshotSession,
+    stats::{StateSyncStats, SubtreeSyncStats},
+    validation::{SumTreeIssue, SyncValidationReport},
+};
+use self::{
+    diff_sync::ReplacedSubtrees,
+    stats::ChunkTimings,
+    subtree_sync::{ProvenSubtree, SubtreeSyncScope},
+};
+use crate::{
+    element::helpers::raw_decode, operations::proof::util::hex_to_ascii, replication, Error,
+    GroveDb, Transaction, TransactionArg,
+};
+
+pub(crate) type SubtreePrefix = [u8; blake3::OUT_LEN];
+
+// Chunk received from a source, either still encoded or already decoded and
+// verified (see GroveDb::apply_chunks())
+pub(crate) enum IncomingChunk {
+    Encoded(Vec<u8>),
+    Verified {
+        verified_chunk: VerifiedChunk,
+        chunk_size: usize,
+        timings: ChunkTimings,
+    },
+}
+
+impl IncomingChunk {
+    // Size of the chunk as received from the source
+    fn len(&self) -> usize {
+        match self {
+            IncomingChunk::Encoded(chunk) => chunk.len(),
+            IncomingChunk::Verified { chunk_size, .. } => *chunk_size,
+        }
+    }
+}
+
+/// Version of the state sync protocol used by default. Version 2 allows
+/// several chunks to be transferred at once (see `GroveDb::fetch_multi_chunk`),
+/// version 3 additionally encodes chunk ids compactly (see `fetch_chunk`).
+pub const CURRENT_STATE_SYNC_VERSION: u16 = 3;
+
+/// Versions of the state sync protocol supported by this implementation
+pub const SUPPORTED_STATE_SYNC_VERSIONS: &[u16] = &[1, 2, 3];
+
+/// First version of the state sync protocol using compact chunk ids
+const COMPACT_CHUNK_ID_STATE_SYNC_VERSION: u16 = 3;
+
+/// Returns the highest version of the state sync protocol supported by both
+/// this implementation and the peer, if any
+pub fn negotiate_state_sync_version(peer_versions: &[u16]) -> Option<u16> {
+    SUPPORTED_STATE_SYNC_VERSIONS
+        .iter()
+        .rev()
+        .find(|version| peer_versions.contains(version))
+        .copied()
+}
+
+// Returns an error if the given version of the state sync protocol is not
+// supported
+fn check_state_sync_version(version: u16) -> Result<(), Error> {
+    if !SUPPORTED_STATE_SYNC_VERSIONS.contains(&version) {
+        return Err(Error::CorruptedData(
+            "Unsupported state sync protocol version".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Returns an error if the given version of a replication method is not
+// supported (check_grovedb_v0!() for methods not returning Error)
+fn check_replication_method_version(method: &'static str, version: u16) -> Result<(), Error> {
+    check_grovedb_v0!(method, version);
+    Ok(())
+}
+
+#[derive(Default)]
+struct SubtreeStateSyncInfo<'db> {
+    // Current Chunk restorer
+    restorer: Option<Restorer<PrefixedRocksDbImmediateStorageContext<'db>>>,
+    // Set of local chunk ids (in the encoding of the state sync protocol version) requested
+    // to be fetched and pending for processing. For the description of chunk ids check
+    // fetch_chunk().
+    pending_chunks: BTreeSet<Vec<u8>>,
+    // Number of processed chunks in current prefix (Path digest)
+    num_processed_chunks: usize,
+    // Actual path of the subtree
+    path: Vec<Vec<u8>>,
+    // Expected root hash of the subtree (Parent Subtree elem_value_hash, or the
+    // app_hash for the root subtree)
+    expected_root_hash: CryptoHash,
+    // Parent Subtree actual_value_hash (None for the root subtree)
+    parent_value_hash: Option<CryptoHash>,
+    // Child subtrees the replica had before the subtree was cleared for its
+    // restoration (diff syncs only)
+    replaced_subtrees: ReplacedSubtrees,
+}
+
+// Struct governing state sync
+pub struct MultiStateSyncInfo<'db> {
+    // Map of current processing subtrees
+    // SubtreePrefix (Path digest) -> SubtreeStateSyncInfo
+    current_prefixes: BTreeMap<SubtreePrefix, SubtreeStateSyncInfo<'db>>,
+    // Set of processed prefixes (Path digests)
+    processed_prefixes: BTreeSet<SubtreePrefix>,
+    // Processed prefixes not persisted yet, in the order they were processed
+    unpersisted_processed_prefixes: Vec<SubtreePrefix>,
+    // Root app_hash
+    app_hash: [u8; 32],
+    // Version of state sync protocol,
+    version: u16,
+    // Observer receiving the progress events of the state sync
+    observer: Option<Arc<dyn StateSyncObserver>>,
+    // Metadata of the subtrees discovered so far. It is updated incrementally
+    // with the child subtrees of every finished subtree.
+    subtrees_metadata: SubtreesMetadata,
+    // Subtree the state sync is restricted to (None when syncing the whole
+    // GroveDB)
+    subtree_scope: Option<SubtreeSyncScope>,
+    // Manifest of the snapshot being synced (if provided by the source)
+    manifest: Option<SnapshotManifest>,
+    // Whether the subtrees the replica already has are reused (see
+    // with_diff_sync())
+    diff_sync: bool,
+    // Whether the restored GroveDB is validated before being committed (see
+    // with_validation())
+    validation: bool,
+    // Statistics of the chunks applied so far
+    stats: StateSyncStats,
+}
+
+impl<'db> MultiStateSyncInfo<'db> {
+    /// Attaches an observer receiving the progress events of the state sync
+    pub fn with_observer(mut self, observer: Arc<dyn StateSyncObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Sets the version of the state sync protocol, for syncing from sources
+    /// that negotiated an older version than `CURRENT_STATE_SYNC_VERSION`
+    pub fn with_version(mut self, version: u16) -> Self {
+        self.version = version;
+        self
+    }
+
+    // Forwards the event to the observer (if any)
+    fn notify(&self, event: StateSyncEvent) {
+        if let Some(observer) = &self.observer {
+            observer.on_event(event);
+        }
+    }
+
+    /// Returns the global chunk ids of all chunks that are still pending for
+    /// processing. When resuming an interrupted state sync these are the
+    /// chunks that have to be (re)fetched from sources.
+    pub fn pending_global_chunk_ids(&self) -> Vec<Vec<u8>> {
+        let mut res = vec![];
+        for (prefix, subtree_state_sync) in self.current_prefixes.iter() {
+            for local_chunk_id in subtree_state_sync.pending_chunks.iter() {
+                res.push(self.global_chunk_id(prefix, local_chunk_id));
+            }
+        }
+        res
+    }
+
+    // Builds the global chunk id of the given local chunk id of a subtree
+    fn global_chunk_id(&self, prefix: &SubtreePrefix, local_chunk_id: &[u8]) -> Vec<u8> {
+        if prefix == &[0u8; 32] && local_chunk_id.is_empty() {
+            // The root chunk is identified by the app_hash
+            self.app_hash.to_vec()
+        } else {
+            let mut global_chunk_id = prefix.to_vec();
+            global_chunk_id.extend(local_chunk_id.to_vec());
+            global_chunk_id
+        }
+    }
+
+    // Returns true if the given local chunk id of a subtree is pending for
+    // processing
+    fn is_chunk_pending(&self, prefix: &SubtreePrefix, local_chunk_id: &[u8]) -> bool {
+        self.current_prefixes
+            .get(prefix)
+            .map(|subtree_state_sync| subtree_state_sync.pending_chunks.contains(local_chunk_id))
+            .unwrap_or(false)
+    }
+
+    // Decodes and verifies an incoming chunk of a pending chunk id against the
+    // restorer of its subtree, without modifying the state sync. Returns None
+    // for the empty chunk of an empty subtree, along with the time spent. An
+    // empty chunk for any other chunk id is a hash mismatch.
+    fn verify_incoming_chunk(
+        &self,
+        chunk_prefix: &SubtreePrefix,
+        chunk_id: &[u8],
+        global_chunk_id: &[u8],
+        chunk: IncomingChunk,
+        version: u16,
+    ) -> Result<(Option<VerifiedChunk>, ChunkTimings), ReplicationError> {
+        let restorer = self
+            .current_prefixes
+            .get(chunk_prefix)
+            .and_then(|subtree_state_sync| subtree_state_sync.restorer.as_ref())
+            .ok_or_else(|| {
+                ReplicationError::internal(
+                    global_chunk_id,
+                    Error::InternalError("Invalid internal state (restorer".to_string()),
+                )
+            })?;
+        let decode_failure = |e: Error| ReplicationError::DecodeFailure {
+            global_chunk_id: global_chunk_id.to_vec(),
+            reason: e.to_string(),
+        };
+        let restorer_chunk_id = util_decode_chunk_id(chunk_id, version).map_err(decode_failure)?;
+        match chunk {
+            IncomingChunk::Encoded(chunk_data) if chunk_data.is_empty() => {
+                // Only the root chunk of an empty subtree is empty
+                restorer
+                    .chunk_verifier(&restorer_chunk_id)
+                    .and_then(|verifier| verifier.verify_empty())
+                    .map_err(|e| ReplicationError::from_verification_error(global_chunk_id, e))?;
+                Ok((None, ChunkTimings::default()))
+            }
+            IncomingChunk::Encoded(chunk_data) => {
+                let started_at = Instant::now();
+                let ops = util_decode_vec_ops(chunk_data).map_err(decode_failure)?;
+                let decoded_at = Instant::now();
+                let verified_chunk = restorer
+                    .chunk_verifier(&restorer_chunk_id)
+                    .and_then(|verifier| verifier.verify(ops))
+                    .map_err(|e| ReplicationError::from_verification_error(global_chunk_id, e))?;
+                let timings = ChunkTimings {
+                    decode: decoded_at - started_at,
+                    verify: decoded_at.elapsed(),
+                };
+                Ok((Some(verified_chunk), timings))
+            }
+            IncomingChunk::Verified {
+                verified_chunk,
+                timings,
+                ..
+            } => {
+                if verified_chunk.chunk_id() != restorer_chunk_id {
+                    return Err(ReplicationError::UnexpectedChunk {
+                        global_chunk_id: global_chunk_id.to_vec(),
+                    });
+                }
+                Ok((Some(verified_chunk), timings))
+            }
+        }
+    }
+
+    /// Returns true once all discovered subtrees have been restored
+    pub fn is_sync_completed(&self) -> bool {
+        self.current_prefixes.is_empty() && !self.processed_prefixes.is_empty()
+    }
+}
+
+impl<'db> Default for MultiStateSyncInfo<'db> {
+    fn default() -> Self {
+        Self {
+            current_prefixes: BTreeMap::new(),
+            processed_prefixes: BTreeSet::new(),
+            unpersisted_processed_prefixes: vec![],
+            app_hash: [0; 32],
+            version: CURRENT_STATE_SYNC_VERSION,
+            observer: None,
+            subtrees_metadata: SubtreesMetadata::new(),
+            subtree_scope: None,
+            manifest: None,
+            diff_sync: false,
+            validation: false,
+            stats: StateSyncStats::default(),
+        }
+    }
+}
+
+// Struct containing information about current subtrees found in GroveDB
+pub struct SubtreesMetadata {
+    // Map of Prefix (Path digest) -> (Actual path, Parent Subtree actual_value_hash, Parent
+    // Subtree elem_value_hash) Note: Parent Subtree actual_value_hash, Parent Subtree
+    // elem_value_hash are needed when verifying the new constructed subtree after wards.
+    pub data: BTreeMap<SubtreePrefix, (Vec<Vec<u8>>, CryptoHash, CryptoHash)>,
+}
+
+impl SubtreesMetadata {
+    pub fn new() -> SubtreesMetadata {
+        SubtreesMetadata {
+            data: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for SubtreesMetadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for SubtreesMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (prefix, metadata) in self.data.iter() {
+            let metadata_path = &metadata.0;
+            let metadata_path_str = util_path_to_string(metadata_path);
+            writeln!(
+                f,
+                " prefix:{:?} -> path:{:?}",
+                hex::encode(prefix),
+                metadata_path_str
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Converts a path into a human-readable string (for debugging), segments
+// that aren't plain text are hex encoded
+pub fn util_path_to_string(path: &[Vec<u8>]) -> Vec<String> {
+    path.iter().map(|segment| hex_to_ascii(segment)).collect()
+}
+
+// Splits the given global chunk id into [SUBTREE_PREFIX:CHUNK_ID]
+pub fn util_split_global_chunk_id(
+    global_chunk_id: &[u8],
+    app_hash: &[u8],
+) -> Result<(crate::SubtreePrefix, Vec<u8>), Error> {
+    let chunk_prefix_length: usize = 32;
+    if global_chunk_id.len() < chunk_prefix_length {
+        return Err(Error::CorruptedData(
+            "expected global chunk id of at least 32 length".to_string(),
+        ));
+    }
+
+    if global_chunk_id == app_hash {
+        let array_of_zeros: [u8; 32] = [0; 32];
+        let root_chunk_prefix_key: crate::SubtreePrefix = array_of_zeros;
+        return Ok((root_chunk_prefix_key, vec![]));
+    }
+
+    let (chunk_prefix, chunk_id) = global_chunk_id.split_at(chunk_prefix_length);
+    let mut array = [0u8; 32];
+    array.copy_from_slice(chunk_prefix);
+    let chunk_prefix_key: crate::SubtreePrefix = array;
+    Ok((chunk_prefix_key, chunk_id.to_vec()))
+}
+
+// Converts a local chunk id in the encoding of the given state sync protocol
+// version into traversal instruction bytes (as used by Merk)
+pub fn util_decode_chunk_id(chunk_id: &[u8], version: u16) -> Result<Vec<u8>, Error> {
+    if version < COMPACT_CHUNK_ID_STATE_SYNC_VERSION {
+        return Ok(chunk_id.to_vec());
+    }
+    compact_bytes_as_vec_bytes(chunk_id)
+        .map_err(|e| Error::CorruptedData(format!("unable to decode chunk id: {}", e)))
+}
+
+// Converts traversal instruction bytes (as used by Merk) into a local chunk id in
+// the encoding of the given state sync protocol version
+pub fn util_encode_chunk_id(vec_bytes: &[u8], version: u16) -> Result<Vec<u8>, Error> {
+    if version < COMPACT_CHUNK_ID_STATE_SYNC_VERSION {
+        return Ok(vec_bytes.to_vec());
+    }
+    vec_bytes_as_compact_bytes(vec_bytes)
+        .map_err(|e| Error::CorruptedData(format!("unable to encode chunk id: {}", e)))
+}
+
+pub fn util_encode_vec_ops(chunk: Vec<Op>) -> Result<Vec<u8>, Error> {
+    let mut res = vec![];
+    for op in chunk {
+        op.encode_into(&mut res)
+            .map_err(|e| Error::CorruptedData(format!("unable to encode chunk: {}", e)))?;
+    }
+    Ok(res)
+}
+
+pub fn util_decode_vec_ops(chunk: Vec<u8>) -> Result<Vec<Op>, Error> {
+    let decoder = Decoder::new(&chunk);
+    let mut res = vec![];
+    for op in decoder {
+        match op {
+            Ok(op) => res.push(op),
+            Err(e) => {
+                return Err(Error::CorruptedData(format!(
+                    "unable to decode chunk: {}",
+                    e
+                )));
+            }
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    // Returns the discovered subtrees found recursively along with their associated
+    // metadata Params:
+    // tx: Transaction. Function returns the data by opening merks at given tx.
+    pub fn get_subtrees_metadata(
+        &self,
+        tx: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> Result<SubtreesMetadata, Error> {
+        self.get_subtrees_metadata_at_path(SubtreePath::empty(), tx, grove_version)
+    }
+
+    // Returns the subtree at the given path and its descendant subtrees found
+    // recursively along with their associated metadata Params:
+    // path: Path of the subtree to start searching from
+    // tx: Transaction. Function returns the data by opening merks at given tx.
+    pub fn get_subtrees_metadata_at_path<B: AsRef<[u8]>>(
+        &self,
+        path: SubtreePath<B>,
+        tx: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> Result<SubtreesMetadata, Error> {
+        check_grovedb_v0!(
+            "is_empty_tree",
+            grove_version
+                .grovedb_versions
+                .replication
+                .get_subtrees_metadata
+        );
+        let mut subtrees_metadata = SubtreesMetadata::new();
+
+        let subtrees_root = self.find_subtrees(&path, tx, grove_version).value?;
+        for subtree in subtrees_root.into_iter() {
+            let subtree_path: Vec<&[u8]> = subtree.iter().map(|vec| vec.as_slice()).collect();
+            let path: &[&[u8]] = &subtree_path;
+            let prefix = RocksDbStorage::build_prefix(path.as_ref().into()).unwrap();
+
+            let current_path = SubtreePath::from(path);
+
+            match (current_path.derive_parent(), subtree.last()) {
+                (Some((parent_path, _)), Some(parent_key)) => match tx {
+                    None => {
+                        let parent_merk = self
+                            .open_non_transactional_merk_at_path(parent_path, None, grove_version)
+                            .value?;
+                        if let Ok(Some((elem_value, elem_value_hash))) = parent_merk
+                            .get_value_and_value_hash(
+                                parent_key,
+                                true,
+                                None::<&fn(&[u8], &GroveVersion) -> Option<ValueDefinedCostType>>,
+                                grove_version,
+                            )
+                            .value
+                        {
+                            let actual_value_hash = value_hash(&elem_value).unwrap();
+                            subtrees_metadata.data.insert(
+                                prefix,
+                                (current_path.to_vec(), actual_value_hash, elem_value_hash),
+                            );
+                        }
+                    }
+                    Some(t) => {
+                        let parent_merk = self
+                            .open_transactional_merk_at_path(parent_path, t, None, grove_version)
+                            .value?;
+                        if let Ok(Some((elem_value, elem_value_hash))) = parent_merk
+                            .get_value_and_value_hash(
+                                parent_key,
+                                true,
+                                None::<&fn(&[u8], &GroveVersion) -> Option<ValueDefinedCostType>>,
+                                grove_version,
+                            )
+                            .value
+                        {
+                            let actual_value_hash = value_hash(&elem_value).unwrap();
+                            subtrees_metadata.data.insert(
+                                prefix,
+                                (current_path.to_vec(), actual_value_hash, elem_value_hash),
+                            );
+                        }
+                    }
+                },
+                _ => {
+                    subtrees_metadata.data.insert(
+                        prefix,
+                        (
+                            current_path.to_vec(),
+                            CryptoHash::default(),
+                            CryptoHash::default(),
+                        ),
+                    );
+                }
+            }
+        }
+        Ok(subtrees_metadata)
+    }
+
+    // Returns the metadata of the direct child subtrees of the subtree at the
+    // given path. Unlike get_subtrees_metadata() this only scans a single merk,
+    // which is used to discover subtrees incrementally while restoring.
+    // path: Path of the parent subtree
+    // tx: Transaction. Function returns the data by opening merks at given tx.
+    fn get_child_subtrees_metadata(
+        &self,
+        path: &[Vec<u8>],
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<SubtreesMetadata, Error> {
+        let mut subtrees_metadata = SubtreesMetadata::new();
+
+        let subtree_path: Vec<&[u8]> = path.iter().map(|vec| vec.as_slice()).collect();
+        let parent_path: &[&[u8]] = &subtree_path;
+        let merk = self
+            .open_transactional_merk_at_path(parent_path.into(), tx, None, grove_version)
+            .value?;
+
+        let mut all_query = Query::new();
+        all_query.insert_all();
+        let mut element_iterator = KVIterator::new(merk.storage.raw_iter(), &all_query).unwrap();
+
+        while let Some((key, element_value)) = element_iterator.next_kv().unwrap() {
+            let element = raw_decode(&element_value, grove_version)?;
+            if !element.is_any_tree() {
+                continue;
+            }
+            let (elem_value, elem_value_hash) = merk
+                .get_value_and_value_hash(
+                    &key,
+                    true,
+                    None::<&fn(&[u8], &GroveVersion) -> Option<ValueDefinedCostType>>,
+                    grove_version,
+                )
+                .value
+                .map_err(Error::MerkError)?
+                .ok_or(Error::CorruptedData(
+                    "expected merk to contain value of child subtree".to_string(),
+                ))?;
+            let actual_value_hash = value_hash(&elem_value).unwrap();
+
+            let mut child_path = path.to_vec();
+            child_path.push(key);
+            let child_subtree_path: Vec<&[u8]> =
+                child_path.iter().map(|vec| vec.as_slice()).collect();
+            let child_path_ref: &[&[u8]] = &child_subtree_path;
+            let prefix = RocksDbStorage::build_prefix(child_path_ref.into()).unwrap();
+
+            subtrees_metadata
+                .data
+                .insert(prefix, (child_path, actual_value_hash, elem_value_hash));
+        }
+        Ok(subtrees_metadata)
+    }
+
+    // Fetch a chunk by global chunk id (should be called by ABCI when
+    // LoadSnapshotChunk method is called) Params:
+    // global_chunk_id: Global chunk id in the following format:
+    // [SUBTREE_PREFIX:CHUNK_ID] SUBTREE_PREFIX: 32 bytes (mandatory) (All zeros
+    // = Root subtree) CHUNK_ID: 0.. bytes (optional) Traversal instructions to
+    // the root of the given chunk. Up to version 2 of the state sync protocol
+    // traversal instructions are one byte per step, "1" for left, and "0" for
+    // right. Since version 3 they are packed into bits, preceded by the number of
+    // bits used in the last byte (see traversal_instruction_as_compact_bytes()).
+    // tx: Transaction. Function returns the data by opening merks at given tx.
+    // Returns the Chunk proof operators for the requested chunk encoded in bytes
+    pub fn fetch_chunk(
+        &self,
+        global_chunk_id: &[u8],
+        tx: TransactionArg,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        check_grovedb_v0!(
+            "fetch_chunk",
+            grove_version.grovedb_versions.replication.fetch_chunk
+        );
+        check_state_sync_version(version)?;
+
+        let root_app_hash = self.root_hash(tx, grove_version).value?;
+        let (chunk_prefix, chunk_id) =
+            replication::util_split_global_chunk_id(global_chunk_id, &root_app_hash)?;
+        let chunk_id = util_decode_chunk_id(&chunk_id, version)?;
+
+        let subtrees_metadata = self.get_subtrees_metadata(tx, grove_version)?;
+
+        self.fetch_subtree_chunk(
+            &chunk_prefix,
+            &chunk_id,
+            &subtrees_metadata,
+            tx,
+            grove_version,
+        )
+    }
+
+    // Fetch a chunk of the subtree with the given prefix, looking up the subtree
+    // in the given (possibly cached) subtrees metadata
+    // chunk_prefix: Subtree prefix (Path digest)
+    // chunk_id: Local chunk id
+    // subtrees_metadata: Metadata about subtrees found in GroveDB
+    // tx: Transaction. Function returns the data by opening merks at given tx.
+    // Returns the Chunk proof operators for the requested chunk encoded in bytes
+    pub(crate) fn fetch_subtree_chunk(
+        &self,
+        chunk_prefix: &SubtreePrefix,
+        chunk_id: &[u8],
+        subtrees_metadata: &SubtreesMetadata,
+        tx: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        match subtrees_metadata.data.get(chunk_prefix) {
+            Some(path_data) => {
+                let subtree = &path_data.0;
+                let subtree_path: Vec<&[u8]> = subtree.iter().map(|vec| vec.as_slice()).collect();
+                let path: &[&[u8]] = &subtree_path;
+
+                match tx {
+                    None => {
+                        let merk = self
+                            .open_non_transactional_merk_at_path(path.into(), None, grove_version)
+                            .value?;
+
+                        if merk.is_empty_tree().unwrap() {
+                            return Ok(vec![]);
+                        }
+
+                        let chunk_producer_res = ChunkProducer::new(&merk);
+                        match chunk_producer_res {
+                            Ok(mut chunk_producer) => {
+                                let chunk_res = chunk_producer.chunk(chunk_id, grove_version);
+                                match chunk_res {
+                                    Ok((chunk, _)) => match util_encode_vec_ops(chunk) {
+                                        Ok(op_bytes) => Ok(op_bytes),
+                                        Err(_) => Err(Error::CorruptedData(
+                                            "Unable to create to load chunk".to_string(),
+                                        )),
+                                    },
+                                    Err(_) => Err(Error::CorruptedData(
+                                        "Unable to create to load chunk".to_string(),
+                                    )),
+                                }
+                            }
+                            Err(_) => Err(Error::CorruptedData(
+                                "Unable to create Chunk producer".to_string(),
+                            )),
+                        }
+                    }
+                    Some(t) => {
+                        let merk = self
+                            .open_transactional_merk_at_path(path.into(), t, None, grove_version)
+                            .value?;
+
+                        if merk.is_empty_tree().unwrap() {
+                            return Ok(vec![]);
+                        }
+
+                        let chunk_producer_res = ChunkProducer::new(&merk);
+                        match chunk_producer_res {
+                            Ok(mut chunk_producer) => {
+                                let chunk_res = chunk_producer.chunk(chunk_id, grove_version);
+                                match chunk_res {
+                                    Ok((chunk, _)) => match util_encode_vec_ops(chunk) {
+                                        Ok(op_bytes) => Ok(op_bytes),
+                                        Err(_) => Err(Error::CorruptedData(
+                                            "Unable to create to load chunk".to_string(),
+                                        )),
+                                    },
+                                    Err(_) => Err(Error::CorruptedData(
+                                        "Unable to create to load chunk".to_string(),
+                                    )),
+                                }
+                            }
+                            Err(_) => Err(Error::CorruptedData(
+                                "Unable to create Chunk producer".to_string(),
+                            )),
+                        }
+                    }
+                }
+            }
+            None => Err(Error::CorruptedData("Prefix not found".to_string())),
+        }
+    }
+
+    // Starts a state sync process (should be called by ABCI when OfferSnapshot
+    // method is called) Params:
+    // state_sync_info: Consumed StateSyncInfo
+    // app_hash: Snapshot's AppHash
+    // tx: Transaction for the state sync
+    // Returns the StateSyncInfo transferring ownership back to the caller)
+    // If the progress of an interrupted state sync of the same app_hash was
+    // persisted (and committed) in tx, the state sync is resumed from it. The
+    // chunks to fetch next are given by
+    // MultiStateSyncInfo::pending_global_chunk_ids().
+    pub fn start_snapshot_syncing<'db>(
+        &'db self,
+        state_sync_info: MultiStateSyncInfo<'db>,
+        app_hash: CryptoHash,
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<MultiStateSyncInfo, Error> {
+        check_grovedb_v0!(
+            "start_snapshot_syncing",
+            grove_version
+                .grovedb_versions
+                .replication
+                .start_snapshot_syncing
+        );
+        let observer = state_sync_info.observer.clone();
+        let result = self.start_or_resume_snapshot_syncing(
+            state_sync_info,
+            app_hash,
+            None,
+            tx,
+            version,
+            grove_version,
+        );
+        if let (Err(error), Some(observer)) = (&result, observer) {
+            observer.on_event(StateSyncEvent::Error {
+                global_chunk_id: None,
+                error,
+            });
+        }
+        result
+    }
+
+    // Starts or resumes a state sync, restricted to the given proven subtree if
+    // any
+    fn start_or_resume_snapshot_syncing<'db>(
+        &'db self,
+        mut state_sync_info: MultiStateSyncInfo<'db>,
+        app_hash: CryptoHash,
+        subtree: Option<ProvenSubtree>,
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<MultiStateSyncInfo, Error> {
+        check_state_sync_version(version)?;
+        if version != state_sync_info.version {
+            return Err(Error::CorruptedData(
+                "Unsupported state sync protocol version".to_string(),
+            ));
+        }
+
+        if !state_sync_info.current_prefixes.is_empty()
+            || !state_sync_info.processed_prefixes.is_empty()
+        {
+            return Err(Error::InternalError(
+                "GroveDB has already started a snapshot syncing".to_string(),
+            ));
+        }
+
+        if state_sync_info.diff_sync {
+            if subtree.is_some() {
+                return Err(Error::InvalidParameter(
+                    "diff sync can't be restricted to a subtree",
+                ));
+            }
+            state_sync_info.manifest = None;
+        }
+
+        if let Some(manifest) = &state_sync_info.manifest {
+            if manifest.app_hash != app_hash {
+                return Err(Error::CorruptedData(
+                    "snapshot manifest doesn't match the app_hash".to_string(),
+                ));
+            }
+        }
+
+        if let Some(mut persisted_state_sync_info) = self.load_state_sync_info(tx, grove_version)? {
+            let persisted_subtree_path = persisted_state_sync_info
+                .subtree_scope
+                .as_ref()
+                .map(|subtree_scope| subtree_scope.path.as_slice());
+            if persisted_state_sync_info.app_hash != app_hash
+                || persisted_state_sync_info.version != version
+                || persisted_subtree_path != subtree.as_ref().map(ProvenSubtree::path)
+                || persisted_state_sync_info.diff_sync != state_sync_info.diff_sync
+            {
+                return Err(Error::InternalError(
+                    "GroveDB has an interrupted snapshot syncing of a different snapshot"
+                        .to_string(),
+                ));
+            }
+            persisted_state_sync_info.observer = state_sync_info.observer.take();
+            persisted_state_sync_info.manifest = state_sync_info.manifest.take();
+            persisted_state_sync_info.validation |= state_sync_info.validation;
+            persisted_state_sync_info.stats = mem::take(&mut state_sync_info.stats);
+            persisted_state_sync_info.stats.sync_started();
+            persisted_state_sync_info.notify(StateSyncEvent::SyncStarted {
+                app_hash,
+                resumed: true,
+            });
+            return Ok(persisted_state_sync_info);
+        }
+
+        state_sync_info.stats.sync_started();
+        state_sync_info.notify(StateSyncEvent::SyncStarted {
+            app_hash,
+            resumed: false,
+        });
+
+        if let Some(subtree) = subtree {
+            self.start_subtree_restoration(&mut state_sync_info, subtree, tx, grove_version)?;
+            state_sync_info.check_subtrees_against_manifest(&state_sync_info.subtrees_metadata)?;
+            state_sync_info.app_hash = app_hash;
+            self.persist_state_sync_info(&mut state_sync_info, tx)?;
+            return Ok(state_sync_info);
+        }
+
+        let root_prefix = [0u8; 32];
+        state_sync_info.app_hash = app_hash;
+        if state_sync_info.diff_sync
+            && self.root_hash(Some(tx), grove_version).unwrap()? == app_hash
+        {
+            // The replica is already in sync
+            state_sync_info.mark_prefix_processed(root_prefix);
+            self.validate_state_sync(&state_sync_info, tx, grove_version)?;
+            state_sync_info.stats.sync_completed();
+            state_sync_info.notify(StateSyncEvent::SyncCompleted {
+                app_hash,
+                num_processed_subtrees: 0,
+            });
+            return Ok(state_sync_info);
+        }
+
+        state_sync_info.notify(StateSyncEvent::SubtreeStarted { path: &[] });
+        state_sync_info.stats.subtree_started(&[]);
+
+        let mut root_prefix_state_sync_info = SubtreeStateSyncInfo::default();
+        if let Ok(mut merk) =
+            self.open_merk_for_replication(SubtreePath::empty(), tx, grove_version)
+        {
+            if state_sync_info.diff_sync {
+                root_prefix_state_sync_info.replaced_subtrees =
+                    self.prepare_diff_restoration(&[], &mut merk, tx, grove_version)?;
+            }
+            let restorer = Restorer::new(merk, app_hash, None);
+            root_prefix_state_sync_info.restorer = Some(restorer);
+            root_prefix_state_sync_info.pending_chunks.insert(vec![]);
+            root_prefix_state_sync_info.expected_root_hash = app_hash;
+            state_sync_info
+                .current_prefixes
+                .insert(root_prefix, root_prefix_state_sync_info);
+            state_sync_info.subtrees_metadata.data.insert(
+                root_prefix,
+                (vec![], CryptoHash::default(), CryptoHash::default()),
+            );
+        } else {
+            return Err(Error::InternalError(
+                "Unable to open merk for replication".to_string(),
+            ));
+        }
+
+        self.persist_state_sync_info(&mut state_sync_info, tx)?;
+
+        Ok(state_sync_info)
+    }
+
+    // Apply a chunk (should be called by ABCI when ApplySnapshotChunk method is
+    // called) Params:
+    // state_sync_info: Consumed MultiStateSyncInfo
+    // global_chunk_id: Global chunk id
+    // chunk: Chunk proof operators encoded in bytes
+    // tx: Transaction for the state sync
+    // Returns the next set of global chunk ids that can be fetched from sources (+
+    // the MultiStateSyncInfo transferring ownership back to the caller)
+    // The progress of the state sync is persisted in tx along with the chunk.
+    // Chunks of subtree-scoped state syncs (see start_subtree_snapshot_syncing())
+    // are applied the same way.
+    pub fn apply_chunk<'db>(
+        &'db self,
+        state_sync_info: MultiStateSyncInfo<'db>,
+        global_chunk_id: &[u8],
+        chunk: Vec<u8>,
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<(Vec<Vec<u8>>, MultiStateSyncInfo<'db>), ApplyChunkError<'db>> {
+        if let Err(error) = check_replication_method_version(
+            "apply_chunk",
+            grove_version.grovedb_versions.replication.apply_chunk,
+        ) {
+            return Err(state_sync_info.fail(ReplicationError::internal(global_chunk_id, error)));
+        }
+        let result = self.apply_global_chunk(
+            state_sync_info,
+            global_chunk_id,
+            IncomingChunk::Encoded(chunk),
+            tx,
+            version,
+            grove_version,
+        );
+        if let Err(error) = &result {
+            error.state_sync_info.notify_error(&error.error);
+        }
+        result
+    }
+
+    // Applies a chunk, see apply_chunk(). The chunk is verified before the
+    // state sync is modified, so that on any recoverable error the state sync is
+    // handed back as it was.
+    pub(crate) fn apply_global_chunk<'db>(
+        &'db self,
+        mut state_sync_info: MultiStateSyncInfo<'db>,
+        global_chunk_id: &[u8],
+        chunk: IncomingChunk,
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<(Vec<Vec<u8>>, MultiStateSyncInfo<'db>), ApplyChunkError<'db>> {
+        if check_state_sync_version(version).is_err() || version != state_sync_info.version {
+            return Err(state_sync_info.fail(ReplicationError::WrongVersion {
+                global_chunk_id: global_chunk_id.to_vec(),
+                version,
+            }));
+        }
+
+        let (chunk_prefix, chunk_id) =
+            match util_split_global_chunk_id(global_chunk_id, &state_sync_info.app_hash) {
+                Ok(ids) => ids,
+                Err(e) => {
+                    return Err(state_sync_info.fail(ReplicationError::DecodeFailure {
+                        global_chunk_id: global_chunk_id.to_vec(),
+                        reason: e.to_string(),
+                    }))
+                }
+            };
+        if !state_sync_info.is_chunk_pending(&chunk_prefix, &chunk_id) {
+            return Err(state_sync_info.fail(ReplicationError::UnexpectedChunk {
+                global_chunk_id: global_chunk_id.to_vec(),
+            }));
+        }
+
+        let chunk_size = chunk.len();
+        let (verified_chunk, timings) = match state_sync_info.verify_incoming_chunk(
+            &chunk_prefix,
+            &chunk_id,
+            global_chunk_id,
+            chunk,
+            version,
+        ) {
+            Ok(verified_chunk) => verified_chunk,
+            Err(error) => return Err(state_sync_info.fail(error)),
+        };
+
+        let path = state_sync_info
+            .current_prefixes
+            .get(&chunk_prefix)
+            .map(|subtree_state_sync| subtree_state_sync.path.clone())
+            .unwrap_or_default();
+        let started_at = Instant::now();
+        match self.apply_verified_chunk(
+            &mut state_sync_info,
+            chunk_prefix,
+            &chunk_id,
+            global_chunk_id,
+            verified_chunk,
+            chunk_size,
+            tx,
+            version,
+            grove_version,
+        ) {
+            Ok(next_chunk_ids) => {
+                state_sync_info.stats.chunk_applied(
+                    &path,
+                    chunk_size,
+                    timings,
+                    started_at.elapsed(),
+                );
+                Ok((next_chunk_ids, state_sync_info))
+            }
+            Err(error) => {
+                Err(state_sync_info.fail(ReplicationError::internal(global_chunk_id, error)))
+            }
+        }
+    }
+
+    // Applies a verified chunk (None for the empty chunk of an empty subtree)
+    // of a pending chunk id, finalizing its subtree once it has no pending
+    // chunks left
+    // Returns the next set of global chunk ids that can be fetched from sources
+    #[allow(clippy::too_many_arguments)]
+    fn apply_verified_chunk<'db>(
+        &'db self,
+        state_sync_info: &mut MultiStateSyncInfo<'db>,
+        chunk_prefix: SubtreePrefix,
+        chunk_id: &[u8],
+        global_chunk_id: &[u8],
+        verified_chunk: Option<VerifiedChunk>,
+        chunk_size: usize,
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut next_chunk_ids = vec![];
+
+        let subtree_state_sync = state_sync_info
+            .current_prefixes
+            .get_mut(&chunk_prefix)
+            .ok_or(Error::InternalError("Invalid incoming prefix".to_string()))?;
+        let res = self.apply_inner_chunk(
+            subtree_state_sync,
+            chunk_id,
+            verified_chunk,
+            version,
+            grove_version,
+        )?;
+        let subtree_state_sync = &state_sync_info.current_prefixes[&chunk_prefix];
+        state_sync_info.notify(StateSyncEvent::ChunkApplied {
+            path: &subtree_state_sync.path,
+            global_chunk_id,
+            chunk_size,
+        });
+        for local_chunk_id in res.iter() {
+            let mut next_global_chunk_id = chunk_prefix.to_vec();
+            next_global_chunk_id.extend(local_chunk_id.to_vec());
+            next_chunk_ids.push(next_global_chunk_id);
+        }
+        if !subtree_state_sync.pending_chunks.is_empty() {
+            self.persist_state_sync_info(state_sync_info, tx)?;
+            return Ok(next_chunk_ids);
+        }
+
+        // Subtree is finished. We can save it.
+        let mut subtree_state_sync = state_sync_info
+            .current_prefixes
+            .remove(&chunk_prefix)
+            .ok_or(Error::InternalError("Invalid incoming prefix".to_string()))?;
+        let restorer = subtree_state_sync
+            .restorer
+            .take()
+            .ok_or(Error::InternalError(
+                "Unable to finalize subtree".to_string(),
+            ))?;
+        let mut restored_root = None;
+        if subtree_state_sync.num_processed_chunks > 0 {
+            let merk = restorer
+                .finalize(grove_version)
+                .map_err(|_| Error::InternalError("Unable to finalize Merk".to_string()))?;
+            restored_root = Some(
+                merk.root_hash_key_and_sum()
+                    .unwrap()
+                    .map_err(Error::MerkError)?,
+            );
+        }
+        // The root subtree of a subtree-scoped state sync still has to be
+        // attached to its parent
+        if let Some(subtree_scope) = &state_sync_info.subtree_scope {
+            if subtree_scope.path == subtree_state_sync.path {
+                self.attach_restored_subtree(subtree_scope, restored_root, tx, grove_version)?;
+            }
+        }
+        state_sync_info.mark_prefix_processed(chunk_prefix);
+        state_sync_info
+            .stats
+            .subtree_finished(&subtree_state_sync.path);
+        state_sync_info.notify(StateSyncEvent::SubtreeFinalized {
+            path: &subtree_state_sync.path,
+            num_processed_chunks: subtree_state_sync.num_processed_chunks,
+        });
+
+        // Subtree was successfully save. Time to discover new subtrees that
+        // need to be processed. Only the children of the finished subtree can
+        // be new.
+        let subtrees_metadata =
+            self.get_child_subtrees_metadata(&subtree_state_sync.path, tx, grove_version)?;
+        state_sync_info.check_subtrees_against_manifest(&subtrees_metadata)?;
+        if state_sync_info.diff_sync {
+            self.delete_replaced_subtrees(
+                &subtree_state_sync.replaced_subtrees,
+                &subtrees_metadata,
+                tx,
+                grove_version,
+            )?;
+        }
+        let res = self
+            .discover_subtrees(
+                state_sync_info,
+                subtrees_metadata,
+                &subtree_state_sync.replaced_subtrees,
+                tx,
+                grove_version,
+            )
+            .map_err(|_| Error::InternalError("Unable to discover Subtrees".to_string()))?;
+        next_chunk_ids.extend(res);
+        if state_sync_info.current_prefixes.is_empty() {
+            state_sync_info.check_completed_against_manifest()?;
+            if state_sync_info.diff_sync {
+                self.check_diff_synced_root_hash(&state_sync_info.app_hash, tx, grove_version)?;
+            }
+            self.validate_state_sync(state_sync_info, tx, grove_version)?;
+        }
+        self.persist_state_sync_info(state_sync_info, tx)?;
+        if state_sync_info.current_prefixes.is_empty() {
+            state_sync_info.stats.sync_completed();
+            state_sync_info.notify(StateSyncEvent::SyncCompleted {
+                app_hash: state_sync_info.app_hash,
+                num_processed_subtrees: state_sync_info.processed_prefixes.len(),
+            });
+        }
+        Ok(next_chunk_ids)
+    }
+
+    // Apply a verified chunk using the given SubtreeStateSyncInfo
+    // state_sync_info: SubtreeStateSyncInfo of the chunk's subtree
+    // chunk_id: Local chunk id (in the encoding of the given version)
+    // verified_chunk: Verified chunk proof (None for the empty chunk of an empty
+    // subtree)
+    // Returns the next set of local chunk ids that can be fetched from sources
+    fn apply_inner_chunk<'db>(
+        &'db self,
+        state_sync_info: &mut SubtreeStateSyncInfo<'db>,
+        chunk_id: &[u8],
+        verified_chunk: Option<VerifiedChunk>,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut res = vec![];
+
+        match &mut state_sync_info.restorer {
+            Some(restorer) => {
+                if !state_sync_info.pending_chunks.remove(chunk_id) {
+                    return Err(Error::InternalError(
+                        "Incoming global_chunk_id not expected".to_string(),
+                    ));
+                }
+                if let Some(verified_chunk) = verified_chunk {
+                    match restorer.process_verified_chunk(verified_chunk, grove_version) {
+                        Ok(next_chunk_ids) => {
+                            state_sync_info.num_processed_chunks += 1;
+                            for next_chunk_id in next_chunk_ids {
+                                let next_chunk_id = util_encode_chunk_id(&next_chunk_id, version)?;
+                                state_sync_info.pending_chunks.insert(next_chunk_id.clone());
+                                res.push(next_chunk_id);
+                            }
+                        }
+                        _ => {
+                            return Err(Error::InternalError(
+                                "Unable to process incoming chunk".to_string(),
+                            ));
+                        }
+                    };
+                }
+            }
+            _ => {
+                return Err(Error::InternalError(
+                    "Invalid internal state (restorer".to_string(),
+                ));
+            }
+        }
+
+        Ok(res)
+    }
+
+    // Prepares SubtreeStateSyncInfos for the freshly discovered subtrees in
+    // subtrees_metadata and returns the root global chunk ids for all of those
+    // new subtrees. state_sync_info: MultiStateSyncInfo to add the new subtrees to
+    // subtrees_metadata: Metadata about discovered subtrees
+    // replaced_subtrees: Subtrees the replica had in place of the discovered
+    // ones (diff syncs only). Identical subtrees are not restored again.
+    // Returns the next set of global chunk ids that can be fetched from sources
+    fn discover_subtrees<'db>(
+        &'db self,
+        state_sync_info: &mut MultiStateSyncInfo<'db>,
+        subtrees_metadata: SubtreesMetadata,
+        replaced_subtrees: &ReplacedSubtrees,
+        tx: &'db Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut res = vec![];
+
+        for (prefix, prefix_metadata) in &subtrees_metadata.data {
+            if !state_sync_info.processed_prefixes.contains(prefix)
+                && !state_sync_info.current_prefixes.contains_key(prefix)
+            {
+                let (current_path, s_actual_value_hash, s_elem_value_hash) = &prefix_metadata;
+
+                // The value hash of a subtree's element commits to the subtree's
+                // root hash, so a subtree with the same value hash in the
+                // restored parent is identical with all its descendants
+                if replaced_subtrees
+                    .get(prefix)
+                    .map(|(_, elem_value_hash)| elem_value_hash)
+                    == Some(s_elem_value_hash)
+                {
+                    state_sync_info.mark_prefix_processed(*prefix);
+                    state_sync_info.stats.subtrees_skipped += 1;
+                    continue;
+                }
+
+                let subtree_path: Vec<&[u8]> =
+                    current_path.iter().map(|vec| vec.as_slice()).collect();
+                let path: &[&[u8]] = &subtree_path;
+                state_sync_info.notify(StateSyncEvent::SubtreeStarted {
+                    path: current_path.as_slice(),
+                });
+                state_sync_info.stats.subtree_started(current_path);
+
+                let mut subtree_state_sync_info = SubtreeStateSyncInfo::default();
+                if let Ok(mut merk) = self.open_merk_for_replication(path.into(), tx, grove_version)
+                {
+                    if state_sync_info.diff_sync {
+                        subtree_state_sync_info.replaced_subtrees = self.prepare_diff_restoration(
+                            current_path,
+                            &mut merk,
+                            tx,
+                            grove_version,
+                        )?;
+                    }
+                    let restorer =
+                        Restorer::new(merk, *s_elem_value_hash, Some(*s_actual_value_hash));
+                    subtree_state_sync_info.restorer = Some(restorer);
+                    subtree_state_sync_info.pending_chunks.insert(vec![]);
+                    subtree_state_sync_info.path = current_path.clone();
+                    subtree_state_sync_info.expected_root_hash = *s_elem_value_hash;
+                    subtree_state_sync_info.parent_value_hash = Some(*s_actual_value_hash);
+
+                    state_sync_info
+                        .current_prefixes
+                        .insert(*prefix, subtree_state_sync_info);
+
+                    let root_chunk_prefix = prefix.to_vec();
+                    res.push(root_chunk_prefix.to_vec());
+                } else {
+                    return Err(Error::InternalError(
+                        "Unable to open Merk for replication".to_string(),
+                    ));
+                }
+            }
+        }
+        state_sync_info
+            .subtrees_metadata
+            .data
+            .extend(subtrees_metadata.data);
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,396 @@
+//! Multi-chunk transfer (state sync protocol version 2)
+//!
+//! Version 1 of the state sync protocol moves a single chunk per round trip.
+//! Since version 2 a source can pack several chunks, possibly of different
+//! subtrees, into one multi-chunk whose encoded size stays within a byte limit
+//! chosen by the requesting replica. Within a subtree the chunks are produced
+//! by `ChunkProducer::multi_chunk_with_limit`, which also merges adjacent
+//! chunks into a single one whenever they fit.
+
+use std::collections::BTreeSet;
+
+use bincode::{config, Decode, Encode};
+use grovedb_merk::{
+    ed::{Decode as _, Encode as _},
+    error::Error as MerkError,
+    proofs::chunk::{
+        chunk_op::ChunkOp, error::ChunkError, util::traversal_instruction_as_vec_bytes,
+    },
+    ChunkProducer, Merk,
+};
+use grovedb_storage::StorageContext;
+use grovedb_version::{check_grovedb_v0, error::GroveVersionError, version::GroveVersion};
+
+use crate::{
+    replication::{
+        check_replication_method_version, check_state_sync_version, util_decode_chunk_id,
+        util_encode_chunk_id, util_encode_vec_ops, util_split_global_chunk_id, ApplyChunkError,
+        IncomingChunk, MultiStateSyncInfo, ReplicationError, SubtreePrefix, SubtreesMetadata,
+    },
+    Error, GroveDb, Transaction, TransactionArg,
+};
+
+/// First version of the state sync protocol supporting multi-chunks
+const MULTI_CHUNK_STATE_SYNC_VERSION: u16 = 2;
+
+/// Maximum length of a bincode varint encoded length
+const MAX_ENCODED_LENGTH_SIZE: usize = 9;
+
+/// Maximum encoding overhead of a `SubtreeMultiChunk` on top of its chunk ops
+const SUBTREE_MULTI_CHUNK_OVERHEAD: usize = blake3::OUT_LEN + MAX_ENCODED_LENGTH_SIZE;
+
+/// Chunks of a single subtree within a multi-chunk
+#[derive(Encode, Decode)]
+struct SubtreeMultiChunk {
+    // Subtree prefix (Path digest)
+    prefix: SubtreePrefix,
+    // Encoded chunk ops, each chunk being preceded by its local chunk id. Empty
+    // for an empty subtree.
+    chunk_ops: Vec<u8>,
+}
+
+// Returns an error if the given version of the state sync protocol does not
+// support multi-chunks
+pub(super) fn check_multi_chunk_version(version: u16) -> Result<(), Error> {
+    check_state_sync_version(version)?;
+    if version < MULTI_CHUNK_STATE_SYNC_VERSION {
+        return Err(Error::CorruptedData(
+            "Multi-chunks are not supported by this state sync protocol version".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Packs as many chunks of the given merk as fit in limit, starting at the given
+// local chunk id. Returns the encoded chunk ops along with the local chunk ids
+// of the packed chunks, or None if not even the first chunk fits.
+fn multi_chunk_from_merk<'db, S: StorageContext<'db>>(
+    merk: &'db Merk<S>,
+    chunk_id: &[u8],
+    limit: usize,
+    grove_version: &GroveVersion,
+) -> Result<Option<(Vec<u8>, Vec<Vec<u8>>)>, Error> {
+    if merk.is_empty_tree().unwrap() {
+        return Ok(Some((vec![], vec![chunk_id.to_vec()])));
+    }
+
+    let mut chunk_producer = ChunkProducer::new(merk)
+        .map_err(|_| Error::CorruptedData("Unable to create Chunk producer".to_string()))?;
+    let mut producer_limit = limit;
+    loop {
+        let multi_chunk = match chunk_producer.multi_chunk_with_limit(
+            chunk_id,
+            Some(producer_limit),
+            grove_version,
+        ) {
+            Ok(multi_chunk) => multi_chunk,
+            Err(MerkError::ChunkingError(ChunkError::LimitTooSmall(_))) => return Ok(None),
+            Err(_) => {
+                return Err(Error::CorruptedData(
+                    "Unable to create to load chunk".to_string(),
+                ))
+            }
+        };
+
+        let mut chunk_ops = vec![];
+        let mut chunk_ids = vec![];
+        for chunk_op in multi_chunk.chunk {
+            if let ChunkOp::ChunkId(instruction) = &chunk_op {
+                chunk_ids.push(traversal_instruction_as_vec_bytes(instruction));
+            }
+            chunk_op
+                .encode_into(&mut chunk_ops)
+                .map_err(|e| Error::CorruptedData(format!("unable to encode chunk: {}", e)))?;
+        }
+        if chunk_ops.len() <= limit {
+            return Ok(Some((chunk_ops, chunk_ids)));
+        }
+        // The chunk producer doesn't account for the headers of the chunk ops,
+        // retry with the limit reduced by the excess
+        producer_limit = producer_limit.saturating_sub(chunk_ops.len() - limit);
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    // Fetch a multi-chunk (requires state sync protocol version 2) Params:
+    // global_chunk_ids: Global chunk ids to pack, in order of priority. For the
+    // description of global chunk id check fetch_chunk().
+    // limit: Maximum size of the returned multi-chunk in bytes
+    // tx: Transaction. Function returns the data by opening merks at given tx.
+    // Returns as many of the requested chunks (and the chunks following them in
+    // their subtrees) as fit in limit, encoded in bytes. Fails if not even the
+    // first requested chunk fits.
+    pub fn fetch_multi_chunk(
+        &self,
+        global_chunk_ids: &[Vec<u8>],
+        limit: usize,
+        tx: TransactionArg,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        check_grovedb_v0!(
+            "fetch_multi_chunk",
+            grove_version.grovedb_versions.replication.fetch_multi_chunk
+        );
+        check_multi_chunk_version(version)?;
+
+        let root_app_hash = self.root_hash(tx, grove_version).value?;
+        let subtrees_metadata = self.get_subtrees_metadata(tx, grove_version)?;
+
+        self.pack_multi_chunk(
+            global_chunk_ids,
+            limit,
+            &root_app_hash,
+            &subtrees_metadata,
+            tx,
+            version,
+            grove_version,
+        )
+    }
+
+    // Packs the requested chunks into a multi-chunk of at most limit bytes,
+    // looking up the subtrees in the given (possibly cached) subtrees metadata
+    pub(crate) fn pack_multi_chunk(
+        &self,
+        global_chunk_ids: &[Vec<u8>],
+        limit: usize,
+        app_hash: &[u8],
+        subtrees_metadata: &SubtreesMetadata,
+        tx: TransactionArg,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        let mut subtree_multi_chunks = vec![];
+        let mut packed_chunk_ids = BTreeSet::new();
+        let mut remaining_limit = limit.saturating_sub(MAX_ENCODED_LENGTH_SIZE);
+
+        for global_chunk_id in global_chunk_ids {
+            let (chunk_prefix, chunk_id) = util_split_global_chunk_id(global_chunk_id, app_hash)?;
+            let chunk_id = util_decode_chunk_id(&chunk_id, version)?;
+            // The chunk may already be part of the multi-chunk of a previously
+            // requested chunk
+            if packed_chunk_ids.contains(&(chunk_prefix, chunk_id.clone())) {
+                continue;
+            }
+            let Some(chunk_limit) = remaining_limit.checked_sub(SUBTREE_MULTI_CHUNK_OVERHEAD)
+            else {
+                break;
+            };
+            let Some((chunk_ops, chunk_ids)) = self.fetch_subtree_multi_chunk(
+                &chunk_prefix,
+                &chunk_id,
+                chunk_limit,
+                subtrees_metadata,
+                tx,
+                grove_version,
+            )?
+            else {
+                break;
+            };
+
+            remaining_limit = chunk_limit - chunk_ops.len();
+            packed_chunk_ids.extend(chunk_ids.into_iter().map(|id| (chunk_prefix, id)));
+            subtree_multi_chunks.push(SubtreeMultiChunk {
+                prefix: chunk_prefix,
+                chunk_ops,
+            });
+        }
+
+        if subtree_multi_chunks.is_empty() && !global_chunk_ids.is_empty() {
+            return Err(Error::InvalidParameter(
+                "limit too small to fit the first requested chunk",
+            ));
+        }
+
+        let config = config::standard().with_big_endian().with_no_limit();
+        bincode::encode_to_vec(subtree_multi_chunks, config)
+            .map_err(|e| Error::CorruptedData(format!("unable to encode multi chunk {}", e)))
+    }
+
+    // Packs as many chunks of the subtree with the given prefix as fit in limit,
+    // starting at the given local chunk id
+    fn fetch_subtree_multi_chunk(
+        &self,
+        chunk_prefix: &SubtreePrefix,
+        chunk_id: &[u8],
+        limit: usize,
+        subtrees_metadata: &SubtreesMetadata,
+        tx: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> Result<Option<(Vec<u8>, Vec<Vec<u8>>)>, Error> {
+        let Some((subtree, ..)) = subtrees_metadata.data.get(chunk_prefix) else {
+            return Err(Error::CorruptedData("Prefix not found".to_string()));
+        };
+        let subtree_path: Vec<&[u8]> = subtree.iter().map(|vec| vec.as_slice()).collect();
+        let path: &[&[u8]] = &subtree_path;
+
+        match tx {
+            None => {
+                let merk = self
+                    .open_non_transactional_merk_at_path(path.into(), None, grove_version)
+                    .value?;
+                multi_chunk_from_merk(&merk, chunk_id, limit, grove_version)
+            }
+            Some(t) => {
+                let merk = self
+                    .open_transactional_merk_at_path(path.into(), t, None, grove_version)
+                    .value?;
+                multi_chunk_from_merk(&merk, chunk_id, limit, grove_version)
+            }
+        }
+    }
+
+    // Apply a multi-chunk (requires state sync protocol version 2) Params:
+    // state_sync_info: Consumed MultiStateSyncInfo
+    // multi_chunk: Multi-chunk encoded in bytes, as returned by
+    // fetch_multi_chunk()
+    // tx: Transaction for the state sync
+    // Returns the next set of global chunk ids that can be fetched from sources (+
+    // the MultiStateSyncInfo transferring ownership back to the caller)
+    // Chunks of the multi-chunk that are not pending (e.g. because they were
+    // already applied) are skipped. On failure the state sync is handed back,
+    // with the chunks preceding the failing one applied.
+    pub fn apply_multi_chunk<'db>(
+        &'db self,
+        state_sync_info: MultiStateSyncInfo<'db>,
+        multi_chunk: Vec<u8>,
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<(Vec<Vec<u8>>, MultiStateSyncInfo<'db>), ApplyChunkError<'db>> {
+        if let Err(error) = check_replication_method_version(
+            "apply_multi_chunk",
+            grove_version.grovedb_versions.replication.apply_multi_chunk,
+        ) {
+            return Err(state_sync_info.fail(ReplicationError::internal(&[], error)));
+        }
+        let result =
+            self.apply_global_multi_chunk(state_sync_info, multi_chunk, tx, version, grove_version);
+        if let Err(error) = &result {
+            error.state_sync_info.notify_error(&error.error);
+        }
+        result
+    }
+
+    fn apply_global_multi_chunk<'db>(
+        &'db self,
+        mut state_sync_info: MultiStateSyncInfo<'db>,
+        multi_chunk: Vec<u8>,
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<(Vec<Vec<u8>>, MultiStateSyncInfo<'db>), ApplyChunkError<'db>> {
+        if check_multi_chunk_version(version).is_err() || version != state_sync_info.version {
+            return Err(state_sync_info.fail(ReplicationError::WrongVersion {
+                global_chunk_id: vec![],
+                version,
+            }));
+        }
+
+        let config = config::standard().with_big_endian().with_no_limit();
+        let subtree_multi_chunks: Vec<SubtreeMultiChunk> =
+            match bincode::decode_from_slice(&multi_chunk, config) {
+                Ok((subtree_multi_chunks, _)) => subtree_multi_chunks,
+                Err(e) => {
+                    return Err(state_sync_info.fail(multi_chunk_decode_failure(format!(
+                        "unable to decode multi chunk {}",
+                        e
+                    ))))
+                }
+            };
+
+        let mut next_chunk_ids = vec![];
+        let mut applied_chunk_ids = BTreeSet::new();
+
+        for SubtreeMultiChunk { prefix, chunk_ops } in subtree_multi_chunks {
+            let chunks = match decode_subtree_chunks(&chunk_ops, version) {
+                Ok(chunks) => chunks,
+                Err(error) => return Err(state_sync_info.fail(error)),
+            };
+
+            for (chunk_id, chunk) in chunks {
+                if !state_sync_info.is_chunk_pending(&prefix, &chunk_id) {
+                    continue;
+                }
+                let global_chunk_id = state_sync_info.global_chunk_id(&prefix, &chunk_id);
+                let (res, new_state_sync_info) = self.apply_global_chunk(
+                    state_sync_info,
+                    &global_chunk_id,
+                    IncomingChunk::Encoded(chunk),
+                    tx,
+                    version,
+                    grove_version,
+                )?;
+                state_sync_info = new_state_sync_info;
+                next_chunk_ids.extend(res);
+                applied_chunk_ids.insert(global_chunk_id);
+            }
+        }
+
+        // Chunks discovered and applied within the same multi-chunk don't need to
+        // be fetched anymore
+        next_chunk_ids.retain(|chunk_id| !applied_chunk_ids.contains(chunk_id));
+
+        Ok((next_chunk_ids, state_sync_info))
+    }
+}
+
+// Decodes the chunk ops of a subtree into (local chunk id, chunk) pairs. An
+// empty subtree consists of a single empty root chunk.
+fn decode_subtree_chunks(
+    chunk_ops: &[u8],
+    version: u16,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ReplicationError> {
+    let mut chunks = vec![];
+    if chunk_ops.is_empty() {
+        chunks.push((vec![], vec![]));
+        return Ok(chunks);
+    }
+    let mut input = chunk_ops;
+    while !input.is_empty() {
+        let chunk_id = match ChunkOp::decode(&mut input) {
+            Ok(ChunkOp::ChunkId(instruction)) => {
+                util_encode_chunk_id(&traversal_instruction_as_vec_bytes(&instruction), version)
+                    .map_err(|e| multi_chunk_decode_failure(e.to_string()))?
+            }
+            Ok(ChunkOp::Chunk(_)) => {
+                return Err(multi_chunk_decode_failure(
+                    "invalid multi chunk ordering".to_string(),
+                ))
+            }
+            Err(e) => {
+                return Err(multi_chunk_decode_failure(format!(
+                    "unable to decode multi chunk {}",
+                    e
+                )))
+            }
+        };
+        let chunk = match ChunkOp::decode(&mut input) {
+            Ok(ChunkOp::Chunk(ops)) => {
+                util_encode_vec_ops(ops).map_err(|e| multi_chunk_decode_failure(e.to_string()))?
+            }
+            Ok(ChunkOp::ChunkId(_)) => {
+                return Err(multi_chunk_decode_failure(
+                    "invalid multi chunk ordering".to_string(),
+                ))
+            }
+            Err(e) => {
+                return Err(multi_chunk_decode_failure(format!(
+                    "unable to decode multi chunk {}",
+                    e
+                )))
+            }
+        };
+        chunks.push((chunk_id, chunk));
+    }
+    Ok(chunks)
+}
+
+// Failure to decode a multi-chunk, which isn't attributable to a single chunk
+fn multi_chunk_decode_failure(reason: String) -> ReplicationError {
+    ReplicationError::DecodeFailure {
+        global_chunk_id: vec![],
+        reason,
+    }
+}
@@ -0,0 +1,73 @@
+//! Progress reporting of the state sync
+//!
+//! A `StateSyncObserver` can be attached to a `MultiStateSyncInfo` in order to
+//! receive structured `StateSyncEvent`s while the state sync is running (for
+//! example to drive progress bars or logging). Without an observer the state
+//! sync runs silently.
+
+use grovedb_merk::tree::hash::CryptoHash;
+
+use crate::Error;
+
+/// Event emitted during the state sync process
+#[derive(Debug)]
+pub enum StateSyncEvent<'a> {
+    /// The state sync was started (or resumed from persisted progress) for
+    /// the given app hash
+    SyncStarted {
+        /// Snapshot's app hash
+        app_hash: CryptoHash,
+        /// Whether an interrupted state sync was resumed
+        resumed: bool,
+    },
+    /// Restoration of a subtree started
+    SubtreeStarted {
+        /// Path of the subtree
+        path: &'a [Vec<u8>],
+    },
+    /// A chunk was verified and applied
+    ChunkApplied {
+        /// Path of the subtree the chunk belongs to
+        path: &'a [Vec<u8>],
+        /// Global chunk id of the chunk
+        global_chunk_id: &'a [u8],
+        /// Size of the encoded chunk in bytes
+        chunk_size: usize,
+    },
+    /// A subtree was fully restored and finalized
+    SubtreeFinalized {
+        /// Path of the subtree
+        path: &'a [Vec<u8>],
+        /// Number of chunks the subtree was restored from
+        num_processed_chunks: usize,
+    },
+    /// All subtrees were restored
+    SyncCompleted {
+        /// Snapshot's app hash
+        app_hash: CryptoHash,
+        /// Number of restored subtrees
+        num_processed_subtrees: usize,
+    },
+    /// The state sync failed
+    Error {
+        /// Global chunk id of the chunk being applied, if any
+        global_chunk_id: Option<&'a [u8]>,
+        /// The error
+        error: &'a Error,
+    },
+}
+
+/// Observer of the state sync progress
+pub trait StateSyncObserver: Send + Sync {
+    /// Called for every event emitted during the state sync
+    fn on_event(&self, event: StateSyncEvent);
+}
+
+impl<F> StateSyncObserver for F
+where
+    F: Fn(StateSyncEvent) + Send + Sync,
+{
+    fn on_event(&self, event: StateSyncEvent) {
+        self(event)
+    }
+}
@@ -0,0 +1,603 @@
+//! Operation log shipping
+//!
+//! A primary applying its batches with `GroveDb::apply_logged_batch` records
+//! every batch, along with the root hash it results in, as an entry of an
+//! ordered operation log in its meta storage. The entry is written in the same
+//! transaction as the batch, so the log never gets ahead of or behind the
+//! committed state.
+//!
+//! A replica tails the log of an `OpLogSource` with
+//! `GroveDb::catch_up_from_op_log`, applying the entries it misses in order
+//! with `apply_batch` and checking its root hash against the primary's after
+//! each of them. The position of the replica in the log is kept in its own
+//! meta storage. A replica that has never been synced, or that lags too far
+//! behind (or needs entries that were pruned), falls back to a diff sync of a
+//! snapshot pinned on the primary (see `OpLogPrimary`) and resumes tailing from
+//! the log head of that snapshot. As the snapshot is pinned, the primary can
+//! keep taking writes during the sync.
+
+use std::path::{Path, PathBuf};
+
+use bincode::{config, Decode, Encode};
+use grovedb_merk::tree::hash::CryptoHash;
+use grovedb_path::SubtreePath;
+use grovedb_storage::{Storage, StorageContext};
+use grovedb_version::{check_grovedb_v0, error::GroveVersionError, version::GroveVersion};
+
+use crate::{
+    batch::{key_info::KeyInfo, BatchApplyOptions, GroveOp, KeyInfoPath, QualifiedGroveDbOp},
+    replication::{MultiStateSyncInfo, SnapshotSession},
+    Error, GroveDb, Transaction, TransactionArg,
+};
+
+/// Meta storage key of the bounds of the operation log of a primary
+const OP_LOG_HEAD_KEY: &[u8] = b"op_log_head";
+
+/// Meta storage key prefix of the entries of the operation log of a primary,
+/// followed by the big endian sequence number of the entry
+const OP_LOG_ENTRY_KEY_PREFIX: &[u8] = b"op_log_entry";
+
+/// Meta storage key of the position of a replica in the operation log of its
+/// primary
+const OP_LOG_POSITION_KEY: &[u8] = b"op_log_position";
+
+/// Bounds of an operation log
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct OpLogHead {
+    /// Sequence number of the oldest entry that wasn't pruned
+    pub first: u64,
+    /// Sequence number the next entry will get
+    pub next: u64,
+    /// Root hash of the primary once all entries are applied
+    pub root_hash: CryptoHash,
+}
+
+/// Entry of an operation log: a batch committed by the primary
+#[derive(Clone, Debug)]
+pub struct OpLogEntry {
+    /// Sequence number of the entry
+    pub sequence: u64,
+    /// Operations of the batch
+    pub ops: Vec<QualifiedGroveDbOp>,
+    /// Options the batch was applied with
+    pub batch_apply_options: Option<BatchApplyOptions>,
+    /// Root hash of the primary after applying the batch
+    pub root_hash: CryptoHash,
+}
+
+/// Encoded form of `OpLogEntry`
+#[derive(Encode, Decode)]
+struct EncodedOpLogEntry {
+    sequence: u64,
+    ops: Vec<EncodedOp>,
+    batch_apply_options: Option<EncodedBatchApplyOptions>,
+    root_hash: CryptoHash,
+}
+
+/// Encoded form of `QualifiedGroveDbOp`, only known keys can be logged
+#[derive(Encode, Decode)]
+struct EncodedOp {
+    path: Vec<Vec<u8>>,
+    key: Vec<u8>,
+    op: GroveOp,
+}
+
+/// Encoded form of `BatchApplyOptions`, paused batches can't be logged
+#[derive(Encode, Decode)]
+struct EncodedBatchApplyOptions {
+    validate_insertion_does_not_override: bool,
+    validate_insertion_does_not_override_tree: bool,
+    allow_deleting_non_empty_trees: bool,
+    deleting_non_empty_trees_returns_error: bool,
+    disable_operation_consistency_check: bool,
+    base_root_storage_is_free: bool,
+}
+
+impl OpLogEntry {
+    /// Encodes the entry, for shipping it to a replica
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut ops = Vec::with_capacity(self.ops.len());
+        for op in self.ops.iter() {
+            let mut path = Vec::with_capacity(op.path.0.len());
+            for segment in op.path.0.iter() {
+                path.push(known_key(segment)?.to_vec());
+            }
+            ops.push(EncodedOp {
+                path,
+                key: known_key(&op.key)?.to_vec(),
+                op: op.op.clone(),
+            });
+        }
+        let batch_apply_options = match self.batch_apply_options.as_ref() {
+            Some(options) if options.batch_pause_height.is_some() => {
+                return Err(Error::InvalidParameter("paused batches can't be logged"));
+            }
+            Some(options) => Some(EncodedBatchApplyOptions {
+                validate_insertion_does_not_override: options.validate_insertion_does_not_override,
+                validate_insertion_does_not_override_tree: options
+                    .validate_insertion_does_not_override_tree,
+                allow_deleting_non_empty_trees: options.allow_deleting_non_empty_trees,
+                deleting_non_empty_trees_returns_error: options
+                    .deleting_non_empty_trees_returns_error,
+                disable_operation_consistency_check: options.disable_operation_consistency_check,
+                base_root_storage_is_free: options.base_root_storage_is_free,
+            }),
+            None => None,
+        };
+
+        let config = config::standard().with_big_endian().with_no_limit();
+        bincode::encode_to_vec(
+            EncodedOpLogEntry {
+                sequence: self.sequence,
+                ops,
+                batch_apply_options,
+                root_hash: self.root_hash,
+            },
+            config,
+        )
+        .map_err(|e| Error::CorruptedData(format!("unable to encode op log entry {}", e)))
+    }
+
+    /// Decodes an entry encoded with `encode`
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        let config = config::standard().with_big_endian().with_no_limit();
+        let encoded: EncodedOpLogEntry = bincode::decode_from_slice(bytes, config)
+            .map_err(|e| Error::CorruptedData(format!("unable to decode op log entry {}", e)))?
+            .0;
+        Ok(OpLogEntry {
+            sequence: encoded.sequence,
+            ops: encoded
+                .ops
+                .into_iter()
+                .map(|op| QualifiedGroveDbOp {
+                    path: KeyInfoPath::from_known_owned_path(op.path),
+                    key: KeyInfo::KnownKey(op.key),
+                    op: op.op,
+                })
+                .collect(),
+            batch_apply_options: encoded
+                .batch_apply_options
+                .map(|options| BatchApplyOptions {
+                    validate_insertion_does_not_override: options
+                        .validate_insertion_does_not_override,
+                    validate_insertion_does_not_override_tree: options
+                        .validate_insertion_does_not_override_tree,
+                    allow_deleting_non_empty_trees: options.allow_deleting_non_empty_trees,
+                    deleting_non_empty_trees_returns_error: options
+                        .deleting_non_empty_trees_returns_error,
+                    disable_operation_consistency_check: options
+                        .disable_operation_consistency_check,
+                    base_root_storage_is_free: options.base_root_storage_is_free,
+                    batch_pause_height: None,
+                }),
+            root_hash: encoded.root_hash,
+        })
+    }
+}
+
+/// Outcome of `GroveDb::catch_up_from_op_log`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpLogCatchUp {
+    /// Number of log entries applied
+    pub applied_entries: u64,
+    /// Whether the replica fell back to a snapshot sync
+    pub snapshot_synced: bool,
+    /// Position of the replica in the log, i.e. the sequence number of the
+    /// next entry to apply
+    pub position: u64,
+}
+
+/// Source of the operation log of a primary
+pub trait OpLogSource {
+    /// Returns the bounds of the operation log
+    fn op_log_head(&self, grove_version: &GroveVersion) -> Result<OpLogHead, Error>;
+
+    /// Returns the entry with the given sequence number, or `None` if it was
+    /// pruned or doesn't exist yet
+    fn op_log_entry(
+        &self,
+        sequence: u64,
+        grove_version: &GroveVersion,
+    ) -> Result<Option<OpLogEntry>, Error>;
+}
+
+impl OpLogSource for GroveDb {
+    fn op_log_head(&self, grove_version: &GroveVersion) -> Result<OpLogHead, Error> {
+        match self.get_op_log_meta(OP_LOG_HEAD_KEY, None)? {
+            Some(bytes) => decode_op_log_head(&bytes),
+            // Nothing was logged yet: position 0 is the current state
+            None => Ok(OpLogHead {
+                first: 0,
+                next: 0,
+                root_hash: self.root_hash(None, grove_version).unwrap()?,
+            }),
+        }
+    }
+
+    fn op_log_entry(
+        &self,
+        sequence: u64,
+        _grove_version: &GroveVersion,
+    ) -> Result<Option<OpLogEntry>, Error> {
+        self.get_op_log_meta(&op_log_entry_key(sequence), None)?
+            .map(|bytes| OpLogEntry::decode(&bytes))
+            .transpose()
+    }
+}
+
+impl OpLogSource for SnapshotSession {
+    fn op_log_head(&self, grove_version: &GroveVersion) -> Result<OpLogHead, Error> {
+        self.db().op_log_head(grove_version)
+    }
+
+    fn op_log_entry(
+        &self,
+        sequence: u64,
+        grove_version: &GroveVersion,
+    ) -> Result<Option<OpLogEntry>, Error> {
+        self.db().op_log_entry(sequence, grove_version)
+    }
+}
+
+/// Primary a replica catches up from: the source of its operation log, which
+/// can also pin snapshots of its state for replicas falling back to a snapshot
+/// sync
+pub trait OpLogPrimary: OpLogSource {
+    /// Pins the current state of the primary. The session serves the chunks
+    /// and the operation log of that state only, so writes to the primary
+    /// during a snapshot sync don't affect it.
+    fn pin_snapshot(&self, grove_version: &GroveVersion) -> Result<SnapshotSession, Error>;
+}
+
+/// Primary in the same process, pinning its snapshots in a checkpoint at
+/// `checkpoint_path`. The checkpoint is removed once the snapshot is synced.
+pub struct LocalOpLogPrimary<'a> {
+    db: &'a GroveDb,
+    checkpoint_path: PathBuf,
+}
+
+impl<'a> LocalOpLogPrimary<'a> {
+    /// Creates the primary, `checkpoint_path` must not exist
+    pub fn new<P: AsRef<Path>>(db: &'a GroveDb, checkpoint_path: P) -> Self {
+        LocalOpLogPrimary {
+            db,
+            checkpoint_path: checkpoint_path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl OpLogSource for LocalOpLogPrimary<'_> {
+    fn op_log_head(&self, grove_version: &GroveVersion) -> Result<OpLogHead, Error> {
+        self.db.op_log_head(grove_version)
+    }
+
+    fn op_log_entry(
+        &self,
+        sequence: u64,
+        grove_version: &GroveVersion,
+    ) -> Result<Option<OpLogEntry>, Error> {
+        self.db.op_log_entry(sequence, grove_version)
+    }
+}
+
+impl OpLogPrimary for LocalOpLogPrimary<'_> {
+    fn pin_snapshot(&self, grove_version: &GroveVersion) -> Result<SnapshotSession, Error> {
+        self.db
+            .start_snapshot_session(&self.checkpoint_path, grove_version)
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Applies a batch like `apply_batch` and records it, along with the
+    /// resulting root hash, as the next entry of the operation log. Returns
+    /// the sequence number of the entry. With a transaction the entry is only
+    /// committed along with it. All writes to a primary have to go through
+    /// this method, as replicas can't apply anything else.
+    pub fn apply_logged_batch(
+        &self,
+        ops: Vec<QualifiedGroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+        transaction: TransactionArg,
+        grove_version: &GroveVersion,
+    ) -> Result<u64, Error> {
+        check_grovedb_v0!(
+            "apply_logged_batch",
+            grove_version
+                .grovedb_versions
+                .replication
+                .apply_logged_batch
+        );
+        match transaction {
+            Some(tx) => {
+                self.apply_logged_batch_on_transaction(ops, batch_apply_options, tx, grove_version)
+            }
+            None => {
+                let tx = self.start_transaction();
+                let sequence = self.apply_logged_batch_on_transaction(
+                    ops,
+                    batch_apply_options,
+                    &tx,
+                    grove_version,
+                )?;
+                self.commit_transaction(tx).unwrap()?;
+                Ok(sequence)
+            }
+        }
+    }
+
+    fn apply_logged_batch_on_transaction(
+        &self,
+        ops: Vec<QualifiedGroveDbOp>,
+        batch_apply_options: Option<BatchApplyOptions>,
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<u64, Error> {
+        let mut head = match self.get_op_log_meta(OP_LOG_HEAD_KEY, Some(tx))? {
+            Some(bytes) => decode_op_log_head(&bytes)?,
+            None => OpLogHead {
+                first: 0,
+                next: 0,
+                root_hash: CryptoHash::default(),
+            },
+        };
+        let mut entry = OpLogEntry {
+            sequence: head.next,
+            ops,
+            batch_apply_options,
+            root_hash: CryptoHash::default(),
+        };
+        // Encoded once before applying, to reject batches that can't be logged
+        entry.encode()?;
+
+        self.apply_batch(
+            entry.ops.clone(),
+            entry.batch_apply_options.clone(),
+            Some(tx),
+            grove_version,
+        )
+        .unwrap()?;
+        entry.root_hash = self.root_hash(Some(tx), grove_version).unwrap()?;
+
+        self.put_op_log_meta(&op_log_entry_key(entry.sequence), &entry.encode()?, tx)?;
+        head.next += 1;
+        head.root_hash = entry.root_hash;
+        self.put_op_log_meta(OP_LOG_HEAD_KEY, &encode_op_log_head(&head)?, tx)?;
+        Ok(entry.sequence)
+    }
+
+    /// Removes all but the last `retained_entries` entries of the operation
+    /// log. Replicas lagging further behind will fall back to a snapshot sync.
+    pub fn prune_op_log(
+        &self,
+        retained_entries: u64,
+        transaction: TransactionArg,
+    ) -> Result<(), Error> {
+        match transaction {
+            Some(tx) => self.prune_op_log_on_transaction(retained_entries, tx),
+            None => {
+                let tx = self.start_transaction();
+                self.prune_op_log_on_transaction(retained_entries, &tx)?;
+                self.commit_transaction(tx).unwrap()
+            }
+        }
+    }
+
+    fn prune_op_log_on_transaction(
+        &self,
+        retained_entries: u64,
+        tx: &Transaction,
+    ) -> Result<(), Error> {
+        let Some(bytes) = self.get_op_log_meta(OP_LOG_HEAD_KEY, Some(tx))? else {
+            return Ok(());
+        };
+        let mut head = decode_op_log_head(&bytes)?;
+        let first = head.next.saturating_sub(retained_entries).max(head.first);
+        let meta_storage = self
+            .db
+            .get_immediate_storage_context(SubtreePath::empty(), tx)
+            .unwrap();
+        for sequence in head.first..first {
+            meta_storage
+                .delete_meta(op_log_entry_key(sequence), None)
+                .unwrap()?;
+        }
+        head.first = first;
+        self.put_op_log_meta(OP_LOG_HEAD_KEY, &encode_op_log_head(&head)?, tx)
+    }
+
+    /// Returns the position of this replica in the operation log of its
+    /// primary, i.e. the sequence number of the next entry to apply, or `None`
+    /// if it was never synced
+    pub fn op_log_position(&self) -> Result<Option<u64>, Error> {
+        self.get_op_log_meta(OP_LOG_POSITION_KEY, None)?
+            .map(|bytes| {
+                let bytes: [u8; 8] = bytes
+                    .try_into()
+                    .map_err(|_| Error::CorruptedData("invalid op log position".to_string()))?;
+                Ok(u64::from_be_bytes(bytes))
+            })
+            .transpose()
+    }
+
+    /// Brings this replica up to date with the primary, applying the log
+    /// entries it misses in order, each in its own transaction. After every
+    /// entry the root hash of the replica is checked against the one of the
+    /// primary, a mismatch aborts before the entry is committed.
+    /// If the replica was never synced, lags more than `max_lag` entries
+    /// behind, or needs entries that were pruned, it is diff synced from a
+    /// snapshot pinned on the primary instead.
+    pub fn catch_up_from_op_log<S: OpLogPrimary + ?Sized>(
+        &self,
+        primary: &S,
+        max_lag: u64,
+        grove_version: &GroveVersion,
+    ) -> Result<OpLogCatchUp, Error> {
+        check_grovedb_v0!(
+            "catch_up_from_op_log",
+            grove_version
+                .grovedb_versions
+                .replication
+                .catch_up_from_op_log
+        );
+        let head = primary.op_log_head(grove_version)?;
+        let Some(mut position) = self.op_log_position()? else {
+            return self.catch_up_from_snapshot(primary, 0, grove_version);
+        };
+        if position > head.next {
+            return Err(Error::CorruptedData(
+                "replica is ahead of the op log of the primary".to_string(),
+            ));
+        }
+        if position < head.first || head.next - position > max_lag {
+            return self.catch_up_from_snapshot(primary, 0, grove_version);
+        }
+
+        let mut applied_entries = 0;
+        while position < head.next {
+            let Some(entry) = primary.op_log_entry(position, grove_version)? else {
+                // Pruned since the head was read
+                return self.catch_up_from_snapshot(primary, applied_entries, grove_version);
+            };
+            if entry.sequence != position {
+                return Err(Error::CorruptedData(format!(
+                    "expected op log entry {}, got {}",
+                    position, entry.sequence
+                )));
+            }
+            self.apply_op_log_entry(entry, grove_version)?;
+            position += 1;
+            applied_entries += 1;
+        }
+        Ok(OpLogCatchUp {
+            applied_entries,
+            snapshot_synced: false,
+            position,
+        })
+    }
+
+    // Applies a log entry in its own transaction, along with the new position
+    // of the replica
+    fn apply_op_log_entry(
+        &self,
+        entry: OpLogEntry,
+        grove_version: &GroveVersion,
+    ) -> Result<(), Error> {
+        let tx = self.start_transaction();
+        self.apply_batch(
+            entry.ops,
+            entry.batch_apply_options,
+            Some(&tx),
+            grove_version,
+        )
+        .unwrap()?;
+        let root_hash = self.root_hash(Some(&tx), grove_version).unwrap()?;
+        if root_hash != entry.root_hash {
+            return Err(Error::CorruptedData(format!(
+                "replica diverged from the primary at op log entry {}",
+                entry.sequence
+            )));
+        }
+        self.put_op_log_meta(
+            OP_LOG_POSITION_KEY,
+            &(entry.sequence + 1).to_be_bytes(),
+            &tx,
+        )?;
+        self.commit_transaction(tx).unwrap()
+    }
+
+    // Diff syncs the replica from a snapshot pinned on the primary, and sets its
+    // position to the log head of the snapshot
+    fn catch_up_from_snapshot<S: OpLogPrimary + ?Sized>(
+        &self,
+        primary: &S,
+        applied_entries: u64,
+        grove_version: &GroveVersion,
+    ) -> Result<OpLogCatchUp, Error> {
+        let session = primary.pin_snapshot(grove_version)?;
+        let head = session.op_log_head(grove_version)?;
+        if head.root_hash != session.app_hash() {
+            return Err(Error::CorruptedData(
+                "snapshot doesn't match the op log head of the primary".to_string(),
+            ));
+        }
+
+        let tx = self.start_transaction();
+        let state_sync_info = self.sync_from_source(
+            &session,
+            MultiStateSyncInfo::default().with_diff_sync(),
+            &tx,
+            grove_version,
+        )?;
+        drop(state_sync_info);
+        session.release()?;
+
+        self.put_op_log_meta(OP_LOG_POSITION_KEY, &head.next.to_be_bytes(), &tx)?;
+        self.commit_transaction(tx).unwrap()?;
+        Ok(OpLogCatchUp {
+            applied_entries,
+            snapshot_synced: true,
+            position: head.next,
+        })
+    }
+
+    fn get_op_log_meta(
+        &self,
+        key: &[u8],
+        transaction: TransactionArg,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        match transaction {
+            Some(tx) => self
+                .db
+                .get_immediate_storage_context(SubtreePath::empty(), tx)
+                .unwrap()
+                .get_meta(key)
+                .unwrap()
+                .map_err(Into::into),
+            None => self
+                .db
+                .get_storage_context(SubtreePath::empty(), None)
+                .unwrap()
+                .get_meta(key)
+                .unwrap()
+                .map_err(Into::into),
+        }
+    }
+
+    fn put_op_log_meta(&self, key: &[u8], value: &[u8], tx: &Transaction) -> Result<(), Error> {
+        self.db
+            .get_immediate_storage_context(SubtreePath::empty(), tx)
+            .unwrap()
+            .put_meta(key, value, None)
+            .unwrap()
+            .map_err(Into::into)
+    }
+}
+
+fn op_log_entry_key(sequence: u64) -> Vec<u8> {
+    let mut key = OP_LOG_ENTRY_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&sequence.to_be_bytes());
+    key
+}
+
+fn encode_op_log_head(head: &OpLogHead) -> Result<Vec<u8>, Error> {
+    let config = config::standard().with_big_endian().with_no_limit();
+    bincode::encode_to_vec(head, config)
+        .map_err(|e| Error::CorruptedData(format!("unable to encode op log head {}", e)))
+}
+
+fn decode_op_log_head(bytes: &[u8]) -> Result<OpLogHead, Error> {
+    let config = config::standard().with_big_endian().with_no_limit();
+    Ok(bincode::decode_from_slice(bytes, config)
+        .map_err(|e| Error::CorruptedData(format!("unable to decode op log head {}", e)))?
+        .0)
+}
+
+fn known_key(key: &KeyInfo) -> Result<&[u8], Error> {
+    match key {
+        KeyInfo::KnownKey(key) => Ok(key),
+        KeyInfo::MaxKeySize { .. } => Err(Error::InvalidParameter(
+            "only operations on known keys can be logged",
+        )),
+    }
+}
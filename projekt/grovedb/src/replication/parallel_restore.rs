@@ -0,0 +1,260 @@
+//! Concurrent chunk restoration
+//!
+//! Decoding a chunk and verifying it against its expected hash (rebuilding the
+//! proof tree and hashing all its nodes) dominates the cost of applying it, and
+//! doesn't need access to the storage. `GroveDb::apply_chunks` therefore
+//! verifies a batch of pending chunks (typically of many independent subtrees)
+//! on several threads, and only writes the verified chunks through the state
+//! sync transaction one by one.
+
+use std::{num::NonZeroUsize, thread, time::Instant};
+
+use grovedb_merk::merk::restore::{ChunkVerifier, VerifiedChunk};
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    replication::{
+        check_replication_method_version, check_state_sync_version, stats::ChunkTimings,
+        util_decode_chunk_id, util_decode_vec_ops, util_split_global_chunk_id, ApplyChunkError,
+        IncomingChunk, MultiStateSyncInfo, ReplicationError,
+    },
+    Error, GroveDb, Transaction,
+};
+
+// Verified chunk along with the time spent decoding and verifying it
+type VerificationResult = Result<(VerifiedChunk, ChunkTimings), ReplicationError>;
+
+// Chunk to verify: the verifier obtained from the chunk's subtree restorer and
+// the encoded chunk
+struct VerificationJob {
+    index: usize,
+    global_chunk_id: Vec<u8>,
+    verifier: ChunkVerifier,
+    chunk: Vec<u8>,
+}
+
+impl VerificationJob {
+    fn run(self) -> (usize, VerificationResult) {
+        let global_chunk_id = self.global_chunk_id;
+        let started_at = Instant::now();
+        let verified_chunk = util_decode_vec_ops(self.chunk)
+            .map_err(|e| ReplicationError::DecodeFailure {
+                global_chunk_id: global_chunk_id.clone(),
+                reason: e.to_string(),
+            })
+            .and_then(|ops| {
+                let decoded_at = Instant::now();
+                self.verifier
+                    .verify(ops)
+                    .map(|verified_chunk| {
+                        let timings = ChunkTimings {
+                            decode: decoded_at - started_at,
+                            verify: decoded_at.elapsed(),
+                        };
+                        (verified_chunk, timings)
+                    })
+                    .map_err(|e| ReplicationError::from_verification_error(&global_chunk_id, e))
+            });
+        (self.index, verified_chunk)
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    // Apply several chunks at once (should be called by ABCI when
+    // ApplySnapshotChunk method is called for chunks fetched concurrently)
+    // Params:
+    // state_sync_info: Consumed MultiStateSyncInfo
+    // chunks: Global chunk ids along with their chunk proof operators encoded
+    // in bytes. All of them must be pending, as returned by
+    // MultiStateSyncInfo::pending_global_chunk_ids().
+    // tx: Transaction for the state sync
+    // Returns the next set of global chunk ids that can be fetched from sources (+
+    // the MultiStateSyncInfo transferring ownership back to the caller)
+    // The chunks are decoded and verified concurrently on up to
+    // `std::thread::available_parallelism()` threads, then applied in the given
+    // order. If any chunk fails verification none of them is applied, otherwise
+    // chunks preceding a failing chunk remain applied.
+    pub fn apply_chunks<'db>(
+        &'db self,
+        state_sync_info: MultiStateSyncInfo<'db>,
+        chunks: Vec<(Vec<u8>, Vec<u8>)>,
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<(Vec<Vec<u8>>, MultiStateSyncInfo<'db>), ApplyChunkError<'db>> {
+        if let Err(error) = check_replication_method_version(
+            "apply_chunks",
+            grove_version.grovedb_versions.replication.apply_chunks,
+        ) {
+            let global_chunk_id = chunks.first().map(|(id, _)| id.as_slice()).unwrap_or(&[]);
+            return Err(state_sync_info.fail(ReplicationError::internal(global_chunk_id, error)));
+        }
+        let result = self.apply_global_chunks(state_sync_info, chunks, tx, version, grove_version);
+        if let Err(error) = &result {
+            error.state_sync_info.notify_error(&error.error);
+        }
+        result
+    }
+
+    // Verifies the chunks concurrently and applies them
+    fn apply_global_chunks<'db>(
+        &'db self,
+        mut state_sync_info: MultiStateSyncInfo<'db>,
+        chunks: Vec<(Vec<u8>, Vec<u8>)>,
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<(Vec<Vec<u8>>, MultiStateSyncInfo<'db>), ApplyChunkError<'db>> {
+        if check_state_sync_version(version).is_err() || version != state_sync_info.version {
+            let global_chunk_id = chunks.first().map(|(id, _)| id.clone()).unwrap_or_default();
+            return Err(state_sync_info.fail(ReplicationError::WrongVersion {
+                global_chunk_id,
+                version,
+            }));
+        }
+
+        // Collect the verifiers while all restorers are in place. Empty chunks
+        // (of empty subtrees) only have to match the empty tree hash.
+        let mut global_chunk_ids = Vec::with_capacity(chunks.len());
+        let mut incoming_chunks = Vec::with_capacity(chunks.len());
+        let mut jobs = vec![];
+        for (index, (global_chunk_id, chunk)) in chunks.into_iter().enumerate() {
+            let verifier = match state_sync_info.chunk_verifier(&global_chunk_id, version) {
+                Ok(verifier) => verifier,
+                Err(error) => return Err(state_sync_info.fail(error)),
+            };
+            if !chunk.is_empty() {
+                jobs.push(VerificationJob {
+                    index,
+                    global_chunk_id: global_chunk_id.clone(),
+                    verifier,
+                    chunk,
+                });
+                incoming_chunks.push(None);
+            } else if let Err(error) = verifier.verify_empty() {
+                return Err(
+                    state_sync_info.fail(ReplicationError::from_verification_error(
+                        &global_chunk_id,
+                        error,
+                    )),
+                );
+            } else {
+                incoming_chunks.push(Some(IncomingChunk::Encoded(chunk)));
+            }
+            global_chunk_ids.push(global_chunk_id);
+        }
+
+        let chunk_sizes: Vec<usize> = jobs.iter().map(|job| job.chunk.len()).collect();
+        for ((index, verified_chunk), chunk_size) in
+            verify_concurrently(jobs).into_iter().zip(chunk_sizes)
+        {
+            let (verified_chunk, timings) = match verified_chunk {
+                Ok(verified_chunk) => verified_chunk,
+                Err(error) => return Err(state_sync_info.fail(error)),
+            };
+            incoming_chunks[index] = Some(IncomingChunk::Verified {
+                verified_chunk,
+                chunk_size,
+                timings,
+            });
+        }
+
+        let mut next_chunk_ids = vec![];
+        for (global_chunk_id, incoming_chunk) in global_chunk_ids.into_iter().zip(incoming_chunks) {
+            let Some(incoming_chunk) = incoming_chunk else {
+                return Err(state_sync_info.fail(ReplicationError::internal(
+                    &global_chunk_id,
+                    Error::InternalError("Chunk verification was skipped".to_string()),
+                )));
+            };
+            let (res, new_state_sync_info) = self.apply_global_chunk(
+                state_sync_info,
+                &global_chunk_id,
+                incoming_chunk,
+                tx,
+                version,
+                grove_version,
+            )?;
+            state_sync_info = new_state_sync_info;
+            next_chunk_ids.extend(res);
+        }
+        Ok((next_chunk_ids, state_sync_info))
+    }
+}
+
+impl<'db> MultiStateSyncInfo<'db> {
+    // Returns the verifier of a pending chunk, from its subtree's restorer
+    fn chunk_verifier(
+        &self,
+        global_chunk_id: &[u8],
+        version: u16,
+    ) -> Result<ChunkVerifier, ReplicationError> {
+        let (chunk_prefix, chunk_id) = util_split_global_chunk_id(global_chunk_id, &self.app_hash)
+            .map_err(|e| ReplicationError::DecodeFailure {
+                global_chunk_id: global_chunk_id.to_vec(),
+                reason: e.to_string(),
+            })?;
+        if !self.is_chunk_pending(&chunk_prefix, &chunk_id) {
+            return Err(ReplicationError::UnexpectedChunk {
+                global_chunk_id: global_chunk_id.to_vec(),
+            });
+        }
+        let restorer = self
+            .current_prefixes
+            .get(&chunk_prefix)
+            .and_then(|subtree_state_sync| subtree_state_sync.restorer.as_ref())
+            .ok_or_else(|| {
+                ReplicationError::internal(
+                    global_chunk_id,
+                    Error::InternalError("Invalid internal state (restorer".to_string()),
+                )
+            })?;
+        let restorer_chunk_id = util_decode_chunk_id(&chunk_id, version).map_err(|e| {
+            ReplicationError::DecodeFailure {
+                global_chunk_id: global_chunk_id.to_vec(),
+                reason: e.to_string(),
+            }
+        })?;
+        restorer
+            .chunk_verifier(&restorer_chunk_id)
+            .map_err(|e| ReplicationError::from_verification_error(global_chunk_id, e))
+    }
+}
+
+// Runs the verification jobs on up to available_parallelism() threads, returns
+// the results in the order of the jobs
+fn verify_concurrently(jobs: Vec<VerificationJob>) -> Vec<(usize, VerificationResult)> {
+    let num_threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(jobs.len());
+    if num_threads <= 1 {
+        return jobs.into_iter().map(VerificationJob::run).collect();
+    }
+
+    // Distribute the jobs round-robin, so that large and small subtrees are
+    // mixed on every thread
+    let mut jobs_per_thread: Vec<Vec<VerificationJob>> = (0..num_threads).map(|_| vec![]).collect();
+    for (i, job) in jobs.into_iter().enumerate() {
+        jobs_per_thread[i % num_threads].push(job);
+    }
+
+    let mut results: Vec<(usize, VerificationResult)> = thread::scope(|scope| {
+        let handles: Vec<_> = jobs_per_thread
+            .into_iter()
+            .map(|jobs| {
+                scope.spawn(move || {
+                    jobs.into_iter()
+                        .map(VerificationJob::run)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("chunk verification thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results
+}
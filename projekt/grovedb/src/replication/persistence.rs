@@ -0,0 +1,267 @@
+//! Persistence of the state sync progress
+//!
+//! The progress of a state sync (processed prefixes, pending chunks and the
+//! state of every subtree `Restorer`) is written to the meta storage inside the
+//! same transaction the chunks are applied in. Once that transaction is
+//! committed, an interrupted state sync can be resumed by calling
+//! `GroveDb::start_snapshot_syncing` again with the same app hash.
+//!
+//! The subtrees being processed are rewritten after every chunk, while every
+//! processed prefix is written once, under its own meta key, when its subtree
+//! is finished. Persisting a chunk thus doesn't depend on the number of
+//! subtrees restored so far.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bincode::{config, Decode, Encode};
+use grovedb_merk::{merk::restore::Restorer, tree::hash::CryptoHash};
+use grovedb_path::SubtreePath;
+use grovedb_storage::{Storage, StorageContext};
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    replication::{
+        diff_sync::ReplacedSubtrees, MultiStateSyncInfo, SubtreePrefix, SubtreeStateSyncInfo,
+        SubtreeSyncScope, SubtreesMetadata,
+    },
+    Error, GroveDb, Transaction,
+};
+
+/// Meta storage key under which the state sync progress is stored
+const STATE_SYNC_INFO_KEY: &[u8] = b"state_sync_info";
+
+/// Meta storage key prefix of the processed prefixes of the state sync,
+/// followed by the big endian index of the prefix in processing order
+const STATE_SYNC_PROCESSED_PREFIX_KEY_PREFIX: &[u8] = b"state_sync_processed_prefix";
+
+/// Persisted form of `SubtreeStateSyncInfo`
+#[derive(Encode, Decode)]
+struct PersistedSubtreeStateSyncInfo {
+    path: Vec<Vec<u8>>,
+    expected_root_hash: CryptoHash,
+    parent_value_hash: Option<CryptoHash>,
+    pending_chunks: BTreeSet<Vec<u8>>,
+    num_processed_chunks: u64,
+    chunk_id_to_root_hash: BTreeMap<Vec<u8>, CryptoHash>,
+    parent_keys: BTreeMap<Vec<u8>, Vec<u8>>,
+    replaced_subtrees: ReplacedSubtrees,
+}
+
+/// Persisted form of `MultiStateSyncInfo`
+#[derive(Encode, Decode)]
+struct PersistedStateSyncInfo {
+    version: u16,
+    app_hash: CryptoHash,
+    num_processed_prefixes: u64,
+    current_prefixes: BTreeMap<SubtreePrefix, PersistedSubtreeStateSyncInfo>,
+    subtree_scope: Option<SubtreeSyncScope>,
+    diff_sync: bool,
+    validation: bool,
+}
+
+impl<'db> MultiStateSyncInfo<'db> {
+    // Marks the subtree of the prefix as processed, the prefix is persisted
+    // along with the next progress
+    pub(crate) fn mark_prefix_processed(&mut self, prefix: SubtreePrefix) {
+        if self.processed_prefixes.insert(prefix) {
+            self.unpersisted_processed_prefixes.push(prefix);
+        }
+    }
+}
+
+impl GroveDb {
+    /// Writes the progress of the given state sync into the meta storage of
+    /// `tx`, along with the prefixes processed since it was last persisted.
+    /// Once there are no more subtrees being processed the stored progress is
+    /// removed.
+    pub(crate) fn persist_state_sync_info<'db>(
+        &'db self,
+        state_sync_info: &mut MultiStateSyncInfo<'db>,
+        tx: &'db Transaction,
+    ) -> Result<(), Error> {
+        let meta_storage = self
+            .db
+            .get_immediate_storage_context(SubtreePath::empty(), tx)
+            .unwrap();
+
+        let num_processed_prefixes = state_sync_info.processed_prefixes.len();
+        if state_sync_info.current_prefixes.is_empty() {
+            state_sync_info.unpersisted_processed_prefixes.clear();
+            for index in 0..num_processed_prefixes {
+                meta_storage
+                    .delete_meta(processed_prefix_key(index as u64), None)
+                    .unwrap()?;
+            }
+            return meta_storage
+                .delete_meta(STATE_SYNC_INFO_KEY, None)
+                .unwrap()
+                .map_err(Into::into);
+        }
+
+        let first_unpersisted =
+            num_processed_prefixes - state_sync_info.unpersisted_processed_prefixes.len();
+        for (index, prefix) in state_sync_info
+            .unpersisted_processed_prefixes
+            .drain(..)
+            .enumerate()
+        {
+            meta_storage
+                .put_meta(
+                    processed_prefix_key((first_unpersisted + index) as u64),
+                    &prefix,
+                    None,
+                )
+                .unwrap()?;
+        }
+
+        let mut current_prefixes = BTreeMap::new();
+        for (prefix, subtree_state_sync) in state_sync_info.current_prefixes.iter() {
+            let restorer = subtree_state_sync
+                .restorer
+                .as_ref()
+                .ok_or(Error::InternalError(
+                    "Invalid internal state (restorer)".to_string(),
+                ))?;
+            let (chunk_id_to_root_hash, parent_keys) = restorer.restoration_state();
+            current_prefixes.insert(
+                *prefix,
+                PersistedSubtreeStateSyncInfo {
+                    path: subtree_state_sync.path.clone(),
+                    expected_root_hash: subtree_state_sync.expected_root_hash,
+                    parent_value_hash: subtree_state_sync.parent_value_hash,
+                    pending_chunks: subtree_state_sync.pending_chunks.clone(),
+                    num_processed_chunks: subtree_state_sync.num_processed_chunks as u64,
+                    chunk_id_to_root_hash: chunk_id_to_root_hash.clone(),
+                    parent_keys: parent_keys.clone(),
+                    replaced_subtrees: subtree_state_sync.replaced_subtrees.clone(),
+                },
+            );
+        }
+
+        let persisted = PersistedStateSyncInfo {
+            version: state_sync_info.version,
+            app_hash: state_sync_info.app_hash,
+            num_processed_prefixes: num_processed_prefixes as u64,
+            current_prefixes,
+            subtree_scope: state_sync_info.subtree_scope.clone(),
+            diff_sync: state_sync_info.diff_sync,
+            validation: state_sync_info.validation,
+        };
+        let config = config::standard().with_big_endian().with_no_limit();
+        let bytes = bincode::encode_to_vec(persisted, config).map_err(|e| {
+            Error::CorruptedData(format!("unable to serialize state sync info {}", e))
+        })?;
+
+        meta_storage
+            .put_meta(STATE_SYNC_INFO_KEY, &bytes, None)
+            .unwrap()
+            .map_err(Into::into)
+    }
+
+    /// Loads the progress of an interrupted state sync from the meta storage
+    /// of `tx`, reopening the merks and restorers of all subtrees that were
+    /// being processed. Returns `None` if no state sync was in progress.
+    pub(crate) fn load_state_sync_info<'db>(
+        &'db self,
+        tx: &'db Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<Option<MultiStateSyncInfo<'db>>, Error> {
+        let meta_storage = self
+            .db
+            .get_immediate_storage_context(SubtreePath::empty(), tx)
+            .unwrap();
+
+        let Some(bytes) = meta_storage.get_meta(STATE_SYNC_INFO_KEY).unwrap()? else {
+            return Ok(None);
+        };
+
+        let config = config::standard().with_big_endian().with_no_limit();
+        let persisted: PersistedStateSyncInfo = bincode::decode_from_slice(&bytes, config)
+            .map_err(|e| {
+                Error::CorruptedData(format!("unable to deserialize state sync info {}", e))
+            })?
+            .0;
+
+        let mut processed_prefixes = BTreeSet::new();
+        for index in 0..persisted.num_processed_prefixes {
+            let prefix = meta_storage
+                .get_meta(processed_prefix_key(index))
+                .unwrap()?
+                .and_then(|bytes| SubtreePrefix::try_from(bytes).ok())
+                .ok_or_else(|| {
+                    Error::CorruptedData("missing processed prefix of state sync".to_string())
+                })?;
+            processed_prefixes.insert(prefix);
+        }
+
+        let mut state_sync_info = MultiStateSyncInfo {
+            current_prefixes: BTreeMap::new(),
+            processed_prefixes,
+            unpersisted_processed_prefixes: vec![],
+            app_hash: persisted.app_hash,
+            version: persisted.version,
+            observer: None,
+            subtrees_metadata: SubtreesMetadata::new(),
+            subtree_scope: persisted.subtree_scope,
+            manifest: None,
+            diff_sync: persisted.diff_sync,
+            validation: persisted.validation,
+            stats: Default::default(),
+        };
+
+        for (prefix, persisted_subtree) in persisted.current_prefixes {
+            let subtree_path: Vec<&[u8]> = persisted_subtree
+                .path
+                .iter()
+                .map(|vec| vec.as_slice())
+                .collect();
+            let path: &[&[u8]] = &subtree_path;
+            let merk = self
+                .open_merk_for_replication(path.into(), tx, grove_version)
+                .map_err(|_| {
+                    Error::InternalError("Unable to open merk for replication".to_string())
+                })?;
+            let (actual_value_hash, elem_value_hash) = match persisted_subtree.parent_value_hash {
+                Some(parent_value_hash) => {
+                    (parent_value_hash, persisted_subtree.expected_root_hash)
+                }
+                None => (CryptoHash::default(), CryptoHash::default()),
+            };
+            state_sync_info.subtrees_metadata.data.insert(
+                prefix,
+                (
+                    persisted_subtree.path.clone(),
+                    actual_value_hash,
+                    elem_value_hash,
+                ),
+            );
+            let restorer = Restorer::new_with_state(
+                merk,
+                persisted_subtree.chunk_id_to_root_hash,
+                persisted_subtree.parent_value_hash,
+                persisted_subtree.parent_keys,
+            );
+
+            state_sync_info.current_prefixes.insert(
+                prefix,
+                SubtreeStateSyncInfo {
+                    restorer: Some(restorer),
+                    pending_chunks: persisted_subtree.pending_chunks,
+                    num_processed_chunks: persisted_subtree.num_processed_chunks as usize,
+                    path: persisted_subtree.path,
+                    expected_root_hash: persisted_subtree.expected_root_hash,
+                    parent_value_hash: persisted_subtree.parent_value_hash,
+                    replaced_subtrees: persisted_subtree.replaced_subtrees,
+                },
+            );
+        }
+
+        Ok(Some(state_sync_info))
+    }
+}
+
+fn processed_prefix_key(index: u64) -> Vec<u8> {
+    let mut key = STATE_SYNC_PROCESSED_PREFIX_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&index.to_be_bytes());
+    key
+}
@@ -0,0 +1,176 @@
+//! Point-in-time snapshot serving
+//!
+//! A `SnapshotSession` pins the state of the source database at one app hash
+//! by creating a RocksDB checkpoint of it. All chunks fetched through the
+//! session are served from that frozen state, so writes to the source during
+//! a state sync do not affect the chunks handed out to replicas. The subtrees
+//! metadata of the pinned state is computed once when the session starts.
+//! The checkpoint is removed when the session is released or dropped.
+
+use std::path::{Path, PathBuf};
+
+use grovedb_merk::tree::hash::CryptoHash;
+use grovedb_version::{check_grovedb_v0, error::GroveVersionError, version::GroveVersion};
+
+use crate::{
+    replication::{
+        check_state_sync_version, multi_chunk::check_multi_chunk_version, util_decode_chunk_id,
+        util_split_global_chunk_id, SnapshotManifest, SubtreesMetadata,
+    },
+    Error, GroveDb,
+};
+
+// Checkpoint directory of a snapshot session, removed when dropped
+struct Checkpoint {
+    path: PathBuf,
+    removed: bool,
+}
+
+impl Checkpoint {
+    // Removes the checkpoint directory, reporting failures
+    fn remove(mut self) -> Result<(), Error> {
+        self.removed = true;
+        std::fs::remove_dir_all(&self.path).map_err(|e| {
+            Error::InternalError(format!(
+                "unable to remove snapshot checkpoint {}: {}",
+                self.path.display(),
+                e
+            ))
+        })
+    }
+}
+
+impl Drop for Checkpoint {
+    fn drop(&mut self) {
+        if !self.removed {
+            // Errors can't be reported while dropping, `release` reports them
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// Snapshot of a GroveDB frozen at one app hash, used for serving chunks.
+/// Dropping the session removes its checkpoint, like `release` does.
+pub struct SnapshotSession {
+    // GroveDB opened on the checkpoint, declared before the checkpoint so it
+    // is closed before the checkpoint is removed
+    db: GroveDb,
+    // Root hash of the checkpoint
+    app_hash: CryptoHash,
+    // Metadata of all subtrees of the checkpoint
+    subtrees_metadata: SubtreesMetadata,
+    // Location of the checkpoint, removed when the session is released
+    checkpoint: Checkpoint,
+}
+
+impl SnapshotSession {
+    /// Returns the app hash the session is pinned to
+    pub fn app_hash(&self) -> CryptoHash {
+        self.app_hash
+    }
+
+    /// Fetches a chunk by global chunk id from the pinned state. See
+    /// `GroveDb::fetch_chunk` for the format of global chunk ids.
+    pub fn fetch_chunk(
+        &self,
+        global_chunk_id: &[u8],
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        check_grovedb_v0!(
+            "fetch_chunk",
+            grove_version.grovedb_versions.replication.fetch_chunk
+        );
+        check_state_sync_version(version)?;
+
+        let (chunk_prefix, chunk_id) = util_split_global_chunk_id(global_chunk_id, &self.app_hash)?;
+        let chunk_id = util_decode_chunk_id(&chunk_id, version)?;
+        self.db.fetch_subtree_chunk(
+            &chunk_prefix,
+            &chunk_id,
+            &self.subtrees_metadata,
+            None,
+            grove_version,
+        )
+    }
+
+    /// Fetches a multi-chunk of at most `limit` bytes from the pinned state.
+    /// See `GroveDb::fetch_multi_chunk`.
+    pub fn fetch_multi_chunk(
+        &self,
+        global_chunk_ids: &[Vec<u8>],
+        limit: usize,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        check_grovedb_v0!(
+            "fetch_multi_chunk",
+            grove_version.grovedb_versions.replication.fetch_multi_chunk
+        );
+        check_multi_chunk_version(version)?;
+
+        self.db.pack_multi_chunk(
+            global_chunk_ids,
+            limit,
+            &self.app_hash,
+            &self.subtrees_metadata,
+            None,
+            version,
+            grove_version,
+        )
+    }
+
+    /// Builds the manifest of the pinned state, listing all its subtrees and
+    /// their chunk counts
+    pub fn manifest(&self, grove_version: &GroveVersion) -> Result<SnapshotManifest, Error> {
+        self.db
+            .build_snapshot_manifest(self.app_hash, &self.subtrees_metadata, None, grove_version)
+    }
+
+    /// Returns the metadata of all subtrees of the pinned state
+    pub fn subtrees_metadata(&self) -> &SubtreesMetadata {
+        &self.subtrees_metadata
+    }
+
+    // GroveDB opened on the checkpoint
+    pub(super) fn db(&self) -> &GroveDb {
+        &self.db
+    }
+
+    /// Releases the session, removing the checkpoint it was served from
+    pub fn release(self) -> Result<(), Error> {
+        let SnapshotSession { db, checkpoint, .. } = self;
+        drop(db);
+        checkpoint.remove()
+    }
+}
+
+impl GroveDb {
+    /// Starts a snapshot session pinning the current state of the database.
+    /// A checkpoint is created at `checkpoint_path` (which must not exist yet)
+    /// and every chunk fetched through the returned session is served from
+    /// it until the session is released. The checkpoint is removed if the
+    /// session fails to start.
+    pub fn start_snapshot_session<P: AsRef<Path>>(
+        &self,
+        checkpoint_path: P,
+        grove_version: &GroveVersion,
+    ) -> Result<SnapshotSession, Error> {
+        let checkpoint_path = checkpoint_path.as_ref().to_path_buf();
+        self.create_checkpoint(&checkpoint_path)?;
+        // Removes the checkpoint when returning early on errors
+        let checkpoint = Checkpoint {
+            path: checkpoint_path,
+            removed: false,
+        };
+        let db = GroveDb::open(&checkpoint.path)?;
+        let app_hash = db.root_hash(None, grove_version).unwrap()?;
+        let subtrees_metadata = db.get_subtrees_metadata(None, grove_version)?;
+        Ok(SnapshotSession {
+            db,
+            app_hash,
+            subtrees_metadata,
+            checkpoint,
+        })
+    }
+}
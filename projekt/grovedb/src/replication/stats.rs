@@ -0,0 +1,178 @@
+//! State sync statistics
+//!
+//! Every `MultiStateSyncInfo` keeps `StateSyncStats` of the chunks applied
+//! through it: bytes and chunks received (in total and per subtree), the time
+//! spent decoding, verifying and writing them, and the subtrees discovered and
+//! finished. The statistics are not persisted along with the progress of the
+//! state sync, a resumed state sync only accounts for the chunks applied since.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::{operations::proof::util::hex_to_ascii, replication::MultiStateSyncInfo};
+
+/// Time spent on a chunk before writing it
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ChunkTimings {
+    pub(crate) decode: Duration,
+    pub(crate) verify: Duration,
+}
+
+/// Statistics of the restoration of a subtree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubtreeSyncStats {
+    /// Number of chunks applied
+    pub chunks: u64,
+    /// Size of the chunks applied, in bytes
+    pub bytes: u64,
+    /// Time from the discovery of the subtree until it was finalized, `None`
+    /// while it is being restored
+    pub duration: Option<Duration>,
+    started_at: Instant,
+}
+
+/// Statistics of a state sync
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateSyncStats {
+    /// Size of all chunks applied, in bytes
+    pub bytes_received: u64,
+    /// Number of chunks applied
+    pub chunks_applied: u64,
+    /// Time spent decoding chunks
+    pub decode_time: Duration,
+    /// Time spent verifying chunks against their expected hashes
+    pub verify_time: Duration,
+    /// Time spent writing verified chunks (including the finalization of
+    /// subtrees and the persistence of the progress)
+    pub write_time: Duration,
+    /// Number of subtrees discovered and restored (identical subtrees skipped
+    /// by a diff sync are not counted)
+    pub subtrees_discovered: u64,
+    /// Number of subtrees finalized
+    pub subtrees_finished: u64,
+    /// Number of subtrees skipped by a diff sync as identical in the replica
+    pub subtrees_skipped: u64,
+    /// Statistics of every discovered subtree, by path
+    pub subtrees: BTreeMap<Vec<Vec<u8>>, SubtreeSyncStats>,
+    /// Time from the start of the state sync until it was completed, `None`
+    /// while it is running
+    pub duration: Option<Duration>,
+    started_at: Option<Instant>,
+}
+
+impl StateSyncStats {
+    /// Returns the time elapsed since the start of the state sync, up to its
+    /// completion
+    pub fn elapsed(&self) -> Duration {
+        self.duration.unwrap_or_else(|| {
+            self.started_at
+                .map_or(Duration::ZERO, |started_at| started_at.elapsed())
+        })
+    }
+
+    /// Returns the average number of bytes received per second
+    pub fn throughput(&self) -> f64 {
+        let elapsed = self.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.bytes_received as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    pub(crate) fn sync_started(&mut self) {
+        self.started_at.get_or_insert_with(Instant::now);
+    }
+
+    pub(crate) fn sync_completed(&mut self) {
+        self.duration = Some(self.elapsed());
+    }
+
+    pub(crate) fn subtree_started(&mut self, path: &[Vec<u8>]) {
+        self.subtrees_discovered += 1;
+        self.subtrees.insert(
+            path.to_vec(),
+            SubtreeSyncStats {
+                chunks: 0,
+                bytes: 0,
+                duration: None,
+                started_at: Instant::now(),
+            },
+        );
+    }
+
+    pub(crate) fn subtree_finished(&mut self, path: &[Vec<u8>]) {
+        self.subtrees_finished += 1;
+        if let Some(subtree_stats) = self.subtrees.get_mut(path) {
+            subtree_stats.duration = Some(subtree_stats.started_at.elapsed());
+        }
+    }
+
+    pub(crate) fn chunk_applied(
+        &mut self,
+        path: &[Vec<u8>],
+        chunk_size: usize,
+        timings: ChunkTimings,
+        write_time: Duration,
+    ) {
+        self.bytes_received += chunk_size as u64;
+        self.chunks_applied += 1;
+        self.decode_time += timings.decode;
+        self.verify_time += timings.verify;
+        self.write_time += write_time;
+        if let Some(subtree_stats) = self.subtrees.get_mut(path) {
+            subtree_stats.chunks += 1;
+            subtree_stats.bytes += chunk_size as u64;
+        }
+    }
+}
+
+impl fmt::Display for StateSyncStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} chunks, {} bytes in {:?} ({:.0} bytes/s)",
+            self.chunks_applied,
+            self.bytes_received,
+            self.elapsed(),
+            self.throughput()
+        )?;
+        writeln!(
+            f,
+            "decode {:?}, verify {:?}, write {:?}",
+            self.decode_time, self.verify_time, self.write_time
+        )?;
+        write!(
+            f,
+            "subtrees: {} discovered, {} finished, {} skipped",
+            self.subtrees_discovered, self.subtrees_finished, self.subtrees_skipped
+        )?;
+        for (path, subtree_stats) in self.subtrees.iter() {
+            let path: String = path
+                .iter()
+                .map(|segment| format!("/{}", hex_to_ascii(segment)))
+                .collect();
+            write!(
+                f,
+                "\n  {}: {} chunks, {} bytes",
+                if path.is_empty() { "/" } else { &path },
+                subtree_stats.chunks,
+                subtree_stats.bytes
+            )?;
+            if let Some(duration) = subtree_stats.duration {
+                write!(f, " in {:?}", duration)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'db> MultiStateSyncInfo<'db> {
+    /// Returns the statistics of the state sync
+    pub fn stats(&self) -> &StateSyncStats {
+        &self.stats
+    }
+}
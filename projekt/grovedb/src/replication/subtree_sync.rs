@@ -0,0 +1,371 @@
+//! Subtree-scoped state sync
+//!
+//! Instead of replicating a whole GroveDB, a state sync can be restricted to a
+//! single subtree and its descendants. The source proves the element of the
+//! subtree in its parent merk against the app hash, and the replica restores
+//! the subtree at the same path, verifying the restored data against the
+//! proven value hash of that element. The parent path of the subtree must
+//! already exist in the replica.
+
+use std::collections::HashMap;
+
+use bincode::{Decode, Encode};
+use grovedb_merk::{
+    merk::restore::Restorer,
+    tree::{hash::CryptoHash, kv::ValueDefinedCostType, value_hash},
+};
+use grovedb_path::SubtreePath;
+use grovedb_storage::{rocksdb_storage::RocksDbStorage, StorageBatch};
+use grovedb_version::{check_grovedb_v0, error::GroveVersionError, version::GroveVersion};
+
+use crate::{
+    element::helpers::raw_decode,
+    replication::{
+        check_state_sync_version, util_decode_chunk_id, util_split_global_chunk_id,
+        MultiStateSyncInfo, StateSyncEvent, SubtreeStateSyncInfo,
+    },
+    Element, Error, GroveDb, PathQuery, Transaction, TransactionArg,
+};
+
+/// Subtree a scoped state sync is restricted to
+#[derive(Clone, Encode, Decode)]
+pub(crate) struct SubtreeSyncScope {
+    // Path of the subtree
+    pub(crate) path: Vec<Vec<u8>>,
+    // Value hash of the subtree's element in the parent merk of the source, as
+    // proven against the app hash
+    pub(crate) elem_value_hash: CryptoHash,
+}
+
+/// Subtree element proven against the app hash
+pub(crate) struct ProvenSubtree {
+    // Path of the subtree
+    path: Vec<Vec<u8>>,
+    // Element of the subtree in the parent merk
+    element: Element,
+    // Hash of the serialized element
+    actual_value_hash: CryptoHash,
+    // Value hash of the element in the parent merk
+    elem_value_hash: CryptoHash,
+}
+
+impl ProvenSubtree {
+    pub(crate) fn path(&self) -> &[Vec<u8>] {
+        &self.path
+    }
+}
+
+// Returns the path query proving the element of the subtree at the given path
+// in its parent merk
+fn subtree_element_path_query<B: AsRef<[u8]>>(
+    subtree_path: &SubtreePath<B>,
+) -> Result<PathQuery, Error> {
+    let (parent_path, parent_key) = subtree_path.derive_parent().ok_or(Error::InvalidParameter(
+        "subtree-scoped state sync requires a non-root subtree path",
+    ))?;
+    Ok(PathQuery::new_single_key(
+        parent_path.to_vec(),
+        parent_key.to_vec(),
+    ))
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    // Proves the element of the subtree at the given path in its parent merk
+    // (should be served along with the snapshot when offering a subtree-scoped
+    // state sync) Params:
+    // subtree_path: Path of the subtree to replicate
+    // Returns the proof encoded in bytes
+    pub fn prove_subtree_element<B: AsRef<[u8]>>(
+        &self,
+        subtree_path: SubtreePath<B>,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        let path_query = subtree_element_path_query(&subtree_path)?;
+        self.prove_query(&path_query, None, grove_version).unwrap()
+    }
+
+    // Fetch a chunk by global chunk id, restricted to the subtree at the given
+    // path and its descendants Params:
+    // subtree_path: Path of the replicated subtree
+    // global_chunk_id: Global chunk id. For the description of global chunk id
+    // check fetch_chunk(). The root chunk of the replicated subtree is
+    // identified by its SUBTREE_PREFIX alone.
+    // tx: Transaction. Function returns the data by opening merks at given tx.
+    // Returns the Chunk proof operators for the requested chunk encoded in bytes
+    pub fn fetch_chunk_in_subtree<B: AsRef<[u8]>>(
+        &self,
+        subtree_path: SubtreePath<B>,
+        global_chunk_id: &[u8],
+        tx: TransactionArg,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        check_grovedb_v0!(
+            "fetch_chunk",
+            grove_version.grovedb_versions.replication.fetch_chunk
+        );
+        check_state_sync_version(version)?;
+
+        let root_app_hash = self.root_hash(tx, grove_version).value?;
+        let (chunk_prefix, chunk_id) = util_split_global_chunk_id(global_chunk_id, &root_app_hash)?;
+        let chunk_id = util_decode_chunk_id(&chunk_id, version)?;
+
+        let subtrees_metadata =
+            self.get_subtrees_metadata_at_path(subtree_path, tx, grove_version)?;
+
+        self.fetch_subtree_chunk(
+            &chunk_prefix,
+            &chunk_id,
+            &subtrees_metadata,
+            tx,
+            grove_version,
+        )
+    }
+
+    // Starts a state sync process restricted to the subtree at the given path
+    // and its descendants Params:
+    // state_sync_info: Consumed StateSyncInfo
+    // app_hash: Snapshot's AppHash
+    // subtree_path: Path of the subtree to replicate. Its parent path must
+    // exist, and its key must not be used yet.
+    // subtree_proof: Proof of the subtree's element in its parent merk (see
+    // prove_subtree_element())
+    // tx: Transaction for the state sync
+    // Returns the StateSyncInfo transferring ownership back to the caller)
+    // The chunks are applied with apply_chunk(). Once the subtree is restored
+    // its element is inserted in the parent merk and checked against the
+    // proven value hash. An interrupted subtree-scoped state sync is resumed
+    // like a regular one.
+    pub fn start_subtree_snapshot_syncing<'db, B: AsRef<[u8]>>(
+        &'db self,
+        state_sync_info: MultiStateSyncInfo<'db>,
+        app_hash: CryptoHash,
+        subtree_path: SubtreePath<B>,
+        subtree_proof: &[u8],
+        tx: &'db Transaction,
+        version: u16,
+        grove_version: &GroveVersion,
+    ) -> Result<MultiStateSyncInfo, Error> {
+        check_grovedb_v0!(
+            "start_snapshot_syncing",
+            grove_version
+                .grovedb_versions
+                .replication
+                .start_snapshot_syncing
+        );
+        let observer = state_sync_info.observer.clone();
+        let result =
+            Self::verify_subtree_element(&subtree_path, subtree_proof, &app_hash, grove_version)
+                .and_then(|proven_subtree| {
+                    self.start_or_resume_snapshot_syncing(
+                        state_sync_info,
+                        app_hash,
+                        Some(proven_subtree),
+                        tx,
+                        version,
+                        grove_version,
+                    )
+                });
+        if let (Err(error), Some(observer)) = (&result, observer) {
+            observer.on_event(StateSyncEvent::Error {
+                global_chunk_id: None,
+                error,
+            });
+        }
+        result
+    }
+
+    // Verifies the proof of the subtree's element against the app hash
+    fn verify_subtree_element<B: AsRef<[u8]>>(
+        subtree_path: &SubtreePath<B>,
+        subtree_proof: &[u8],
+        app_hash: &CryptoHash,
+        grove_version: &GroveVersion,
+    ) -> Result<ProvenSubtree, Error> {
+        let path_query = subtree_element_path_query(subtree_path)?;
+        let (root_hash, proved_path_key_values) =
+            GroveDb::verify_query_raw(subtree_proof, &path_query, grove_version)?;
+        if &root_hash != app_hash {
+            return Err(Error::InvalidProof(
+                "subtree proof doesn't match the app hash".to_string(),
+            ));
+        }
+
+        let proved_path_key_value =
+            proved_path_key_values
+                .into_iter()
+                .next()
+                .ok_or(Error::InvalidProof(
+                    "subtree element is missing from the proof".to_string(),
+                ))?;
+        let element = raw_decode(&proved_path_key_value.value, grove_version)?;
+        if !element.is_any_tree() {
+            return Err(Error::InvalidProof(
+                "proven subtree element is not a tree".to_string(),
+            ));
+        }
+
+        Ok(ProvenSubtree {
+            path: subtree_path.to_vec(),
+            element,
+            actual_value_hash: value_hash(&proved_path_key_value.value).unwrap(),
+            elem_value_hash: proved_path_key_value.proof,
+        })
+    }
+
+    // Inserts an empty tree for the proven subtree in its parent merk and
+    // prepares the restoration of the subtree
+    pub(crate) fn start_subtree_restoration<'db>(
+        &'db self,
+        state_sync_info: &mut MultiStateSyncInfo<'db>,
+        proven_subtree: ProvenSubtree,
+        tx: &'db Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<(), Error> {
+        let ProvenSubtree {
+            path,
+            element,
+            actual_value_hash,
+            elem_value_hash,
+        } = proven_subtree;
+        let subtree_path: Vec<&[u8]> = path.iter().map(|vec| vec.as_slice()).collect();
+        let subtree_path: SubtreePath<_> = subtree_path.as_slice().into();
+        let (parent_path, parent_key) = subtree_path.derive_parent().ok_or(
+            Error::InvalidParameter("subtree-scoped state sync requires a non-root subtree path"),
+        )?;
+
+        if self
+            .has_raw(parent_path.clone(), parent_key, Some(tx), grove_version)
+            .unwrap()?
+        {
+            return Err(Error::InternalError(
+                "GroveDB already has an element at the path of the synced subtree".to_string(),
+            ));
+        }
+        let empty_tree = if element.is_sum_tree() {
+            Element::empty_sum_tree_with_flags(element.get_flags().clone())
+        } else {
+            Element::empty_tree_with_flags(element.get_flags().clone())
+        };
+        self.insert(
+            parent_path,
+            parent_key,
+            empty_tree,
+            None,
+            Some(tx),
+            grove_version,
+        )
+        .unwrap()?;
+
+        state_sync_info.notify(StateSyncEvent::SubtreeStarted { path: &path });
+
+        let merk = self
+            .open_merk_for_replication(subtree_path.clone(), tx, grove_version)
+            .map_err(|_| Error::InternalError("Unable to open merk for replication".to_string()))?;
+        let prefix = RocksDbStorage::build_prefix(subtree_path).unwrap();
+
+        let mut subtree_state_sync_info = SubtreeStateSyncInfo::default();
+        subtree_state_sync_info.restorer = Some(Restorer::new(
+            merk,
+            elem_value_hash,
+            Some(actual_value_hash),
+        ));
+        subtree_state_sync_info.pending_chunks.insert(vec![]);
+        subtree_state_sync_info.path = path.clone();
+        subtree_state_sync_info.expected_root_hash = elem_value_hash;
+        subtree_state_sync_info.parent_value_hash = Some(actual_value_hash);
+
+        state_sync_info
+            .current_prefixes
+            .insert(prefix, subtree_state_sync_info);
+        state_sync_info
+            .subtrees_metadata
+            .data
+            .insert(prefix, (path.clone(), actual_value_hash, elem_value_hash));
+        state_sync_info.subtree_scope = Some(SubtreeSyncScope {
+            path,
+            elem_value_hash,
+        });
+        Ok(())
+    }
+
+    // Updates the element of the restored root subtree of a subtree-scoped
+    // state sync in its parent merk, propagating the change up to the root, and
+    // verifies it against the proven value hash
+    // restored_root: Root hash, root key and sum of the restored subtree (None
+    // for an empty subtree)
+    pub(crate) fn attach_restored_subtree(
+        &self,
+        subtree_scope: &SubtreeSyncScope,
+        restored_root: Option<(CryptoHash, Option<Vec<u8>>, Option<i64>)>,
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<(), Error> {
+        let subtree_path: Vec<&[u8]> = subtree_scope
+            .path
+            .iter()
+            .map(|vec| vec.as_slice())
+            .collect();
+        let subtree_path: SubtreePath<_> = subtree_path.as_slice().into();
+        let (parent_path, parent_key) = subtree_path.derive_parent().ok_or(
+            Error::InternalError("Invalid internal state (subtree scope)".to_string()),
+        )?;
+
+        if let Some((root_hash, root_key, sum)) = restored_root {
+            let batch = StorageBatch::new();
+            let mut parent_merk = self
+                .open_transactional_merk_at_path(
+                    parent_path.clone(),
+                    tx,
+                    Some(&batch),
+                    grove_version,
+                )
+                .value?;
+            Self::update_tree_item_preserve_flag(
+                &mut parent_merk,
+                parent_key,
+                root_key,
+                root_hash,
+                sum,
+                grove_version,
+            )
+            .value?;
+            let mut merk_cache = HashMap::default();
+            merk_cache.insert(parent_path.clone(), parent_merk);
+            self.propagate_changes_with_transaction(
+                merk_cache,
+                parent_path.clone(),
+                tx,
+                &batch,
+                grove_version,
+            )
+            .value?;
+            self.db
+                .commit_multi_context_batch(batch, Some(tx))
+                .unwrap()?;
+        }
+
+        let parent_merk = self
+            .open_transactional_merk_at_path(parent_path, tx, None, grove_version)
+            .value?;
+        let (_, elem_value_hash) = parent_merk
+            .get_value_and_value_hash(
+                parent_key,
+                true,
+                None::<&fn(&[u8], &GroveVersion) -> Option<ValueDefinedCostType>>,
+                grove_version,
+            )
+            .value
+            .map_err(Error::MerkError)?
+            .ok_or(Error::CorruptedData(
+                "expected merk to contain value of the restored subtree".to_string(),
+            ))?;
+        if elem_value_hash != subtree_scope.elem_value_hash {
+            return Err(Error::CorruptedData(
+                "restored subtree doesn't match the proven value hash".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
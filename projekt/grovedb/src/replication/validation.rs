@@ -0,0 +1,229 @@
+//! Post-sync validation
+//!
+//! Restoring a subtree verifies its merk hashes, but neither resolves the
+//! references it holds nor checks the sums of its sum trees against their
+//! children. For a state sync made with `MultiStateSyncInfo::with_validation`,
+//! `GroveDb::validate_state_sync` runs both checks over the restored GroveDB
+//! and fails if any of them does, so that the transaction of the state sync
+//! isn't committed. Applying the last chunk of the state sync (with
+//! `apply_chunk`, `apply_chunks` or `apply_multi_chunk`) runs it, and a state
+//! sync persisted with validation is still validated when resumed.
+
+use std::{collections::BTreeMap, fmt};
+
+use grovedb_merk::{tree::hash::CryptoHash, KVIterator};
+use grovedb_path::SubtreePath;
+use grovedb_storage::StorageContext;
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    element::{helpers::raw_decode, SumValue},
+    operations::proof::util::hex_to_ascii,
+    replication::MultiStateSyncInfo,
+    Element, Error, GroveDb, Query, Transaction,
+};
+
+/// Sum tree whose sum doesn't match the sum of its children
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SumTreeIssue {
+    /// Sum stored in the sum tree element
+    pub stored_sum: SumValue,
+    /// Sum of the children, `None` if it overflows
+    pub computed_sum: Option<SumValue>,
+}
+
+/// Outcome of the validation of a synced GroveDB
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncValidationReport {
+    /// Elements whose value hash doesn't match, as returned by
+    /// `GroveDb::verify_grovedb`: path -> (root hash or actual value hash,
+    /// expected value hash, actual value hash)
+    pub hash_issues: BTreeMap<Vec<Vec<u8>>, (CryptoHash, CryptoHash, CryptoHash)>,
+    /// Sum trees whose sum doesn't match their children: path -> issue
+    pub sum_tree_issues: BTreeMap<Vec<Vec<u8>>, SumTreeIssue>,
+    /// Error resolving the references (e.g. a dangling reference), which
+    /// aborts the verification of the remaining references
+    pub reference_error: Option<String>,
+}
+
+impl SyncValidationReport {
+    /// Returns true if no issue was found
+    pub fn is_valid(&self) -> bool {
+        self.hash_issues.is_empty()
+            && self.sum_tree_issues.is_empty()
+            && self.reference_error.is_none()
+    }
+}
+
+impl fmt::Display for SyncValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "no issues");
+        }
+        let mut issues = vec![];
+        for (path, (_, expected, actual)) in self.hash_issues.iter() {
+            issues.push(format!(
+                "hash mismatch at {}: expected {}, got {}",
+                display_path(path),
+                hex::encode(expected),
+                hex::encode(actual)
+            ));
+        }
+        for (path, issue) in self.sum_tree_issues.iter() {
+            issues.push(format!(
+                "sum mismatch at {}: stored {}, computed {}",
+                display_path(path),
+                issue.stored_sum,
+                issue
+                    .computed_sum
+                    .map_or("overflow".to_string(), |sum| sum.to_string())
+            ));
+        }
+        if let Some(reference_error) = &self.reference_error {
+            issues.push(format!("unresolvable reference: {}", reference_error));
+        }
+        write!(f, "{}", issues.join("; "))
+    }
+}
+
+impl<'db> MultiStateSyncInfo<'db> {
+    /// Validates the references and sum trees of the restored GroveDB once the
+    /// last chunk is applied, before it is committed (see
+    /// `GroveDb::validate_state_sync`). Applying the last chunk fails with
+    /// `ReplicationError::ValidationFailed` if the restored GroveDB isn't
+    /// valid.
+    pub fn with_validation(mut self) -> Self {
+        self.validation = true;
+        self
+    }
+}
+
+#[cfg(feature = "full")]
+impl GroveDb {
+    /// Validates the GroveDB restored by a completed state sync made
+    /// `with_validation` (see `validate_synced_state`). Returns
+    /// `Error::SyncValidationFailed` if any issue is found, in which case `tx`
+    /// must not be committed. Returns `None` for state syncs made without
+    /// validation.
+    pub fn validate_state_sync(
+        &self,
+        state_sync_info: &MultiStateSyncInfo,
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<Option<SyncValidationReport>, Error> {
+        if !state_sync_info.is_sync_completed() {
+            return Err(Error::InternalError(
+                "State sync is not completed".to_string(),
+            ));
+        }
+        if !state_sync_info.validation {
+            return Ok(None);
+        }
+        let report = self.validate_synced_state(tx, grove_version)?;
+        if !report.is_valid() {
+            return Err(Error::SyncValidationFailed(report));
+        }
+        Ok(Some(report))
+    }
+
+    /// Validates the GroveDB restored in `tx`: verifies all hashes and
+    /// references with `verify_grovedb`, and recomputes the sum of every sum
+    /// tree from its children. Failing to resolve a reference is reported,
+    /// other errors (e.g. of the storage) are returned.
+    pub fn validate_synced_state(
+        &self,
+        tx: &Transaction,
+        grove_version: &GroveVersion,
+    ) -> Result<SyncValidationReport, Error> {
+        let mut report = SyncValidationReport::default();
+        let hash_issues = match self.verify_grovedb(Some(tx), true, false, grove_version) {
+            Ok(hash_issues) => hash_issues,
+            Err(error) if is_reference_error(&error) => {
+                report.reference_error = Some(error.to_string());
+                self.verify_grovedb(Some(tx), false, false, grove_version)?
+            }
+            Err(error) => return Err(error),
+        };
+        report.hash_issues = hash_issues.into_iter().collect();
+        self.check_sum_trees(&SubtreePath::empty(), tx, &mut report, grove_version)?;
+        Ok(report)
+    }
+
+    // Checks the sum trees in the subtree at the given path and in its
+    // descendants, adding their issues to report. Returns the sum of the
+    // children of the subtree, None if it overflows.
+    fn check_sum_trees<B: AsRef<[u8]>>(
+        &self,
+        path: &SubtreePath<B>,
+        tx: &Transaction,
+        report: &mut SyncValidationReport,
+        grove_version: &GroveVersion,
+    ) -> Result<Option<SumValue>, Error> {
+        let merk = self
+            .open_transactional_merk_at_path(path.clone(), tx, None, grove_version)
+            .unwrap()?;
+        let mut all_query = Query::new();
+        all_query.insert_all();
+
+        let mut child_trees = vec![];
+        let mut sum = Some(0 as SumValue);
+        let mut element_iterator = KVIterator::new(merk.storage.raw_iter(), &all_query).unwrap();
+        while let Some((key, element_value)) = element_iterator.next_kv().unwrap() {
+            let element = raw_decode(&element_value, grove_version)?;
+            match element {
+                Element::SumItem(value, _) => {
+                    sum = sum.and_then(|sum| sum.checked_add(value));
+                }
+                Element::SumTree(_, stored_sum, _) => {
+                    sum = sum.and_then(|sum| sum.checked_add(stored_sum));
+                    child_trees.push((key, Some(stored_sum)));
+                }
+                Element::Tree(..) => child_trees.push((key, None)),
+                Element::Item(..) | Element::Reference(..) => {}
+            }
+        }
+        drop(element_iterator);
+        drop(merk);
+
+        for (key, stored_sum) in child_trees {
+            let child_path = path.derive_owned_with_child(key);
+            let computed_sum =
+                self.check_sum_trees(&SubtreePath::from(&child_path), tx, report, grove_version)?;
+            if let Some(stored_sum) = stored_sum {
+                if computed_sum != Some(stored_sum) {
+                    report.sum_tree_issues.insert(
+                        child_path.to_vec(),
+                        SumTreeIssue {
+                            stored_sum,
+                            computed_sum,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(sum)
+    }
+}
+
+// Returns true for the errors of resolving a reference, as opposed to errors
+// reading the GroveDB itself
+fn is_reference_error(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::CyclicReference
+            | Error::ReferenceLimit
+            | Error::MissingReference(_)
+            | Error::InvalidInput(_)
+            | Error::PathKeyNotFound(_)
+            | Error::PathNotFound(_)
+            | Error::PathParentLayerNotFound(_)
+            | Error::CorruptedReferencePathKeyNotFound(_)
+            | Error::CorruptedReferencePathNotFound(_)
+            | Error::CorruptedReferencePathParentLayerNotFound(_)
+    )
+}
+
+fn display_path(path: &[Vec<u8>]) -> String {
+    let segments: Vec<String> = path.iter().map(|segment| hex_to_ascii(segment)).collect();
+    format!("/{}", segments.join("/"))
+}
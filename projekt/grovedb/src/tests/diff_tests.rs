@@ -0,0 +1,171 @@
+//! Diff tests
+
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    operations::diff::DiffEntry,
+    tests::{make_deep_tree, make_test_grovedb, ANOTHER_TEST_LEAF, TEST_LEAF},
+    Element,
+};
+
+#[test]
+fn test_diff_of_identical_groves_is_empty() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+    let other_db = make_deep_tree(grove_version);
+
+    assert_eq!(
+        db.diff(&other_db, grove_version).expect("should diff"),
+        vec![]
+    );
+}
+
+#[test]
+fn test_diff_reports_added_removed_and_changed_entries() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+    let other_db = make_deep_tree(grove_version);
+
+    other_db
+        .insert(
+            [TEST_LEAF, b"innertree"].as_ref(),
+            b"key1",
+            Element::new_item(b"changed".to_vec()),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    other_db
+        .insert(
+            [TEST_LEAF, b"innertree"].as_ref(),
+            b"key4",
+            Element::new_item(b"value4".to_vec()),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    other_db
+        .delete(
+            [ANOTHER_TEST_LEAF, b"innertree2"].as_ref(),
+            b"key3",
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful delete");
+    other_db
+        .insert(
+            [TEST_LEAF].as_ref(),
+            b"innertree5",
+            Element::empty_tree(),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful subtree insert");
+
+    let entries = db.diff(&other_db, grove_version).expect("should diff");
+    assert_eq!(
+        entries,
+        vec![
+            DiffEntry::Added {
+                path: vec![TEST_LEAF.to_vec()],
+                key: b"innertree5".to_vec(),
+                element: Element::empty_tree(),
+            },
+            DiffEntry::Changed {
+                path: vec![TEST_LEAF.to_vec(), b"innertree".to_vec()],
+                key: b"key1".to_vec(),
+                old_element: Element::new_item(b"value1".to_vec()),
+                new_element: Element::new_item(b"changed".to_vec()),
+            },
+            DiffEntry::Added {
+                path: vec![TEST_LEAF.to_vec(), b"innertree".to_vec()],
+                key: b"key4".to_vec(),
+                element: Element::new_item(b"value4".to_vec()),
+            },
+            DiffEntry::Removed {
+                path: vec![ANOTHER_TEST_LEAF.to_vec(), b"innertree2".to_vec()],
+                key: b"key3".to_vec(),
+                element: Element::new_item(b"value3".to_vec()),
+            },
+        ]
+    );
+    assert_eq!(
+        entries[1].to_string(),
+        "~ /test_leaf/innertree/key1: Item(value1) -> Item(changed)"
+    );
+
+    // Diffing the other way round swaps added and removed entries
+    let reverse_entries = other_db.diff(&db, grove_version).expect("should diff");
+    assert_eq!(reverse_entries.len(), entries.len());
+    assert!(matches!(reverse_entries[0], DiffEntry::Removed { .. }));
+}
+
+#[test]
+fn test_diff_descends_into_trees_of_another_type_or_flags() {
+    let grove_version = GroveVersion::latest();
+    let db = make_test_grovedb(grove_version);
+    let other_db = make_test_grovedb(grove_version);
+    for (grove_db, value, flagged_tree, typed_tree) in [
+        (&db, b"old", Element::empty_tree(), Element::empty_tree()),
+        (
+            &other_db,
+            b"new",
+            Element::empty_tree_with_flags(Some(vec![1])),
+            Element::empty_sum_tree(),
+        ),
+    ] {
+        for (key, tree) in [
+            (b"flagged".as_slice(), flagged_tree),
+            (b"sum_tree".as_slice(), typed_tree),
+        ] {
+            grove_db
+                .insert([TEST_LEAF].as_ref(), key, tree, None, None, grove_version)
+                .unwrap()
+                .expect("successful subtree insert");
+            grove_db
+                .insert(
+                    [TEST_LEAF, key].as_ref(),
+                    b"key",
+                    Element::new_item(value.to_vec()),
+                    None,
+                    None,
+                    grove_version,
+                )
+                .unwrap()
+                .expect("successful item insert");
+        }
+    }
+
+    // The tree elements changed, and so did the items below them
+    let entries = db.diff(&other_db, grove_version).expect("should diff");
+    let changed: Vec<(Vec<Vec<u8>>, Vec<u8>)> = entries
+        .iter()
+        .map(|entry| {
+            assert!(matches!(entry, DiffEntry::Changed { .. }));
+            (entry.path().to_vec(), entry.key().to_vec())
+        })
+        .collect();
+    assert_eq!(
+        changed,
+        vec![
+            (vec![TEST_LEAF.to_vec()], b"flagged".to_vec()),
+            (vec![TEST_LEAF.to_vec()], b"sum_tree".to_vec()),
+            (
+                vec![TEST_LEAF.to_vec(), b"flagged".to_vec()],
+                b"key".to_vec()
+            ),
+            (
+                vec![TEST_LEAF.to_vec(), b"sum_tree".to_vec()],
+                b"key".to_vec()
+            ),
+        ]
+    );
+}
@@ -2,8 +2,22 @@
 
 pub mod common;
 
+mod diff_tests;
+
+mod query_aggregate_tests;
+
+mod query_cursor_tests;
+
+mod query_iterator_tests;
+
+mod query_language_tests;
+
 mod query_tests;
 
+mod query_value_filter_tests;
+
+mod replication_tests;
+
 mod sum_tree_tests;
 
 mod tree_hashes_tests;
@@ -519,6 +533,23 @@ pub fn make_deep_tree(grove_version: &GroveVersion) -> TempGroveDb {
     temp_db
 }
 
+/// A helper method to create a query of all the elements two levels below
+/// the deep nodes of `make_deep_tree`
+pub fn deep_leaf_query(left_to_right: bool, limit: Option<u16>, offset: Option<u16>) -> PathQuery {
+    let mut deeper_query = Query::new_with_direction(left_to_right);
+    deeper_query.insert_all();
+    let mut deep_node_query = Query::new_with_direction(left_to_right);
+    deep_node_query.insert_all();
+    deep_node_query.set_subquery(deeper_query);
+    let mut query = Query::new_with_direction(left_to_right);
+    query.insert_all();
+    query.set_subquery(deep_node_query);
+    PathQuery::new(
+        vec![DEEP_LEAF.to_vec()],
+        SizedQuery::new(query, limit, offset),
+    )
+}
+
 pub fn make_deep_tree_with_sum_trees(grove_version: &GroveVersion) -> TempGroveDb {
     // Tree Structure
     // root
@@ -3954,6 +3985,7 @@ mod tests {
                     },
                     conditional_subquery_branches: None,
                     left_to_right: true,
+                    value_filter: None,
                 },
                 limit: None,
                 offset: None,
@@ -0,0 +1,256 @@
+use grovedb_merk::proofs::Query;
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    query_result_type::QueryAggregates,
+    reference_path::ReferencePathType,
+    tests::{make_test_grovedb, TempGroveDb, TEST_LEAF},
+    Element, GroveDb, PathQuery, SizedQuery,
+};
+
+// Two sum trees of balances under TEST_LEAF/accounts, an item and a reference
+// to a balance under TEST_LEAF/other
+fn make_accounts_grovedb(grove_version: &GroveVersion) -> TempGroveDb {
+    let db = make_test_grovedb(grove_version);
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"accounts",
+        Element::empty_tree(),
+        None,
+        None,
+        grove_version,
+    )
+    .unwrap()
+    .expect("successful tree insert");
+    for (account, balances) in [
+        (b"a", vec![(b"1", 10), (b"2", -4), (b"3", 7)]),
+        (b"b", vec![(b"1", 25), (b"2", 1)]),
+    ] {
+        db.insert(
+            [TEST_LEAF, b"accounts"].as_ref(),
+            account,
+            Element::empty_sum_tree(),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful sum tree insert");
+        for (key, balance) in balances {
+            db.insert(
+                [TEST_LEAF, b"accounts", account].as_ref(),
+                key,
+                Element::new_sum_item(balance),
+                None,
+                None,
+                grove_version,
+            )
+            .unwrap()
+            .expect("successful sum item insert");
+        }
+    }
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"other",
+        Element::empty_tree(),
+        None,
+        None,
+        grove_version,
+    )
+    .unwrap()
+    .expect("successful tree insert");
+    db.insert(
+        [TEST_LEAF, b"other"].as_ref(),
+        b"item",
+        Element::new_item(b"value".to_vec()),
+        None,
+        None,
+        grove_version,
+    )
+    .unwrap()
+    .expect("successful item insert");
+    db.insert(
+        [TEST_LEAF, b"other"].as_ref(),
+        b"reference",
+        Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+            TEST_LEAF.to_vec(),
+            b"accounts".to_vec(),
+            b"b".to_vec(),
+            b"1".to_vec(),
+        ])),
+        None,
+        None,
+        grove_version,
+    )
+    .unwrap()
+    .expect("successful reference insert");
+    db
+}
+
+fn query_aggregates(
+    db: &TempGroveDb,
+    path_query: &PathQuery,
+    grove_version: &GroveVersion,
+) -> QueryAggregates {
+    db.query_aggregates(path_query, true, true, true, None, grove_version)
+        .unwrap()
+        .expect("expected successful aggregate query")
+}
+
+#[test]
+fn test_query_aggregates_of_sum_items_in_range() {
+    let grove_version = GroveVersion::latest();
+    let db = make_accounts_grovedb(grove_version);
+
+    let mut query = Query::new();
+    query.insert_range_inclusive(b"1".to_vec()..=b"2".to_vec());
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"accounts".to_vec(), b"a".to_vec()],
+        SizedQuery::new(query, None, None),
+    );
+    assert_eq!(
+        query_aggregates(&db, &path_query, grove_version),
+        QueryAggregates {
+            count: 2,
+            sum: 6,
+            min: Some(-4),
+            max: Some(10),
+        }
+    );
+
+    let (sums, _) = db
+        .query_sums(&path_query, true, true, true, None, grove_version)
+        .unwrap()
+        .expect("expected successful sum query");
+    assert_eq!(sums.iter().sum::<i64>(), 6);
+}
+
+#[test]
+fn test_query_aggregates_with_subqueries_and_limit() {
+    let grove_version = GroveVersion::latest();
+    let db = make_accounts_grovedb(grove_version);
+
+    let mut query = Query::new();
+    query.insert_all();
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    query.set_subquery(subquery);
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"accounts".to_vec()],
+        SizedQuery::new(query.clone(), None, None),
+    );
+    assert_eq!(
+        query_aggregates(&db, &path_query, grove_version),
+        QueryAggregates {
+            count: 5,
+            sum: 39,
+            min: Some(-4),
+            max: Some(25),
+        }
+    );
+
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"accounts".to_vec()],
+        SizedQuery::new(query, Some(4), None),
+    );
+    assert_eq!(
+        query_aggregates(&db, &path_query, grove_version),
+        QueryAggregates {
+            count: 4,
+            sum: 38,
+            min: Some(-4),
+            max: Some(25),
+        }
+    );
+
+    // The sum trees themselves, and the item and reference which only counts
+    // the item
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"accounts".to_vec()],
+        SizedQuery::new(query.clone(), None, None),
+    );
+    assert_eq!(
+        query_aggregates(&db, &path_query, grove_version),
+        QueryAggregates {
+            count: 2,
+            sum: 39,
+            min: Some(13),
+            max: Some(26),
+        }
+    );
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"other".to_vec()],
+        SizedQuery::new(query, None, None),
+    );
+    assert_eq!(
+        query_aggregates(&db, &path_query, grove_version),
+        QueryAggregates {
+            count: 2,
+            sum: 25,
+            min: Some(25),
+            max: Some(25),
+        }
+    );
+}
+
+#[test]
+fn test_query_aggregates_of_empty_result_set() {
+    let grove_version = GroveVersion::latest();
+    let db = make_accounts_grovedb(grove_version);
+
+    let mut query = Query::new();
+    query.insert_range_after(b"3".to_vec()..);
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"accounts".to_vec(), b"a".to_vec()],
+        SizedQuery::new(query, None, None),
+    );
+    assert_eq!(
+        query_aggregates(&db, &path_query, grove_version),
+        QueryAggregates::default()
+    );
+}
+
+#[test]
+fn test_verify_query_aggregates() {
+    let grove_version = GroveVersion::latest();
+    let db = make_accounts_grovedb(grove_version);
+
+    let mut query = Query::new();
+    query.insert_all();
+    let mut subquery = Query::new();
+    subquery.insert_range_from(b"2".to_vec()..);
+    query.set_subquery(subquery);
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"accounts".to_vec()],
+        SizedQuery::new(query, None, None),
+    );
+
+    let proof = db
+        .prove_query(&path_query, None, grove_version)
+        .unwrap()
+        .expect("expected successful proving");
+    let (root_hash, aggregates) =
+        GroveDb::verify_query_aggregates(&proof, &path_query, grove_version)
+            .expect("expected successful verification");
+    assert_eq!(
+        root_hash,
+        db.root_hash(None, grove_version)
+            .unwrap()
+            .expect("expected root hash")
+    );
+    assert_eq!(
+        aggregates,
+        query_aggregates(&db, &path_query, grove_version)
+    );
+    assert_eq!(
+        aggregates,
+        QueryAggregates {
+            count: 3,
+            sum: 4,
+            min: Some(-4),
+            max: Some(7),
+        }
+    );
+}
@@ -0,0 +1,203 @@
+use grovedb_merk::proofs::Query;
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    query_result_type::{PathKeyElementTrio, QueryResultType},
+    tests::{
+        deep_leaf_query, make_deep_tree, make_test_grovedb, TempGroveDb, DEEP_LEAF, TEST_LEAF,
+    },
+    Element, Error, GroveDb, PathQuery, QueryCursor, SizedQuery,
+};
+
+fn query_page(
+    db: &TempGroveDb,
+    path_query: &PathQuery,
+    grove_version: &GroveVersion,
+) -> (Vec<PathKeyElementTrio>, Option<QueryCursor>) {
+    let (elements, _, cursor) = db
+        .query_with_cursor(
+            path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("expected successful query");
+    (elements.to_path_key_elements(), cursor)
+}
+
+// Queries all pages of the path query, following the cursors
+fn query_all_pages(
+    db: &TempGroveDb,
+    path_query: &PathQuery,
+    grove_version: &GroveVersion,
+) -> Vec<Vec<PathKeyElementTrio>> {
+    let mut pages = vec![];
+    let mut page_query = path_query.clone();
+    loop {
+        let (page, cursor) = query_page(db, &page_query, grove_version);
+        pages.push(page);
+        match cursor {
+            Some(cursor) => {
+                page_query = path_query
+                    .after_cursor(&cursor, grove_version)
+                    .expect("expected cursor within the path query");
+            }
+            None => return pages,
+        }
+    }
+}
+
+#[test]
+fn test_query_cursor_pages_through_subqueries() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+
+    for left_to_right in [true, false] {
+        let (all_elements, cursor) = query_page(
+            &db,
+            &deep_leaf_query(left_to_right, None, None),
+            grove_version,
+        );
+        assert_eq!(all_elements.len(), 14);
+        assert_eq!(cursor, None);
+
+        let pages = query_all_pages(
+            &db,
+            &deep_leaf_query(left_to_right, Some(4), None),
+            grove_version,
+        );
+        assert_eq!(
+            pages.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![4, 4, 4, 2]
+        );
+        assert_eq!(pages.concat(), all_elements);
+    }
+}
+
+#[test]
+fn test_query_cursor_ends_on_a_page_filled_exactly() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+
+    let pages = query_all_pages(&db, &deep_leaf_query(true, Some(7), None), grove_version);
+    assert_eq!(
+        pages.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![7, 7, 0]
+    );
+}
+
+#[test]
+fn test_query_cursor_is_not_shifted_by_inserts_before_it() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+    let path = vec![TEST_LEAF.to_vec(), b"innertree".to_vec()];
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new(path.clone(), SizedQuery::new(query, Some(2), None));
+
+    let (page, cursor) = query_page(&db, &path_query, grove_version);
+    let keys: Vec<_> = page.into_iter().map(|(_, key, _)| key).collect();
+    assert_eq!(keys, vec![b"key1".to_vec(), b"key2".to_vec()]);
+    let cursor = cursor.expect("expected a cursor");
+    assert_eq!(cursor, QueryCursor::new(path.clone(), b"key2".to_vec()));
+
+    for key in [b"key0", b"key4"] {
+        db.insert(
+            path.as_slice(),
+            key,
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    }
+
+    let next_query = path_query
+        .after_cursor(&cursor, grove_version)
+        .expect("expected cursor within the path query");
+    let (page, _) = query_page(&db, &next_query, grove_version);
+    let keys: Vec<_> = page.into_iter().map(|(_, key, _)| key).collect();
+    assert_eq!(keys, vec![b"key3".to_vec(), b"key4".to_vec()]);
+}
+
+#[test]
+fn test_query_cursor_bytes_round_trip() {
+    let cursor = QueryCursor::new(
+        vec![DEEP_LEAF.to_vec(), b"deep_node_1".to_vec()],
+        b"deeper_2".to_vec(),
+    );
+    let bytes = cursor.to_bytes().expect("expected to encode cursor");
+    assert_eq!(
+        QueryCursor::from_bytes(&bytes).expect("expected to decode cursor"),
+        cursor
+    );
+    assert!(matches!(
+        QueryCursor::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(Error::CorruptedData(_))
+    ));
+}
+
+#[test]
+fn test_query_cursor_outside_of_path_query_is_rejected() {
+    let grove_version = GroveVersion::latest();
+    let cursor = QueryCursor::new(vec![TEST_LEAF.to_vec()], b"innertree".to_vec());
+    assert!(matches!(
+        deep_leaf_query(true, Some(4), None).after_cursor(&cursor, grove_version),
+        Err(Error::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_prove_and_verify_page_after_cursor() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+    let path_query = deep_leaf_query(true, Some(4), None);
+
+    let (_, cursor) = query_page(&db, &path_query, grove_version);
+    let next_query = path_query
+        .after_cursor(&cursor.expect("expected a cursor"), grove_version)
+        .expect("expected cursor within the path query");
+    let (page, _) = query_page(&db, &next_query, grove_version);
+
+    let proof = db
+        .prove_query(&next_query, None, grove_version)
+        .unwrap()
+        .expect("expected successful proving");
+    let (hash, proved_elements) = GroveDb::verify_query(&proof, &next_query, grove_version)
+        .expect("expected successful verification");
+    assert_eq!(
+        hash,
+        db.root_hash(None, grove_version)
+            .unwrap()
+            .expect("expected root hash")
+    );
+    assert_eq!(
+        proved_elements
+            .into_iter()
+            .map(|(path, key, element)| (path, key, element.expect("expected element")))
+            .collect::<Vec<_>>(),
+        page
+    );
+}
+
+#[test]
+fn test_query_cursor_of_empty_tree() {
+    let grove_version = GroveVersion::latest();
+    let db = make_test_grovedb(grove_version);
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec()],
+        SizedQuery::new(query, Some(2), None),
+    );
+
+    let (page, cursor) = query_page(&db, &path_query, grove_version);
+    assert!(page.is_empty());
+    assert_eq!(cursor, None);
+}
@@ -0,0 +1,353 @@
+use grovedb_merk::proofs::Query;
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    query_result_type::{QueryResultElement, QueryResultType},
+    reference_path::ReferencePathType,
+    tests::{deep_leaf_query, make_deep_tree, TempGroveDb, TEST_LEAF},
+    Element, Error, PathQuery, SizedQuery,
+};
+
+fn assert_iter_matches_query_raw(
+    db: &TempGroveDb,
+    path_query: &PathQuery,
+    grove_version: &GroveVersion,
+) {
+    let (expected, expected_skipped) = db
+        .query_raw(
+            path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("expected successful query");
+
+    let mut iter = db
+        .query_raw_iter(
+            path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            None,
+            grove_version,
+        )
+        .expect("expected query iterator");
+    let elements = iter
+        .by_ref()
+        .collect::<Result<Vec<QueryResultElement>, Error>>()
+        .expect("expected successful iteration");
+    assert_eq!(elements, expected.elements, "{}", path_query);
+    assert_eq!(iter.skipped(), expected_skipped, "{}", path_query);
+}
+
+#[test]
+fn test_query_raw_iter_matches_query_raw() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+
+    for left_to_right in [true, false] {
+        for (limit, offset) in [
+            (None, None),
+            (Some(5), None),
+            (Some(1), None),
+            (None, Some(4)),
+            (Some(3), Some(6)),
+            (Some(20), Some(2)),
+        ] {
+            assert_iter_matches_query_raw(
+                &db,
+                &deep_leaf_query(left_to_right, limit, offset),
+                grove_version,
+            );
+        }
+    }
+
+    // Subquery paths, keys and subtrees without matches
+    let mut query = Query::new();
+    query.insert_keys(vec![b"innertree".to_vec(), b"innertree4".to_vec()]);
+    query.set_subquery_path(vec![b"key5".to_vec()]);
+    let path_query = PathQuery::new(vec![TEST_LEAF.to_vec()], SizedQuery::new(query, None, None));
+    let mut iter = db
+        .query_raw_iter(
+            &path_query,
+            true,
+            true,
+            false,
+            QueryResultType::QueryKeyElementPairResultType,
+            None,
+            grove_version,
+        )
+        .expect("expected query iterator");
+    assert_eq!(
+        iter.next()
+            .expect("expected a result")
+            .expect("expected successful iteration"),
+        QueryResultElement::KeyElementPairResultItem((
+            b"key5".to_vec(),
+            Element::new_item(b"value5".to_vec())
+        ))
+    );
+    assert!(iter.next().is_none());
+
+    let mut query = Query::new();
+    query.insert_all();
+    let mut subquery = Query::new();
+    subquery.insert_key(b"key2".to_vec());
+    query.set_subquery(subquery);
+    for limit in [None, Some(1), Some(2)] {
+        assert_iter_matches_query_raw(
+            &db,
+            &PathQuery::new(
+                vec![TEST_LEAF.to_vec()],
+                SizedQuery::new(query.clone(), limit, None),
+            ),
+            grove_version,
+        );
+    }
+
+    // Missing subtrees, at the path of the query or below a subquery path,
+    // fail only if intermediate trees are required
+    let mut query = Query::new();
+    query.insert_key(b"key1".to_vec());
+    let missing_path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"missing".to_vec()],
+        SizedQuery::new(query.clone(), None, None),
+    );
+    let mut intermediate_query = Query::new();
+    intermediate_query.insert_key(b"innertree".to_vec());
+    intermediate_query.set_subquery_path(vec![b"missing".to_vec()]);
+    intermediate_query.set_subquery(query);
+    let missing_intermediate_path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec()],
+        SizedQuery::new(intermediate_query, None, None),
+    );
+    for path_query in [&missing_path_query, &missing_intermediate_path_query] {
+        for error_if_intermediate_path_tree_not_present in [true, false] {
+            let expected = db
+                .query_raw(
+                    path_query,
+                    true,
+                    true,
+                    error_if_intermediate_path_tree_not_present,
+                    QueryResultType::QueryPathKeyElementTrioResultType,
+                    None,
+                    grove_version,
+                )
+                .unwrap();
+            let result = db
+                .query_raw_iter(
+                    path_query,
+                    true,
+                    true,
+                    error_if_intermediate_path_tree_not_present,
+                    QueryResultType::QueryPathKeyElementTrioResultType,
+                    None,
+                    grove_version,
+                )
+                .and_then(|iter| iter.collect::<Result<Vec<QueryResultElement>, Error>>());
+            if error_if_intermediate_path_tree_not_present {
+                assert!(
+                    matches!(expected, Err(Error::PathParentLayerNotFound(_))),
+                    "{}",
+                    path_query
+                );
+                assert!(
+                    matches!(result, Err(Error::PathParentLayerNotFound(_))),
+                    "{}",
+                    path_query
+                );
+            } else {
+                let (expected, _) = expected.expect("expected successful query");
+                assert!(expected.elements.is_empty(), "{}", path_query);
+                assert_eq!(
+                    result.expect("expected successful iteration"),
+                    expected.elements,
+                    "{}",
+                    path_query
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_query_iter_is_lazy_and_accumulates_costs() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+    let path_query = deep_leaf_query(true, None, None);
+
+    let mut iter = db
+        .query_raw_iter(
+            &path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryKeyElementPairResultType,
+            None,
+            grove_version,
+        )
+        .expect("expected query iterator");
+    let first = iter
+        .next()
+        .expect("expected a first result")
+        .expect("expected successful iteration");
+    assert_eq!(
+        first,
+        QueryResultElement::KeyElementPairResultItem((
+            b"key1".to_vec(),
+            Element::new_item(b"value1".to_vec())
+        ))
+    );
+    let cost_after_first = iter.cost().clone();
+    assert!(cost_after_first.seek_count > 0);
+
+    assert_eq!(iter.by_ref().count(), 13);
+    assert!(iter.cost().seek_count > cost_after_first.seek_count);
+}
+
+#[test]
+fn test_query_iter_follows_references() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+    db.insert(
+        [TEST_LEAF].as_ref(),
+        b"references",
+        Element::empty_tree(),
+        None,
+        None,
+        grove_version,
+    )
+    .unwrap()
+    .expect("successful tree insert");
+    db.insert(
+        [TEST_LEAF, b"references"].as_ref(),
+        b"ref1",
+        Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+            TEST_LEAF.to_vec(),
+            b"innertree".to_vec(),
+            b"key1".to_vec(),
+        ])),
+        None,
+        None,
+        grove_version,
+    )
+    .unwrap()
+    .expect("successful reference insert");
+    db.insert(
+        [TEST_LEAF, b"references"].as_ref(),
+        b"sum",
+        Element::new_sum_item(7),
+        None,
+        None,
+        grove_version,
+    )
+    .unwrap()
+    .expect("successful sum item insert");
+
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"references".to_vec()],
+        SizedQuery::new(query, None, None),
+    );
+
+    let elements = db
+        .query_iter(
+            &path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryElementResultType,
+            None,
+            grove_version,
+        )
+        .expect("expected query iterator")
+        .collect::<Result<Vec<QueryResultElement>, Error>>()
+        .expect("expected successful iteration");
+    assert_eq!(
+        elements,
+        vec![
+            QueryResultElement::ElementResultItem(Element::new_item(b"value1".to_vec())),
+            QueryResultElement::ElementResultItem(Element::new_sum_item(7)),
+        ]
+    );
+
+    let values = db
+        .query_item_value_iter(&path_query, true, true, true, None, grove_version)
+        .expect("expected query iterator")
+        .collect::<Result<Vec<Vec<u8>>, Error>>()
+        .expect("expected successful iteration");
+    let (expected_values, _) = db
+        .query_item_value(&path_query, true, true, true, None, grove_version)
+        .unwrap()
+        .expect("expected successful query");
+    assert_eq!(values, expected_values);
+}
+
+#[test]
+fn test_query_iter_reads_through_transaction() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+    let transaction = db.start_transaction();
+    db.insert(
+        [TEST_LEAF, b"innertree"].as_ref(),
+        b"key0",
+        Element::new_item(b"value0".to_vec()),
+        None,
+        Some(&transaction),
+        grove_version,
+    )
+    .unwrap()
+    .expect("successful item insert");
+
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"innertree".to_vec()],
+        SizedQuery::new(query, None, None),
+    );
+    let count_keys = |transaction| {
+        db.query_raw_iter(
+            &path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryKeyElementPairResultType,
+            transaction,
+            grove_version,
+        )
+        .expect("expected query iterator")
+        .count()
+    };
+    assert_eq!(count_keys(None), 3);
+    assert_eq!(count_keys(Some(&transaction)), 4);
+}
+
+#[test]
+fn test_query_iter_stops_after_an_error() {
+    let grove_version = GroveVersion::latest();
+    let db = make_deep_tree(grove_version);
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new(vec![TEST_LEAF.to_vec()], SizedQuery::new(query, None, None));
+
+    // Following references fails on trees
+    let mut iter = db
+        .query_iter(
+            &path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryElementResultType,
+            None,
+            grove_version,
+        )
+        .expect("expected query iterator");
+    assert!(matches!(iter.next(), Some(Err(Error::InvalidQuery(_)))));
+    assert!(iter.next().is_none());
+}
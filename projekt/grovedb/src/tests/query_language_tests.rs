@@ -0,0 +1,334 @@
+use grovedb_merk::proofs::{
+    query::{NumericComparison, QueryItem, ValueFilter},
+    Query,
+};
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    query_result_type::QueryResultType,
+    tests::{make_test_grovedb, TEST_LEAF},
+    Element, Error, PathQuery, SizedQuery,
+};
+
+fn parse(text: &str) -> PathQuery {
+    text.parse()
+        .unwrap_or_else(|e| panic!("expected `{}` to parse: {}", text, e))
+}
+
+// Checks the path query is written as the text, and parses back from it
+fn assert_round_trip(path_query: &PathQuery, text: &str) {
+    assert_eq!(path_query.to_string(), text);
+    assert_eq!(&parse(text), path_query);
+}
+
+fn assert_syntax_error(text: &str) {
+    match text.parse::<PathQuery>() {
+        Err(Error::QuerySyntaxError(_)) => {}
+        result => panic!("expected a syntax error for `{}`, got {:?}", text, result),
+    }
+}
+
+fn single_item_query(item: QueryItem) -> PathQuery {
+    PathQuery::new(
+        vec![b"tree".to_vec()],
+        SizedQuery::new(Query::new_single_query_item(item), None, None),
+    )
+}
+
+#[test]
+fn test_parse_query_with_subquery_path() {
+    let mut query = Query::new();
+    query.insert_range_inclusive(vec![0x01]..=vec![0x10]);
+    query.set_subquery_path(vec![b"grades".to_vec()]);
+    let path_query = PathQuery::new(
+        vec![b"student".to_vec()],
+        SizedQuery::new(query, Some(10), None),
+    );
+
+    assert_eq!(
+        parse("SELECT * FROM /student WHERE key BETWEEN 0x01 AND 0x10 LIMIT 10 SUBQUERY /grades"),
+        path_query
+    );
+    assert_eq!(
+        parse("select *\n  from /student\n  where KEY between 0x01 and 0x10 limit 10 subquery /grades"),
+        path_query
+    );
+    assert_round_trip(
+        &path_query,
+        "SELECT * FROM /student WHERE KEY BETWEEN 0x01 AND 0x10 LIMIT 10 SUBQUERY /grades",
+    );
+}
+
+#[test]
+fn test_round_trip_of_query_items() {
+    for (item, condition) in [
+        (QueryItem::Key(b"a".to_vec()), "KEY = \"a\""),
+        (
+            QueryItem::Range(b"a".to_vec()..b"c".to_vec()),
+            "KEY >= \"a\" AND KEY < \"c\"",
+        ),
+        (
+            QueryItem::RangeInclusive(b"a".to_vec()..=b"c".to_vec()),
+            "KEY BETWEEN \"a\" AND \"c\"",
+        ),
+        (QueryItem::RangeFrom(b"a".to_vec()..), "KEY >= \"a\""),
+        (QueryItem::RangeTo(..b"c".to_vec()), "KEY < \"c\""),
+        (
+            QueryItem::RangeToInclusive(..=b"c".to_vec()),
+            "KEY <= \"c\"",
+        ),
+        (QueryItem::RangeAfter(b"a".to_vec()..), "KEY > \"a\""),
+        (
+            QueryItem::RangeAfterTo(b"a".to_vec()..b"c".to_vec()),
+            "KEY > \"a\" AND KEY < \"c\"",
+        ),
+        (
+            QueryItem::RangeAfterToInclusive(b"a".to_vec()..=b"c".to_vec()),
+            "KEY > \"a\" AND KEY <= \"c\"",
+        ),
+    ] {
+        assert_round_trip(
+            &single_item_query(item),
+            &format!("SELECT * FROM /tree WHERE {}", condition),
+        );
+    }
+    assert_round_trip(
+        &single_item_query(QueryItem::RangeFull(..)),
+        "SELECT * FROM /tree",
+    );
+    assert_eq!(
+        parse("SELECT * FROM /tree WHERE ANY KEY"),
+        single_item_query(QueryItem::RangeFull(..))
+    );
+}
+
+#[test]
+fn test_round_trip_of_keys_and_literals() {
+    let mut query = Query::new_with_direction(false);
+    query.insert_keys(vec![
+        vec![],
+        vec![0, 255],
+        b"say \"hi\"".to_vec(),
+        b"back\\slash".to_vec(),
+    ]);
+    let path_query = PathQuery::new(
+        vec![
+            TEST_LEAF.to_vec(),
+            b"0x12".to_vec(),
+            vec![0xff],
+            b"with space".to_vec(),
+            vec![],
+        ],
+        SizedQuery::new(query, Some(5), Some(2)),
+    );
+    assert_round_trip(
+        &path_query,
+        "SELECT * FROM /test_leaf/\"0x12\"/0xff/\"with space\"/\"\" WHERE KEY IN (\"\", 0x00ff, \
+         \"back\\\\slash\", \"say \\\"hi\\\"\") ORDER BY KEY DESC LIMIT 5 OFFSET 2",
+    );
+
+    let mut query = Query::new();
+    query.insert_key(b"a".to_vec());
+    query.insert_range_after(b"m".to_vec()..);
+    assert_round_trip(
+        &PathQuery::new(vec![], SizedQuery::new(query, None, None)),
+        "SELECT * FROM / WHERE KEY = \"a\" OR KEY > \"m\"",
+    );
+
+    assert_round_trip(
+        &PathQuery::new(vec![], SizedQuery::new(Query::new(), None, None)),
+        "SELECT * FROM / WHERE KEY IN ()",
+    );
+    assert_eq!(
+        parse("SELECT * FROM /tree WHERE KEY IN (\"b\") OR KEY = \"a\" ORDER BY KEY ASC"),
+        {
+            let mut query = Query::new();
+            query.insert_keys(vec![b"a".to_vec(), b"b".to_vec()]);
+            PathQuery::new(vec![b"tree".to_vec()], SizedQuery::new(query, None, None))
+        }
+    );
+}
+
+#[test]
+fn test_round_trip_of_subqueries() {
+    let mut grades_subquery = Query::new();
+    grades_subquery.insert_range_from(b"2020".to_vec()..);
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    subquery.set_subquery(grades_subquery);
+    let mut conditional_subquery = Query::new_with_direction(false);
+    conditional_subquery.insert_all();
+
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery_path(vec![b"grades".to_vec()]);
+    query.set_subquery(subquery);
+    query.add_conditional_subquery(
+        QueryItem::Key(b"dave".to_vec()),
+        Some(vec![b"courses".to_vec()]),
+        None,
+    );
+    query.add_conditional_subquery(
+        QueryItem::RangeTo(..b"b".to_vec()),
+        None,
+        Some(conditional_subquery),
+    );
+    query.add_conditional_subquery(QueryItem::Key(b"carol".to_vec()), None, None);
+    let path_query = PathQuery::new(
+        vec![b"student".to_vec()],
+        SizedQuery::new(query, Some(100), None),
+    );
+    assert_round_trip(
+        &path_query,
+        "SELECT * FROM /student LIMIT 100 SUBQUERY /grades (SELECT * SUBQUERY (SELECT * WHERE \
+         KEY >= \"2020\")) SUBQUERY WHEN KEY = \"dave\" /courses SUBQUERY WHEN KEY < \"b\" \
+         (SELECT * ORDER BY KEY DESC) SUBQUERY WHEN KEY = \"carol\"",
+    );
+}
+
+#[test]
+fn test_round_trip_of_value_filters() {
+    for (value_filter, condition) in [
+        (ValueFilter::Equal(b"bob".to_vec()), "VALUE = \"bob\""),
+        (ValueFilter::Prefix(vec![0, 1]), "VALUE STARTS WITH 0x0001"),
+        (
+            ValueFilter::Compare(NumericComparison::Equal, 7),
+            "VALUE = 7",
+        ),
+        (
+            ValueFilter::Compare(NumericComparison::LessThan, -7),
+            "VALUE < -7",
+        ),
+        (
+            ValueFilter::Compare(NumericComparison::GreaterThanOrEqual, 300),
+            "VALUE >= 300",
+        ),
+    ] {
+        let mut query = Query::new();
+        query.insert_all();
+        query.set_value_filter(value_filter.clone());
+        assert_round_trip(
+            &PathQuery::new(vec![b"tree".to_vec()], SizedQuery::new(query, None, None)),
+            &format!("SELECT * FROM /tree WHERE {}", condition),
+        );
+
+        let mut query = Query::new();
+        query.insert_range_after(b"a".to_vec()..);
+        query.set_value_filter(value_filter);
+        assert_round_trip(
+            &PathQuery::new(vec![b"tree".to_vec()], SizedQuery::new(query, None, None)),
+            &format!("SELECT * FROM /tree WHERE KEY > \"a\" AND {}", condition),
+        );
+    }
+}
+
+#[test]
+fn test_query_syntax_errors() {
+    for text in [
+        "",
+        "SELECT",
+        "SELECT * FROM student",
+        "SELECT * FROM /student/",
+        "SELECT * FROM /student WHERE KEY = 1",
+        "SELECT * FROM /student WHERE KEY = 0x1",
+        "SELECT * FROM /student WHERE KEY = \"a",
+        "SELECT * FROM /student WHERE KEY = \"\\n\"",
+        "SELECT * FROM /student WHERE KEY >= \"a\" AND KEY > \"c\"",
+        "SELECT * FROM /student WHERE KEY IN (\"a\" \"b\")",
+        "SELECT * FROM /student WHERE VALUE STARTS WITH 1",
+        "SELECT * FROM /student WHERE VALUE < \"a\"",
+        "SELECT * FROM /student ORDER BY KEY",
+        "SELECT * FROM /student LIMIT 65536",
+        "SELECT * FROM /student LIMIT -1",
+        "SELECT * FROM /student OFFSET 1 LIMIT 1",
+        "SELECT * FROM /student SUBQUERY",
+        "SELECT * FROM /student SUBQUERY /a SUBQUERY /b",
+        "SELECT * FROM /student SUBQUERY WHEN KEY IN (\"a\", \"b\") /c",
+        "SELECT * FROM /student SUBQUERY (SELECT * FROM /grades)",
+        "SELECT * FROM /student SUBQUERY (SELECT *",
+        "SELECT * FROM /student ;",
+    ] {
+        assert_syntax_error(text);
+    }
+
+    assert_eq!(
+        "SELECT * FROM /student WHERE KEY = 1"
+            .parse::<PathQuery>()
+            .expect_err("expected a syntax error")
+            .to_string(),
+        "query syntax error: expected quoted text or 0x-prefixed hex, found `1` at position 35"
+    );
+}
+
+#[test]
+fn test_parsed_query_returns_the_same_results() {
+    let grove_version = GroveVersion::latest();
+    let db = make_test_grovedb(grove_version);
+    let insert = |path: &[&[u8]], key: &[u8], element: Element| {
+        db.insert(path, key, element, None, None, grove_version)
+            .unwrap()
+            .expect("successful insert");
+    };
+    insert(&[TEST_LEAF], b"student", Element::empty_tree());
+    for student in [[0x01], [0x08], [0x20]] {
+        insert(&[TEST_LEAF, b"student"], &student, Element::empty_tree());
+        insert(
+            &[TEST_LEAF, b"student", &student],
+            b"grades",
+            Element::empty_tree(),
+        );
+        for (course, grade) in [(b"art", 3u8), (b"law", 5)] {
+            insert(
+                &[TEST_LEAF, b"student", &student, b"grades"],
+                course,
+                Element::new_item(vec![grade + student[0]]),
+            );
+        }
+    }
+
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    subquery.set_value_filter(ValueFilter::Compare(NumericComparison::GreaterThan, 4));
+    let mut query = Query::new();
+    query.insert_range_inclusive(vec![0x01]..=vec![0x10]);
+    query.set_subquery_path(vec![b"grades".to_vec()]);
+    query.set_subquery(subquery);
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"student".to_vec()],
+        SizedQuery::new(query, Some(10), None),
+    );
+    let parsed_path_query = parse(
+        "SELECT * FROM /test_leaf/student WHERE KEY BETWEEN 0x01 AND 0x10 LIMIT 10 SUBQUERY \
+         /grades (SELECT * WHERE VALUE > 4)",
+    );
+    assert_eq!(parsed_path_query, path_query);
+
+    let query_results = |path_query: &PathQuery| {
+        db.query_raw(
+            path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("expected successful query")
+        .0
+        .to_path_key_elements()
+    };
+    let results = query_results(&parsed_path_query);
+    assert_eq!(results, query_results(&path_query));
+    assert_eq!(
+        results
+            .into_iter()
+            .map(|(_, key, element)| (key, element))
+            .collect::<Vec<_>>(),
+        vec![
+            (b"law".to_vec(), Element::new_item(vec![6])),
+            (b"art".to_vec(), Element::new_item(vec![11])),
+            (b"law".to_vec(), Element::new_item(vec![13])),
+        ]
+    );
+}
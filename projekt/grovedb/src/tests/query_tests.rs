@@ -3432,6 +3432,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3478,6 +3479,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3524,6 +3526,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3570,6 +3573,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3616,6 +3620,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3662,6 +3667,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3710,6 +3716,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3758,6 +3765,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3806,6 +3814,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3854,6 +3863,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3902,6 +3912,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3950,6 +3961,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -3998,6 +4010,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4046,6 +4059,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4097,6 +4111,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4148,6 +4163,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4199,6 +4215,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4250,6 +4267,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4301,6 +4319,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4352,6 +4371,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4403,6 +4423,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4454,6 +4475,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4505,6 +4527,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4556,6 +4579,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4607,6 +4631,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4658,6 +4683,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4709,6 +4735,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4760,6 +4787,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: Some(IndexMap::from([(
                         QueryItem::Key(vec![]),
                         SubqueryBranch {
@@ -4827,6 +4855,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: true,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -4878,6 +4907,7 @@ mod tests {
                         subquery: Some(Query::new_range_full().into()),
                     },
                     left_to_right: false,
+                    value_filter: None,
                     conditional_subquery_branches: None,
                 },
                 limit: Some(2),
@@ -0,0 +1,280 @@
+use grovedb_merk::proofs::{
+    query::{NumericComparison, ValueFilter},
+    Query,
+};
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    query_result_type::{Key, Path, QueryResultElement, QueryResultType},
+    tests::{make_test_grovedb, TempGroveDb, TEST_LEAF},
+    Element, Error, PathQuery, SizedQuery,
+};
+
+// Names under TEST_LEAF/names, and two trees of scores under TEST_LEAF/scores
+// holding big-endian integers, a sum item and a value too long to be a number
+fn make_filter_grovedb(grove_version: &GroveVersion) -> TempGroveDb {
+    let db = make_test_grovedb(grove_version);
+    let insert = |path: &[&[u8]], key: &[u8], element: Element| {
+        db.insert(path, key, element, None, None, grove_version)
+            .unwrap()
+            .expect("successful insert");
+    };
+    insert(&[TEST_LEAF], b"names", Element::empty_tree());
+    for (key, name) in [
+        (b"k1", "alice"),
+        (b"k2", "albert"),
+        (b"k3", "bob"),
+        (b"k4", "alfred"),
+    ] {
+        insert(
+            &[TEST_LEAF, b"names"],
+            key,
+            Element::new_item(name.as_bytes().to_vec()),
+        );
+    }
+    insert(&[TEST_LEAF], b"scores", Element::empty_tree());
+    insert(&[TEST_LEAF, b"scores"], b"s1", Element::empty_tree());
+    insert(
+        &[TEST_LEAF, b"scores", b"s1"],
+        b"a",
+        Element::new_item(10u32.to_be_bytes().to_vec()),
+    );
+    insert(
+        &[TEST_LEAF, b"scores", b"s1"],
+        b"b",
+        Element::new_item(300u32.to_be_bytes().to_vec()),
+    );
+    insert(
+        &[TEST_LEAF, b"scores", b"s1"],
+        b"c",
+        Element::new_sum_item(-5),
+    );
+    insert(&[TEST_LEAF, b"scores"], b"s2", Element::empty_tree());
+    insert(
+        &[TEST_LEAF, b"scores", b"s2"],
+        b"a",
+        Element::new_item(50u64.to_be_bytes().to_vec()),
+    );
+    insert(
+        &[TEST_LEAF, b"scores", b"s2"],
+        b"b",
+        Element::new_item(b"not a number".to_vec()),
+    );
+    insert(
+        &[TEST_LEAF, b"scores", b"s2"],
+        b"c",
+        Element::new_sum_item(500),
+    );
+    db
+}
+
+fn names_query(value_filter: ValueFilter, limit: Option<u16>, offset: Option<u16>) -> PathQuery {
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_value_filter(value_filter);
+    PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"names".to_vec()],
+        SizedQuery::new(query, limit, offset),
+    )
+}
+
+fn scores_query(value_filter: ValueFilter, limit: Option<u16>) -> PathQuery {
+    let mut subquery = Query::new();
+    subquery.insert_all();
+    subquery.set_value_filter(value_filter);
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery(subquery);
+    PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"scores".to_vec()],
+        SizedQuery::new(query, limit, None),
+    )
+}
+
+// Returns the paths and keys of the results of the path query, checking the
+// streaming iterator returns the same
+fn query_path_keys(
+    db: &TempGroveDb,
+    path_query: &PathQuery,
+    grove_version: &GroveVersion,
+) -> Vec<(Path, Key)> {
+    let (results, _) = db
+        .query_raw(
+            path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("expected successful query");
+    let iterated_results = db
+        .query_raw_iter(
+            path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            None,
+            grove_version,
+        )
+        .expect("expected query iterator")
+        .collect::<Result<Vec<QueryResultElement>, Error>>()
+        .expect("expected successful iteration");
+    assert_eq!(iterated_results, results.elements, "{}", path_query);
+    results
+        .to_path_key_elements()
+        .into_iter()
+        .map(|(path, key, _)| (path, key))
+        .collect()
+}
+
+fn keys(path_keys: Vec<(Path, Key)>) -> Vec<Key> {
+    path_keys.into_iter().map(|(_, key)| key).collect()
+}
+
+#[test]
+fn test_value_filter_on_item_bytes() {
+    let grove_version = GroveVersion::latest();
+    let db = make_filter_grovedb(grove_version);
+
+    let equal_bob = names_query(ValueFilter::Equal(b"bob".to_vec()), None, None);
+    assert_eq!(
+        keys(query_path_keys(&db, &equal_bob, grove_version)),
+        vec![b"k3".to_vec()]
+    );
+
+    let prefix_al = ValueFilter::Prefix(b"al".to_vec());
+    assert_eq!(
+        keys(query_path_keys(
+            &db,
+            &names_query(prefix_al.clone(), None, None),
+            grove_version
+        )),
+        vec![b"k1".to_vec(), b"k2".to_vec(), b"k4".to_vec()]
+    );
+
+    // Filtered out elements count toward neither the limit nor the offset
+    assert_eq!(
+        keys(query_path_keys(
+            &db,
+            &names_query(prefix_al.clone(), Some(3), None),
+            grove_version
+        )),
+        vec![b"k1".to_vec(), b"k2".to_vec(), b"k4".to_vec()]
+    );
+    assert_eq!(
+        keys(query_path_keys(
+            &db,
+            &names_query(prefix_al, Some(1), Some(2)),
+            grove_version
+        )),
+        vec![b"k4".to_vec()]
+    );
+}
+
+#[test]
+fn test_numeric_value_filter_in_subqueries() {
+    let grove_version = GroveVersion::latest();
+    let db = make_filter_grovedb(grove_version);
+    let scores_path = |tree: &[u8]| vec![TEST_LEAF.to_vec(), b"scores".to_vec(), tree.to_vec()];
+
+    let greater_than_20 = ValueFilter::Compare(NumericComparison::GreaterThan, 20);
+    assert_eq!(
+        query_path_keys(
+            &db,
+            &scores_query(greater_than_20.clone(), None),
+            grove_version
+        ),
+        vec![
+            (scores_path(b"s1"), b"b".to_vec()),
+            (scores_path(b"s2"), b"a".to_vec()),
+            (scores_path(b"s2"), b"c".to_vec()),
+        ]
+    );
+    assert_eq!(
+        query_path_keys(&db, &scores_query(greater_than_20, Some(2)), grove_version),
+        vec![
+            (scores_path(b"s1"), b"b".to_vec()),
+            (scores_path(b"s2"), b"a".to_vec()),
+        ]
+    );
+
+    let negative = ValueFilter::Compare(NumericComparison::LessThan, 0);
+    assert_eq!(
+        query_path_keys(&db, &scores_query(negative, None), grove_version),
+        vec![(scores_path(b"s1"), b"c".to_vec())]
+    );
+
+    // A subtree without matches still counts toward the limit, as
+    // `decrease_limit_on_range_with_no_sub_elements` is set
+    let equal_50 = ValueFilter::Compare(NumericComparison::Equal, 50);
+    assert_eq!(
+        query_path_keys(&db, &scores_query(equal_50, Some(1)), grove_version),
+        vec![]
+    );
+}
+
+#[test]
+fn test_value_filter_on_subquery_path_results() {
+    let grove_version = GroveVersion::latest();
+    let db = make_filter_grovedb(grove_version);
+
+    let mut query = Query::new();
+    query.insert_all();
+    query.set_subquery_path(vec![b"b".to_vec()]);
+    query.set_value_filter(ValueFilter::Compare(
+        NumericComparison::GreaterThanOrEqual,
+        100,
+    ));
+    let path_query = PathQuery::new(
+        vec![TEST_LEAF.to_vec(), b"scores".to_vec()],
+        SizedQuery::new(query, None, None),
+    );
+    assert_eq!(
+        query_path_keys(&db, &path_query, grove_version),
+        vec![(
+            vec![TEST_LEAF.to_vec(), b"scores".to_vec(), b"s1".to_vec()],
+            b"b".to_vec()
+        )]
+    );
+}
+
+#[test]
+fn test_value_filter_serializes_with_the_query() {
+    let path_query = scores_query(
+        ValueFilter::Compare(NumericComparison::LessThanOrEqual, -7),
+        Some(4),
+    );
+    let config = bincode::config::standard()
+        .with_big_endian()
+        .with_no_limit();
+    let bytes = bincode::encode_to_vec(&path_query, config).expect("expected to encode");
+    let (decoded, _): (PathQuery, _) =
+        bincode::decode_from_slice(&bytes, config).expect("expected to decode");
+    assert_eq!(decoded, path_query);
+}
+
+#[test]
+fn test_value_filters_can_not_be_proved_or_merged() {
+    let grove_version = GroveVersion::latest();
+    let db = make_filter_grovedb(grove_version);
+    let path_query = scores_query(ValueFilter::Prefix(b"a".to_vec()), None);
+
+    assert!(matches!(
+        db.prove_query(&path_query, None, grove_version).unwrap(),
+        Err(Error::InvalidQuery(_))
+    ));
+    assert!(matches!(
+        PathQuery::merge(
+            vec![
+                &path_query,
+                &names_query(ValueFilter::Equal(vec![]), None, None)
+            ],
+            grove_version
+        ),
+        Err(Error::NotSupported(_))
+    ));
+}
@@ -0,0 +1,2036 @@
+//! Replication tests
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use grovedb_merk::tree::hash::CryptoHash;
+use grovedb_storage::{Storage, StorageBatch};
+use grovedb_version::version::GroveVersion;
+use tempfile::TempDir;
+
+use crate::{
+    batch::QualifiedGroveDbOp,
+    reference_path::ReferencePathType,
+    replication::{
+        negotiate_state_sync_version, serve_chunks_connection, write_chunk_directory, ChunkSource,
+        DirectoryChunkSource, LocalOpLogPrimary, MultiStateSyncInfo, OpLogSource, ReplicationError,
+        SnapshotManifest, StateSyncEvent, SumTreeIssue, TcpChunkSource, CURRENT_STATE_SYNC_VERSION,
+    },
+    tests::{
+        make_deep_tree, make_deep_tree_with_sum_trees, make_empty_grovedb, make_test_grovedb,
+        ANOTHER_TEST_LEAF, DEEP_LEAF, TEST_LEAF,
+    },
+    Element, Error, GroveDb, Transaction,
+};
+
+/// Fetches chunks from `source_db` and applies them to `target_db` until the
+/// queue is drained or `max_chunks` chunks have been applied
+fn sync_chunks<'db>(
+    source_db: &GroveDb,
+    target_db: &'db GroveDb,
+    mut state_sync_info: MultiStateSyncInfo<'db>,
+    chunk_queue: &mut VecDeque<Vec<u8>>,
+    tx: &'db Transaction,
+    max_chunks: Option<usize>,
+    grove_version: &GroveVersion,
+) -> MultiStateSyncInfo<'db> {
+    let mut num_applied_chunks = 0;
+    while let Some(chunk_id) = chunk_queue.pop_front() {
+        let chunk = source_db
+            .fetch_chunk(&chunk_id, None, CURRENT_STATE_SYNC_VERSION, grove_version)
+            .expect("expected to fetch chunk");
+        let (next_chunk_ids, new_state_sync_info) = target_db
+            .apply_chunk(
+                state_sync_info,
+                &chunk_id,
+                chunk,
+                tx,
+                CURRENT_STATE_SYNC_VERSION,
+                grove_version,
+            )
+            .expect("expected to apply chunk");
+        state_sync_info = new_state_sync_info;
+        chunk_queue.extend(next_chunk_ids);
+        num_applied_chunks += 1;
+        if max_chunks == Some(num_applied_chunks) {
+            break;
+        }
+    }
+    state_sync_info
+}
+
+#[test]
+fn test_replication_of_deep_tree() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+    assert_eq!(chunk_queue, VecDeque::from([app_hash.to_vec()]));
+
+    let state_sync_info = sync_chunks(
+        &source_db,
+        &target_db,
+        state_sync_info,
+        &mut chunk_queue,
+        &tx,
+        None,
+        grove_version,
+    );
+    assert!(state_sync_info.is_sync_completed());
+    drop(state_sync_info);
+
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+}
+
+#[test]
+fn test_replication_resumes_after_interruption() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    // Apply only a few chunks and commit, then forget the in-memory state as if
+    // the process was restarted
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+    let state_sync_info = sync_chunks(
+        &source_db,
+        &target_db,
+        state_sync_info,
+        &mut chunk_queue,
+        &tx,
+        Some(3),
+        grove_version,
+    );
+    assert!(!state_sync_info.is_sync_completed());
+    let mut pending_chunk_ids = state_sync_info.pending_global_chunk_ids();
+    drop(state_sync_info);
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+
+    // Resume the state sync from the persisted progress
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to resume syncing");
+    let mut resumed_chunk_ids = state_sync_info.pending_global_chunk_ids();
+    pending_chunk_ids.sort();
+    resumed_chunk_ids.sort();
+    assert_eq!(pending_chunk_ids, resumed_chunk_ids);
+
+    let mut chunk_queue: VecDeque<Vec<u8>> = resumed_chunk_ids.into();
+    let state_sync_info = sync_chunks(
+        &source_db,
+        &target_db,
+        state_sync_info,
+        &mut chunk_queue,
+        &tx,
+        None,
+        grove_version,
+    );
+    assert!(state_sync_info.is_sync_completed());
+    drop(state_sync_info);
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+
+    // Once completed there is nothing left to resume
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    assert_eq!(
+        state_sync_info.pending_global_chunk_ids(),
+        vec![app_hash.to_vec()]
+    );
+}
+
+#[test]
+fn test_replication_observer_receives_events() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let events: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let observed_events = events.clone();
+    let observer = move |event: StateSyncEvent| {
+        let description = match event {
+            StateSyncEvent::SyncStarted { resumed, .. } => format!("started:{}", resumed),
+            StateSyncEvent::SubtreeStarted { path } => format!("subtree_started:{}", path.len()),
+            StateSyncEvent::ChunkApplied { .. } => "chunk_applied".to_string(),
+            StateSyncEvent::SubtreeFinalized { path, .. } => {
+                format!("subtree_finalized:{}", path.len())
+            }
+            StateSyncEvent::SyncCompleted { app_hash, .. } => {
+                format!("completed:{}", hex::encode(app_hash))
+            }
+            StateSyncEvent::Error { error, .. } => format!("error:{}", error),
+        };
+        observed_events.lock().unwrap().push(description);
+    };
+
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default().with_observer(Arc::new(observer)),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+    let state_sync_info = sync_chunks(
+        &source_db,
+        &target_db,
+        state_sync_info,
+        &mut chunk_queue,
+        &tx,
+        None,
+        grove_version,
+    );
+    assert!(state_sync_info.is_sync_completed());
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.first(), Some(&"started:false".to_string()));
+    assert_eq!(
+        events.last(),
+        Some(&format!("completed:{}", hex::encode(app_hash)))
+    );
+    let num_started = events
+        .iter()
+        .filter(|e| e.starts_with("subtree_started"))
+        .count();
+    let num_finalized = events
+        .iter()
+        .filter(|e| e.starts_with("subtree_finalized"))
+        .count();
+    assert_eq!(num_started, num_finalized);
+    assert!(events.iter().all(|e| !e.starts_with("error")));
+}
+
+#[test]
+fn test_snapshot_session_serves_pinned_state() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let checkpoint_dir = TempDir::new().unwrap();
+    let checkpoint_path = checkpoint_dir.path().join("snapshot");
+
+    let session = source_db
+        .start_snapshot_session(&checkpoint_path, grove_version)
+        .expect("expected to start snapshot session");
+    let app_hash = session.app_hash();
+    assert_eq!(
+        source_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+
+    // The source keeps taking writes while the replica syncs
+    source_db
+        .insert(
+            [TEST_LEAF].as_ref(),
+            b"new_key",
+            Element::new_item(b"new_value".to_vec()),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful insert");
+    assert_ne!(
+        source_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+
+    let tx = target_db.start_transaction();
+    let mut state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+    while let Some(chunk_id) = chunk_queue.pop_front() {
+        let chunk = session
+            .fetch_chunk(&chunk_id, CURRENT_STATE_SYNC_VERSION, grove_version)
+            .expect("expected to fetch chunk");
+        let (next_chunk_ids, new_state_sync_info) = target_db
+            .apply_chunk(
+                state_sync_info,
+                &chunk_id,
+                chunk,
+                &tx,
+                CURRENT_STATE_SYNC_VERSION,
+                grove_version,
+            )
+            .expect("expected to apply chunk");
+        state_sync_info = new_state_sync_info;
+        chunk_queue.extend(next_chunk_ids);
+    }
+    assert!(state_sync_info.is_sync_completed());
+    drop(state_sync_info);
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+
+    session.release().expect("expected to release session");
+    assert!(!checkpoint_path.exists());
+}
+
+#[test]
+fn test_dropped_snapshot_session_removes_checkpoint() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let checkpoint_dir = TempDir::new().unwrap();
+    let checkpoint_path = checkpoint_dir.path().join("snapshot");
+
+    let session = source_db
+        .start_snapshot_session(&checkpoint_path, grove_version)
+        .expect("expected to start snapshot session");
+    assert!(checkpoint_path.exists());
+    drop(session);
+    assert!(!checkpoint_path.exists());
+
+    // The checkpoint path is free again for a new session
+    source_db
+        .start_snapshot_session(&checkpoint_path, grove_version)
+        .expect("expected to start snapshot session")
+        .release()
+        .expect("expected to release session");
+    assert!(!checkpoint_path.exists());
+}
+
+#[test]
+fn test_replication_with_multi_chunks() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+    let limit = 2048;
+
+    let num_applied_chunks = Arc::new(AtomicUsize::new(0));
+    let observed_applied_chunks = num_applied_chunks.clone();
+    let observer = move |event: StateSyncEvent| {
+        if let StateSyncEvent::ChunkApplied { .. } = event {
+            observed_applied_chunks.fetch_add(1, Ordering::SeqCst);
+        }
+    };
+
+    let tx = target_db.start_transaction();
+    let mut state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default().with_observer(Arc::new(observer)),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+
+    // Every round trip requests all pending chunks, the source packs as many
+    // of them as fit in the limit
+    let mut num_round_trips = 0;
+    while !state_sync_info.is_sync_completed() {
+        let multi_chunk = source_db
+            .fetch_multi_chunk(
+                &state_sync_info.pending_global_chunk_ids(),
+                limit,
+                None,
+                CURRENT_STATE_SYNC_VERSION,
+                grove_version,
+            )
+            .expect("expected to fetch multi chunk");
+        assert!(multi_chunk.len() <= limit);
+        let (_, new_state_sync_info) = target_db
+            .apply_multi_chunk(
+                state_sync_info,
+                multi_chunk,
+                &tx,
+                CURRENT_STATE_SYNC_VERSION,
+                grove_version,
+            )
+            .expect("expected to apply multi chunk");
+        state_sync_info = new_state_sync_info;
+        num_round_trips += 1;
+    }
+    assert!(num_round_trips < num_applied_chunks.load(Ordering::SeqCst));
+    drop(state_sync_info);
+
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+}
+
+#[test]
+fn test_apply_corrupted_multi_chunk() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let pending_chunk_ids = state_sync_info.pending_global_chunk_ids();
+    let multi_chunk = source_db
+        .fetch_multi_chunk(
+            &pending_chunk_ids,
+            2048,
+            None,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to fetch multi chunk");
+
+    // The state sync is handed back untouched
+    let truncated = multi_chunk[..multi_chunk.len() - 1].to_vec();
+    let error = target_db
+        .apply_multi_chunk(
+            state_sync_info,
+            truncated,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect_err("expected a truncated multi chunk to be rejected");
+    assert!(matches!(
+        &error.error,
+        ReplicationError::DecodeFailure { global_chunk_id, .. } if global_chunk_id.is_empty()
+    ));
+    assert!(error.error.is_recoverable());
+    let state_sync_info = error.state_sync_info;
+    assert_eq!(
+        state_sync_info.pending_global_chunk_ids(),
+        pending_chunk_ids
+    );
+
+    let (next_chunk_ids, _) = target_db
+        .apply_multi_chunk(
+            state_sync_info,
+            multi_chunk,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to apply multi chunk");
+    assert!(!next_chunk_ids.is_empty());
+}
+
+#[test]
+fn test_replication_with_negotiated_version_1() {
+    let grove_version = GroveVersion::latest();
+    assert_eq!(negotiate_state_sync_version(&[1, 2]), Some(2));
+    assert_eq!(negotiate_state_sync_version(&[1, 2, 3, 4]), Some(3));
+    assert_eq!(negotiate_state_sync_version(&[1]), Some(1));
+    assert_eq!(negotiate_state_sync_version(&[3]), None);
+
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+    let version = negotiate_state_sync_version(&[1]).unwrap();
+
+    let tx = target_db.start_transaction();
+    let mut state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default().with_version(version),
+            app_hash,
+            &tx,
+            version,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+
+    // Multi-chunks are not part of version 1
+    assert!(source_db
+        .fetch_multi_chunk(
+            &Vec::from(chunk_queue.clone()),
+            2048,
+            None,
+            version,
+            grove_version
+        )
+        .is_err());
+
+    while let Some(chunk_id) = chunk_queue.pop_front() {
+        let chunk = source_db
+            .fetch_chunk(&chunk_id, None, version, grove_version)
+            .expect("expected to fetch chunk");
+        let (next_chunk_ids, new_state_sync_info) = target_db
+            .apply_chunk(
+                state_sync_info,
+                &chunk_id,
+                chunk,
+                &tx,
+                version,
+                grove_version,
+            )
+            .expect("expected to apply chunk");
+        state_sync_info = new_state_sync_info;
+        chunk_queue.extend(next_chunk_ids);
+    }
+    assert!(state_sync_info.is_sync_completed());
+    drop(state_sync_info);
+
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+}
+
+/// Replicates `source_db` into a new database using the given state sync
+/// protocol version, returning the total size of the requested global chunk
+/// ids
+fn sync_with_version(source_db: &GroveDb, version: u16, grove_version: &GroveVersion) -> usize {
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let tx = target_db.start_transaction();
+    let mut state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default().with_version(version),
+            app_hash,
+            &tx,
+            version,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+    let mut chunk_ids_size = 0;
+    while let Some(chunk_id) = chunk_queue.pop_front() {
+        chunk_ids_size += chunk_id.len();
+        let chunk = source_db
+            .fetch_chunk(&chunk_id, None, version, grove_version)
+            .expect("expected to fetch chunk");
+        let (next_chunk_ids, new_state_sync_info) = target_db
+            .apply_chunk(
+                state_sync_info,
+                &chunk_id,
+                chunk,
+                &tx,
+                version,
+                grove_version,
+            )
+            .expect("expected to apply chunk");
+        state_sync_info = new_state_sync_info;
+        chunk_queue.extend(next_chunk_ids);
+    }
+    assert!(state_sync_info.is_sync_completed());
+    drop(state_sync_info);
+
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+    chunk_ids_size
+}
+
+#[test]
+fn test_replication_with_compact_chunk_ids() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_test_grovedb(grove_version);
+    // A subtree big enough to be split into several layers of chunks
+    for i in 0u32..1000 {
+        source_db
+            .insert(
+                [TEST_LEAF].as_ref(),
+                &i.to_be_bytes(),
+                Element::new_item(i.to_be_bytes().to_vec()),
+                None,
+                None,
+                grove_version,
+            )
+            .unwrap()
+            .expect("successful insert");
+    }
+
+    let chunk_ids_size_v2 = sync_with_version(&source_db, 2, grove_version);
+    let chunk_ids_size_v3 = sync_with_version(&source_db, 3, grove_version);
+    assert!(chunk_ids_size_v3 < chunk_ids_size_v2);
+}
+
+/// Replicates the subtree at `subtree_path` of `source_db` (and its
+/// descendants) into `target_db`
+fn sync_subtree(
+    source_db: &GroveDb,
+    target_db: &GroveDb,
+    subtree_path: &[&[u8]],
+    grove_version: &GroveVersion,
+) {
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+    let subtree_proof = source_db
+        .prove_subtree_element(subtree_path.into(), grove_version)
+        .expect("expected to prove subtree element");
+
+    let tx = target_db.start_transaction();
+    let mut state_sync_info = target_db
+        .start_subtree_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            subtree_path.into(),
+            &subtree_proof,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+    while let Some(chunk_id) = chunk_queue.pop_front() {
+        let chunk = source_db
+            .fetch_chunk_in_subtree(
+                subtree_path.into(),
+                &chunk_id,
+                None,
+                CURRENT_STATE_SYNC_VERSION,
+                grove_version,
+            )
+            .expect("expected to fetch chunk");
+        let (next_chunk_ids, new_state_sync_info) = target_db
+            .apply_chunk(
+                state_sync_info,
+                &chunk_id,
+                chunk,
+                &tx,
+                CURRENT_STATE_SYNC_VERSION,
+                grove_version,
+            )
+            .expect("expected to apply chunk");
+        state_sync_info = new_state_sync_info;
+        chunk_queue.extend(next_chunk_ids);
+    }
+    assert!(state_sync_info.is_sync_completed());
+    drop(state_sync_info);
+
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+}
+
+#[test]
+fn test_subtree_scoped_replication() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+
+    for (target_db, subtree_path) in [
+        (make_empty_grovedb(), [DEEP_LEAF].as_ref()),
+        (
+            make_test_grovedb(grove_version),
+            [TEST_LEAF, b"innertree"].as_ref(),
+        ),
+    ] {
+        sync_subtree(&source_db, &target_db, subtree_path, grove_version);
+
+        // The subtree and its descendants are identical to the source,
+        // including the value hash of the subtree's element in its parent
+        let source_metadata = source_db
+            .get_subtrees_metadata_at_path(subtree_path.into(), None, grove_version)
+            .expect("expected to get source subtrees metadata");
+        let target_metadata = target_db
+            .get_subtrees_metadata_at_path(subtree_path.into(), None, grove_version)
+            .expect("expected to get target subtrees metadata");
+        assert_eq!(source_metadata.data, target_metadata.data);
+    }
+}
+
+#[test]
+fn test_subtree_scoped_replication_rejects_invalid_proof() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let subtree_path = [DEEP_LEAF];
+    let subtree_proof = source_db
+        .prove_subtree_element(subtree_path.as_ref().into(), grove_version)
+        .expect("expected to prove subtree element");
+
+    let tx = target_db.start_transaction();
+    assert!(target_db
+        .start_subtree_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            [1; 32],
+            subtree_path.as_ref().into(),
+            &subtree_proof,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .is_err());
+}
+
+#[test]
+fn test_replication_with_manifest() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    // The manifest is transferred as bytes and checked against its hash
+    let manifest = source_db
+        .snapshot_manifest(None, grove_version)
+        .expect("expected to build manifest");
+    let manifest_hash = manifest.hash().expect("expected to hash manifest");
+    let manifest_bytes = manifest.encode().expect("expected to encode manifest");
+    assert!(SnapshotManifest::decode(&manifest_bytes, &[0; 32]).is_err());
+    let manifest = SnapshotManifest::decode(&manifest_bytes, &manifest_hash)
+        .expect("expected to decode manifest");
+    let mut expected_chunk_ids = manifest
+        .global_chunk_ids(CURRENT_STATE_SYNC_VERSION)
+        .expect("expected to list chunk ids");
+
+    let tx = target_db.start_transaction();
+    let mut state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default().with_manifest(manifest.clone()),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let progress = state_sync_info.progress().expect("expected progress");
+    assert_eq!(progress.num_processed_chunks, 0);
+    assert_eq!(progress.num_total_subtrees, manifest.subtrees.len());
+    assert_eq!(progress.num_total_chunks, manifest.total_chunks());
+
+    let mut applied_chunk_ids = vec![];
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+    while let Some(chunk_id) = chunk_queue.pop_front() {
+        let chunk = source_db
+            .fetch_chunk(&chunk_id, None, CURRENT_STATE_SYNC_VERSION, grove_version)
+            .expect("expected to fetch chunk");
+        let (next_chunk_ids, new_state_sync_info) = target_db
+            .apply_chunk(
+                state_sync_info,
+                &chunk_id,
+                chunk,
+                &tx,
+                CURRENT_STATE_SYNC_VERSION,
+                grove_version,
+            )
+            .expect("expected to apply chunk");
+        state_sync_info = new_state_sync_info;
+        chunk_queue.extend(next_chunk_ids);
+        applied_chunk_ids.push(chunk_id);
+    }
+    assert!(state_sync_info.is_sync_completed());
+    let progress = state_sync_info.progress().expect("expected progress");
+    assert_eq!(progress.num_processed_subtrees, progress.num_total_subtrees);
+    assert_eq!(progress.num_processed_chunks, progress.num_total_chunks);
+
+    // The manifest listed exactly the chunks the state sync asked for
+    applied_chunk_ids.sort();
+    expected_chunk_ids.sort();
+    assert_eq!(applied_chunk_ids, expected_chunk_ids);
+}
+
+#[test]
+fn test_manifest_of_empty_grovedb() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_empty_grovedb();
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let manifest = source_db
+        .snapshot_manifest(None, grove_version)
+        .expect("expected to build manifest");
+    assert_eq!(manifest.subtrees.len(), 1);
+    assert_eq!(manifest.total_chunks(), 0);
+    let chunk_ids = manifest
+        .global_chunk_ids(CURRENT_STATE_SYNC_VERSION)
+        .expect("expected to list chunk ids");
+    assert_eq!(chunk_ids, vec![app_hash.to_vec()]);
+
+    // The root chunk is requested by the app hash, as the state sync does
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default().with_manifest(manifest),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    assert_eq!(state_sync_info.pending_global_chunk_ids(), chunk_ids);
+    let chunk = source_db
+        .fetch_chunk(
+            &chunk_ids[0],
+            None,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to fetch chunk");
+    assert!(chunk.is_empty());
+}
+
+#[test]
+fn test_replication_rejects_tampered_manifest() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let mut manifest = source_db
+        .snapshot_manifest(None, grove_version)
+        .expect("expected to build manifest");
+    let entry = manifest
+        .subtrees
+        .iter_mut()
+        .find(|entry| entry.path == vec![DEEP_LEAF.to_vec()])
+        .expect("expected manifest entry of deep leaf");
+    entry.elem_value_hash = [1; 32];
+
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default().with_manifest(manifest),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let chunk = source_db
+        .fetch_chunk(&app_hash, None, CURRENT_STATE_SYNC_VERSION, grove_version)
+        .expect("expected to fetch chunk");
+    assert!(target_db
+        .apply_chunk(
+            state_sync_info,
+            &app_hash,
+            chunk,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .is_err());
+}
+
+#[test]
+fn test_replication_with_concurrently_verified_chunks() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let tx = target_db.start_transaction();
+    let mut state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+
+    // Apply all pending chunks (of many subtrees) in one batch per round trip
+    let mut num_batches = 0;
+    let mut num_applied_chunks = 0;
+    while !state_sync_info.is_sync_completed() {
+        let chunks: Vec<(Vec<u8>, Vec<u8>)> = state_sync_info
+            .pending_global_chunk_ids()
+            .into_iter()
+            .map(|chunk_id| {
+                let chunk = source_db
+                    .fetch_chunk(&chunk_id, None, CURRENT_STATE_SYNC_VERSION, grove_version)
+                    .expect("expected to fetch chunk");
+                (chunk_id, chunk)
+            })
+            .collect();
+        num_batches += 1;
+        num_applied_chunks += chunks.len();
+        state_sync_info = target_db
+            .apply_chunks(
+                state_sync_info,
+                chunks,
+                &tx,
+                CURRENT_STATE_SYNC_VERSION,
+                grove_version,
+            )
+            .expect("expected to apply chunks")
+            .1;
+    }
+    assert!(num_batches < num_applied_chunks);
+    drop(state_sync_info);
+
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+}
+
+#[test]
+fn test_apply_chunks_rejects_unexpected_chunk() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let chunk = source_db
+        .fetch_chunk(&app_hash, None, CURRENT_STATE_SYNC_VERSION, grove_version)
+        .expect("expected to fetch chunk");
+    // The root chunk is pending, but the same chunk can't be applied twice
+    assert!(target_db
+        .apply_chunks(
+            state_sync_info,
+            vec![
+                (app_hash.to_vec(), chunk.clone()),
+                (app_hash.to_vec(), chunk)
+            ],
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .is_err());
+}
+
+#[test]
+fn test_apply_chunk_reports_failing_chunk() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let other_db = make_test_grovedb(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+    let other_app_hash = other_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let tx = target_db.start_transaction();
+    let mut state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let chunk = source_db
+        .fetch_chunk(&app_hash, None, CURRENT_STATE_SYNC_VERSION, grove_version)
+        .expect("expected to fetch chunk");
+    let other_chunk = other_db
+        .fetch_chunk(
+            &other_app_hash,
+            None,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to fetch chunk");
+
+    let failures = vec![
+        (
+            app_hash.to_vec(),
+            other_chunk,
+            CURRENT_STATE_SYNC_VERSION,
+            ReplicationError::HashMismatch {
+                global_chunk_id: app_hash.to_vec(),
+            },
+        ),
+        // An empty chunk only stands for an empty subtree
+        (
+            app_hash.to_vec(),
+            vec![],
+            CURRENT_STATE_SYNC_VERSION,
+            ReplicationError::HashMismatch {
+                global_chunk_id: app_hash.to_vec(),
+            },
+        ),
+        (
+            app_hash.to_vec(),
+            chunk.clone(),
+            1,
+            ReplicationError::WrongVersion {
+                global_chunk_id: app_hash.to_vec(),
+                version: 1,
+            },
+        ),
+        (
+            vec![1; 32],
+            chunk.clone(),
+            CURRENT_STATE_SYNC_VERSION,
+            ReplicationError::UnexpectedChunk {
+                global_chunk_id: vec![1; 32],
+            },
+        ),
+    ];
+    for (global_chunk_id, chunk, version, expected_error) in failures {
+        let error = target_db
+            .apply_chunk(
+                state_sync_info,
+                &global_chunk_id,
+                chunk,
+                &tx,
+                version,
+                grove_version,
+            )
+            .expect_err("expected chunk to fail");
+        assert_eq!(error.error, expected_error);
+        assert!(error.error.is_recoverable());
+        state_sync_info = error.state_sync_info;
+        // The state sync is left as it was before the failing chunk
+        assert_eq!(
+            state_sync_info.pending_global_chunk_ids(),
+            vec![app_hash.to_vec()]
+        );
+    }
+
+    let error = target_db
+        .apply_chunk(
+            state_sync_info,
+            &app_hash,
+            vec![0xff; 8],
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect_err("expected chunk to fail");
+    assert!(matches!(
+        error.error,
+        ReplicationError::DecodeFailure { .. }
+    ));
+    assert_eq!(error.error.global_chunk_id(), app_hash.as_slice());
+    state_sync_info = error.state_sync_info;
+
+    // The chunk can be requested again, e.g. from another source
+    let (next_chunk_ids, state_sync_info) = target_db
+        .apply_chunk(
+            state_sync_info,
+            &app_hash,
+            chunk,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to apply chunk");
+    assert!(!next_chunk_ids.is_empty());
+    assert_eq!(state_sync_info.pending_global_chunk_ids(), next_chunk_ids);
+}
+
+#[test]
+fn test_apply_chunks_hands_back_state_sync_on_failure() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let other_db = make_test_grovedb(grove_version);
+    let target_db = make_empty_grovedb();
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+    let other_app_hash = other_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let chunk = source_db
+        .fetch_chunk(&app_hash, None, CURRENT_STATE_SYNC_VERSION, grove_version)
+        .expect("expected to fetch chunk");
+    let (_, state_sync_info) = target_db
+        .apply_chunk(
+            state_sync_info,
+            &app_hash,
+            chunk,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to apply chunk");
+    let pending_chunk_ids = state_sync_info.pending_global_chunk_ids();
+    assert!(pending_chunk_ids.len() > 1);
+
+    // One of the chunks is replaced by a chunk of another GroveDB
+    let mut chunks: Vec<(Vec<u8>, Vec<u8>)> = pending_chunk_ids
+        .iter()
+        .map(|chunk_id| {
+            let chunk = source_db
+                .fetch_chunk(chunk_id, None, CURRENT_STATE_SYNC_VERSION, grove_version)
+                .expect("expected to fetch chunk");
+            (chunk_id.clone(), chunk)
+        })
+        .collect();
+    chunks[1].1 = other_db
+        .fetch_chunk(
+            &other_app_hash,
+            None,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to fetch chunk");
+    let error = target_db
+        .apply_chunks(
+            state_sync_info,
+            chunks,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect_err("expected chunks to fail");
+    assert_eq!(
+        error.error.global_chunk_id(),
+        pending_chunk_ids[1].as_slice()
+    );
+    assert!(error.error.is_recoverable());
+    assert_eq!(
+        error.state_sync_info.pending_global_chunk_ids(),
+        pending_chunk_ids
+    );
+}
+
+/// Restores the snapshot served by `source` into an empty GroveDB and checks
+/// its root hash
+fn sync_from_source<S: ChunkSource + ?Sized>(source: &S, grove_version: &GroveVersion) {
+    let app_hash = source
+        .app_hash(grove_version)
+        .expect("expected to get app hash");
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .sync_from_source(source, MultiStateSyncInfo::default(), &tx, grove_version)
+        .expect("expected to sync from source");
+    assert!(state_sync_info.is_sync_completed());
+    drop(state_sync_info);
+
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+}
+
+#[test]
+fn test_sync_from_in_process_source() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    sync_from_source(&*source_db, grove_version);
+
+    let checkpoint_dir = TempDir::new().unwrap();
+    let session = source_db
+        .start_snapshot_session(checkpoint_dir.path().join("checkpoint"), grove_version)
+        .expect("expected to start snapshot session");
+    sync_from_source(&session, grove_version);
+}
+
+#[test]
+fn test_sync_from_directory_source() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let chunk_dir = TempDir::new().unwrap();
+    write_chunk_directory(
+        &source_db,
+        chunk_dir.path(),
+        CURRENT_STATE_SYNC_VERSION,
+        grove_version,
+    )
+    .expect("expected to write chunk directory");
+
+    let source = DirectoryChunkSource::open(chunk_dir.path()).expect("expected to open directory");
+    assert_eq!(source.version(), CURRENT_STATE_SYNC_VERSION);
+    sync_from_source(&source, grove_version);
+}
+
+#[test]
+fn test_sync_from_tcp_source() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let listener = TcpListener::bind("127.0.0.1:0").expect("expected to bind listener");
+    let addr = listener.local_addr().unwrap();
+
+    thread::scope(|scope| {
+        let server = scope.spawn(|| {
+            let (stream, _) = listener.accept().expect("expected to accept connection");
+            serve_chunks_connection(&*source_db, stream, grove_version)
+        });
+
+        let source = TcpChunkSource::connect(addr).expect("expected to connect");
+        sync_from_source(&source, grove_version);
+        // Closing the connection ends the server
+        drop(source);
+        server
+            .join()
+            .unwrap()
+            .expect("expected server to serve chunks");
+    });
+}
+
+/// Chunk source serving the app hash and manifest of `source`, but the same
+/// (wrong) chunk for every chunk id
+struct TamperingChunkSource<'a> {
+    source: &'a GroveDb,
+    chunk: Vec<u8>,
+}
+
+impl ChunkSource for TamperingChunkSource<'_> {
+    fn app_hash(&self, grove_version: &GroveVersion) -> Result<CryptoHash, Error> {
+        self.source.app_hash(grove_version)
+    }
+
+    fn manifest(&self, grove_version: &GroveVersion) -> Result<SnapshotManifest, Error> {
+        self.source.manifest(grove_version)
+    }
+
+    fn fetch_chunk(
+        &self,
+        _global_chunk_id: &[u8],
+        _version: u16,
+        _grove_version: &GroveVersion,
+    ) -> Result<Vec<u8>, Error> {
+        Ok(self.chunk.clone())
+    }
+}
+
+#[test]
+fn test_sync_from_sources_skips_faulty_sources() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let other_db = make_test_grovedb(grove_version);
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+    let other_app_hash = other_db.root_hash(None, grove_version).unwrap().unwrap();
+    let tampering_source = TamperingChunkSource {
+        source: &source_db,
+        chunk: other_db
+            .fetch_chunk(
+                &other_app_hash,
+                None,
+                CURRENT_STATE_SYNC_VERSION,
+                grove_version,
+            )
+            .expect("expected to fetch chunk"),
+    };
+
+    // A source of another snapshot, a source serving wrong chunks and a good
+    // source
+    let sources: Vec<&(dyn ChunkSource + Sync)> = vec![&*other_db, &tampering_source, &*source_db];
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    let (state_sync_info, faulty_sources) = target_db
+        .sync_from_sources(
+            &sources,
+            app_hash,
+            MultiStateSyncInfo::default(),
+            &tx,
+            grove_version,
+        )
+        .expect("expected to sync from sources");
+    assert!(state_sync_info.is_sync_completed());
+    assert_eq!(faulty_sources, vec![0, 1]);
+    drop(state_sync_info);
+
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+
+    // Without a good source the state sync fails
+    let sources: Vec<&(dyn ChunkSource + Sync)> = vec![&*other_db, &tampering_source];
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    assert!(target_db
+        .sync_from_sources(
+            &sources,
+            app_hash,
+            MultiStateSyncInfo::default(),
+            &tx,
+            grove_version,
+        )
+        .is_err());
+}
+
+#[test]
+fn test_sync_from_sources_skips_sources_serving_empty_chunks() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+    let empty_chunk_source = TamperingChunkSource {
+        source: &source_db,
+        chunk: vec![],
+    };
+
+    // The source serving empty chunks gets the root chunk first
+    let sources: Vec<&(dyn ChunkSource + Sync)> = vec![&empty_chunk_source, &*source_db];
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    let (state_sync_info, faulty_sources) = target_db
+        .sync_from_sources(
+            &sources,
+            app_hash,
+            MultiStateSyncInfo::default(),
+            &tx,
+            grove_version,
+        )
+        .expect("expected to sync from sources");
+    assert!(state_sync_info.is_sync_completed());
+    assert_eq!(faulty_sources, vec![0]);
+    drop(state_sync_info);
+
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+}
+
+#[test]
+fn test_sync_from_sources_skips_stalled_tcp_sources() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    // The connection is established by the OS, but nothing is ever served
+    let listener = TcpListener::bind("127.0.0.1:0").expect("expected to bind listener");
+    let stalled_source = TcpChunkSource::connect_with_timeout(
+        listener.local_addr().unwrap(),
+        Duration::from_millis(100),
+    )
+    .expect("expected to connect");
+
+    let sources: Vec<&(dyn ChunkSource + Sync)> = vec![&stalled_source, &*source_db];
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    let (state_sync_info, faulty_sources) = target_db
+        .sync_from_sources(
+            &sources,
+            app_hash,
+            MultiStateSyncInfo::default(),
+            &tx,
+            grove_version,
+        )
+        .expect("expected to sync from sources");
+    assert!(state_sync_info.is_sync_completed());
+    assert_eq!(faulty_sources, vec![0]);
+}
+
+#[test]
+fn test_snapshot_export_and_import() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let archive_dir = TempDir::new().unwrap();
+    let archive_path = archive_dir.path().join("snapshot");
+    let app_hash = source_db
+        .export_snapshot(&archive_path, grove_version)
+        .expect("expected to export snapshot");
+    assert_eq!(
+        app_hash,
+        source_db.root_hash(None, grove_version).unwrap().unwrap()
+    );
+
+    let target_db = make_empty_grovedb();
+    target_db
+        .import_snapshot(&archive_path, app_hash, grove_version)
+        .expect("expected to import snapshot");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+
+    // A corrupted archive is rejected without altering the GroveDB
+    let mut archive = std::fs::read(&archive_path).unwrap();
+    let last = archive.len() - 1;
+    archive[last] ^= 0xff;
+    let corrupted_archive_path = archive_dir.path().join("corrupted_snapshot");
+    std::fs::write(&corrupted_archive_path, archive).unwrap();
+    let target_db = make_empty_grovedb();
+    let empty_root_hash = target_db.root_hash(None, grove_version).unwrap().unwrap();
+    assert!(target_db
+        .import_snapshot(&corrupted_archive_path, app_hash, grove_version)
+        .is_err());
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        empty_root_hash
+    );
+
+    // So is a consistent archive of another snapshot
+    let other_archive_path = archive_dir.path().join("other_snapshot");
+    make_test_grovedb(grove_version)
+        .export_snapshot(&other_archive_path, grove_version)
+        .expect("expected to export snapshot");
+    assert!(matches!(
+        target_db.import_snapshot(&other_archive_path, app_hash, grove_version),
+        Err(Error::CorruptedData(_))
+    ));
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        empty_root_hash
+    );
+}
+
+/// Syncs `target_db` from `source_db` with a diff sync, checks its root hash
+/// and returns the number of applied chunks
+fn diff_sync(source_db: &GroveDb, target_db: &GroveDb, grove_version: &GroveVersion) -> usize {
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+    let num_applied_chunks = Arc::new(AtomicUsize::new(0));
+    let observed_applied_chunks = num_applied_chunks.clone();
+    let observer = move |event: StateSyncEvent| {
+        if let StateSyncEvent::ChunkApplied { .. } = event {
+            observed_applied_chunks.fetch_add(1, Ordering::SeqCst);
+        }
+    };
+
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .sync_from_source(
+            source_db,
+            MultiStateSyncInfo::default()
+                .with_observer(Arc::new(observer))
+                .with_diff_sync(),
+            &tx,
+            grove_version,
+        )
+        .expect("expected to diff sync");
+    assert!(state_sync_info.is_sync_completed());
+    drop(state_sync_info);
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+    assert_eq!(
+        target_db.root_hash(None, grove_version).unwrap().unwrap(),
+        app_hash
+    );
+    num_applied_chunks.load(Ordering::SeqCst)
+}
+
+#[test]
+fn test_diff_sync_only_transfers_differing_subtrees() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let replica_db = make_deep_tree(grove_version);
+
+    // The source has a new item in a deep subtree, the replica has a subtree
+    // the source doesn't have
+    source_db
+        .insert(
+            [DEEP_LEAF, b"deep_node_1".as_slice(), b"deeper_1"].as_ref(),
+            b"k4",
+            Element::new_item(b"v4".to_vec()),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful insert");
+    replica_db
+        .insert(
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"stale_tree",
+            Element::empty_tree(),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful insert");
+    replica_db
+        .insert(
+            [ANOTHER_TEST_LEAF, b"stale_tree".as_slice()].as_ref(),
+            b"stale_key",
+            Element::new_item(b"stale_value".to_vec()),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful insert");
+
+    let num_total_chunks = source_db
+        .snapshot_manifest(None, grove_version)
+        .expect("expected to build manifest")
+        .total_chunks();
+    let num_applied_chunks = diff_sync(&source_db, &replica_db, grove_version);
+    assert!(num_applied_chunks > 0);
+    assert!(num_applied_chunks < num_total_chunks as usize);
+
+    assert_eq!(
+        replica_db
+            .get(
+                [DEEP_LEAF, b"deep_node_1".as_slice(), b"deeper_1"].as_ref(),
+                b"k4",
+                None,
+                grove_version
+            )
+            .unwrap()
+            .expect("expected to get synced item"),
+        Element::new_item(b"v4".to_vec())
+    );
+    assert!(replica_db
+        .get(
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"stale_tree",
+            None,
+            grove_version
+        )
+        .unwrap()
+        .is_err());
+    assert_eq!(
+        replica_db
+            .get_subtrees_metadata(None, grove_version)
+            .expect("expected to get subtrees metadata")
+            .data
+            .keys()
+            .collect::<Vec<_>>(),
+        source_db
+            .get_subtrees_metadata(None, grove_version)
+            .expect("expected to get subtrees metadata")
+            .data
+            .keys()
+            .collect::<Vec<_>>()
+    );
+
+    // An identical replica doesn't need any chunk
+    assert_eq!(diff_sync(&source_db, &replica_db, grove_version), 0);
+}
+
+#[test]
+fn test_catch_up_from_op_log() {
+    let grove_version = GroveVersion::latest();
+    let primary_db = make_deep_tree(grove_version);
+    let replica_db = make_empty_grovedb();
+    let checkpoint_dir = TempDir::new().unwrap();
+    let checkpoint_path = checkpoint_dir.path().join("checkpoint");
+    let primary = LocalOpLogPrimary::new(&primary_db, &checkpoint_path);
+
+    let log_item = |key: &[u8], value: &[u8]| {
+        primary_db
+            .apply_logged_batch(
+                vec![QualifiedGroveDbOp::insert_or_replace_op(
+                    vec![TEST_LEAF.to_vec(), b"innertree".to_vec()],
+                    key.to_vec(),
+                    Element::new_item(value.to_vec()),
+                )],
+                None,
+                None,
+                grove_version,
+            )
+            .expect("expected to apply logged batch")
+    };
+    let assert_in_sync = || {
+        assert_eq!(
+            replica_db.root_hash(None, grove_version).unwrap().unwrap(),
+            primary_db.root_hash(None, grove_version).unwrap().unwrap()
+        );
+    };
+
+    // A replica that was never synced starts from a snapshot
+    assert_eq!(log_item(b"key4", b"value4"), 0);
+    let catch_up = replica_db
+        .catch_up_from_op_log(&primary, 10, grove_version)
+        .expect("expected to catch up");
+    assert!(catch_up.snapshot_synced);
+    assert_eq!(catch_up.position, 1);
+    assert_in_sync();
+    assert!(!checkpoint_path.exists());
+
+    // Then tails the log
+    assert_eq!(log_item(b"key5", b"value5"), 1);
+    assert_eq!(log_item(b"key1", b"changed"), 2);
+    let catch_up = replica_db
+        .catch_up_from_op_log(&primary, 10, grove_version)
+        .expect("expected to catch up");
+    assert_eq!(catch_up.applied_entries, 2);
+    assert!(!catch_up.snapshot_synced);
+    assert_eq!(catch_up.position, 3);
+    assert_eq!(replica_db.op_log_position().unwrap(), Some(3));
+    assert_in_sync();
+
+    // Falls back to a snapshot once the entries it needs were pruned
+    for i in 0..5u8 {
+        log_item(&[b'p', i], b"pruned");
+    }
+    primary_db
+        .prune_op_log(2, None)
+        .expect("expected to prune op log");
+    assert_eq!(primary_db.op_log_head(grove_version).unwrap().first, 6);
+    assert_eq!(
+        primary_db
+            .op_log_entry(5, grove_version)
+            .unwrap()
+            .map(|e| e.sequence),
+        None
+    );
+    let catch_up = replica_db
+        .catch_up_from_op_log(&primary, 10, grove_version)
+        .expect("expected to catch up");
+    assert!(catch_up.snapshot_synced);
+    assert_eq!(catch_up.position, 8);
+    assert_in_sync();
+
+    // A diverged replica refuses the entry
+    replica_db
+        .insert(
+            [TEST_LEAF, b"innertree"].as_ref(),
+            b"local_key",
+            Element::new_item(b"local_value".to_vec()),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful insert");
+    log_item(b"key6", b"value6");
+    assert!(replica_db
+        .catch_up_from_op_log(&primary, 10, grove_version)
+        .is_err());
+    assert_eq!(replica_db.op_log_position().unwrap(), Some(8));
+}
+
+#[test]
+fn test_sync_with_validation() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree_with_sum_trees(grove_version);
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .sync_from_source(
+            &*source_db,
+            MultiStateSyncInfo::default().with_validation(),
+            &tx,
+            grove_version,
+        )
+        .expect("expected to sync from source");
+    let report = target_db
+        .validate_state_sync(&state_sync_info, &tx, grove_version)
+        .expect("expected synced state to be valid")
+        .expect("expected a validation report");
+    assert!(report.is_valid());
+    drop(state_sync_info);
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+
+    // A reference whose target was deleted can't be resolved
+    let source_db = make_test_grovedb(grove_version);
+    source_db
+        .insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    source_db
+        .insert(
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"reference",
+            Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+                TEST_LEAF.to_vec(),
+                b"key".to_vec(),
+            ])),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful reference insert");
+    source_db
+        .delete([TEST_LEAF].as_ref(), b"key", None, None, grove_version)
+        .unwrap()
+        .expect("successful delete");
+
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    match target_db.sync_from_source(
+        &*source_db,
+        MultiStateSyncInfo::default().with_validation(),
+        &tx,
+        grove_version,
+    ) {
+        Err(Error::ReplicationError(ReplicationError::ValidationFailed { report, .. })) => {
+            assert!(report.reference_error.is_some());
+            assert!(report.sum_tree_issues.is_empty());
+        }
+        _ => panic!("expected the validation to fail"),
+    }
+
+    drop(tx);
+
+    // Without validation the same snapshot is restored
+    let tx = target_db.start_transaction();
+    target_db
+        .sync_from_source(
+            &*source_db,
+            MultiStateSyncInfo::default(),
+            &tx,
+            grove_version,
+        )
+        .expect("expected to sync from source");
+}
+
+#[test]
+fn test_resumed_sync_is_validated_on_last_chunk() {
+    let grove_version = GroveVersion::latest();
+    // A reference whose target was deleted can't be resolved
+    let source_db = make_test_grovedb(grove_version);
+    source_db
+        .insert(
+            [TEST_LEAF].as_ref(),
+            b"key",
+            Element::new_item(b"value".to_vec()),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful item insert");
+    source_db
+        .insert(
+            [ANOTHER_TEST_LEAF].as_ref(),
+            b"reference",
+            Element::new_reference(ReferencePathType::AbsolutePathReference(vec![
+                TEST_LEAF.to_vec(),
+                b"key".to_vec(),
+            ])),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful reference insert");
+    source_db
+        .delete([TEST_LEAF].as_ref(), b"key", None, None, grove_version)
+        .unwrap()
+        .expect("successful delete");
+    let app_hash = source_db.root_hash(None, grove_version).unwrap().unwrap();
+
+    // Start with validation, apply the root chunk only and commit
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default().with_validation(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to start syncing");
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+    let state_sync_info = sync_chunks(
+        &source_db,
+        &target_db,
+        state_sync_info,
+        &mut chunk_queue,
+        &tx,
+        Some(1),
+        grove_version,
+    );
+    assert!(!state_sync_info.is_sync_completed());
+    drop(state_sync_info);
+    target_db
+        .commit_transaction(tx)
+        .unwrap()
+        .expect("expected to commit transaction");
+
+    // The resumed state sync is still validated, applying its last chunk fails
+    let tx = target_db.start_transaction();
+    let mut state_sync_info = target_db
+        .start_snapshot_syncing(
+            MultiStateSyncInfo::default(),
+            app_hash,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        )
+        .expect("expected to resume syncing");
+    let mut chunk_queue: VecDeque<Vec<u8>> = state_sync_info.pending_global_chunk_ids().into();
+    loop {
+        let chunk_id = chunk_queue.pop_front().expect("expected a pending chunk");
+        let chunk = source_db
+            .fetch_chunk(&chunk_id, None, CURRENT_STATE_SYNC_VERSION, grove_version)
+            .expect("expected to fetch chunk");
+        match target_db.apply_chunk(
+            state_sync_info,
+            &chunk_id,
+            chunk,
+            &tx,
+            CURRENT_STATE_SYNC_VERSION,
+            grove_version,
+        ) {
+            Ok((next_chunk_ids, new_state_sync_info)) => {
+                assert!(!new_state_sync_info.is_sync_completed());
+                state_sync_info = new_state_sync_info;
+                chunk_queue.extend(next_chunk_ids);
+            }
+            Err(error) => {
+                assert!(!error.error.is_recoverable());
+                match error.error {
+                    ReplicationError::ValidationFailed {
+                        global_chunk_id,
+                        report,
+                    } => {
+                        assert_eq!(global_chunk_id, chunk_id);
+                        assert!(report.reference_error.is_some());
+                    }
+                    error => panic!("expected the validation to fail, got {}", error),
+                }
+                assert!(chunk_queue.is_empty());
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_validation_reports_mismatched_sum_trees() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree_with_sum_trees(grove_version);
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .sync_from_source(
+            &*source_db,
+            MultiStateSyncInfo::default(),
+            &tx,
+            grove_version,
+        )
+        .expect("expected to sync from source");
+    drop(state_sync_info);
+
+    // Overwrite the sum stored in a sum tree element within the transaction,
+    // keeping its root key
+    let parent_path: &[&[u8]] = &[DEEP_LEAF, b"deep_node_1", b"c"];
+    let sum_tree_path: &[&[u8]] = &[DEEP_LEAF, b"deep_node_1", b"c", b"1"];
+    let sum_tree_root_hash = target_db
+        .open_transactional_merk_at_path(sum_tree_path.into(), &tx, None, grove_version)
+        .unwrap()
+        .expect("expected to open sum tree")
+        .root_hash()
+        .unwrap();
+    let batch = StorageBatch::new();
+    let mut parent_merk = target_db
+        .open_transactional_merk_at_path(parent_path.into(), &tx, Some(&batch), grove_version)
+        .unwrap()
+        .expect("expected to open parent tree");
+    let Element::SumTree(root_key, 2, flags) =
+        Element::get(&parent_merk, b"1", true, grove_version)
+            .unwrap()
+            .expect("expected sum tree")
+    else {
+        panic!("expected a sum tree of sum 2");
+    };
+    Element::SumTree(root_key, 7, flags)
+        .insert_subtree(
+            &mut parent_merk,
+            b"1",
+            sum_tree_root_hash,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("expected to overwrite sum tree");
+    drop(parent_merk);
+    target_db
+        .db
+        .commit_multi_context_batch(batch, Some(&tx))
+        .unwrap()
+        .expect("expected to commit batch");
+
+    let report = target_db
+        .validate_synced_state(&tx, grove_version)
+        .expect("expected to validate synced state");
+    assert!(!report.is_valid());
+    assert_eq!(report.reference_error, None);
+    assert_eq!(
+        report.sum_tree_issues,
+        BTreeMap::from([(
+            sum_tree_path
+                .iter()
+                .map(|segment| segment.to_vec())
+                .collect(),
+            SumTreeIssue {
+                stored_sum: 7,
+                computed_sum: Some(2),
+            },
+        )])
+    );
+}
+
+#[test]
+fn test_sync_stats() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_deep_tree(grove_version);
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .sync_from_source(
+            &*source_db,
+            MultiStateSyncInfo::default(),
+            &tx,
+            grove_version,
+        )
+        .expect("expected to sync from source");
+
+    let stats = state_sync_info.stats();
+    assert!(stats.duration.is_some());
+    assert!(stats.chunks_applied > 0);
+    assert_eq!(stats.subtrees_discovered, stats.subtrees.len() as u64);
+    assert_eq!(stats.subtrees_finished, stats.subtrees_discovered);
+    assert_eq!(stats.subtrees_skipped, 0);
+    assert!(stats.subtrees.contains_key(&Vec::<Vec<u8>>::new()));
+    assert!(stats
+        .subtrees
+        .contains_key(&vec![TEST_LEAF.to_vec(), b"innertree".to_vec()]));
+    assert!(stats
+        .subtrees
+        .values()
+        .all(|subtree_stats| subtree_stats.duration.is_some()));
+    assert_eq!(
+        stats.bytes_received,
+        stats
+            .subtrees
+            .values()
+            .map(|subtree_stats| subtree_stats.bytes)
+            .sum::<u64>()
+    );
+    assert_eq!(
+        stats.chunks_applied,
+        stats
+            .subtrees
+            .values()
+            .map(|subtree_stats| subtree_stats.chunks)
+            .sum::<u64>()
+    );
+}
+
+#[test]
+fn test_sync_stats_display_binary_keys() {
+    let grove_version = GroveVersion::latest();
+    let source_db = make_test_grovedb(grove_version);
+    source_db
+        .insert(
+            [TEST_LEAF].as_ref(),
+            &[0xff, 0x00],
+            Element::empty_tree(),
+            None,
+            None,
+            grove_version,
+        )
+        .unwrap()
+        .expect("successful subtree insert");
+    let target_db = make_empty_grovedb();
+    let tx = target_db.start_transaction();
+    let state_sync_info = target_db
+        .sync_from_source(
+            &*source_db,
+            MultiStateSyncInfo::default(),
+            &tx,
+            grove_version,
+        )
+        .expect("expected to sync from source");
+
+    let stats = state_sync_info.stats().to_string();
+    assert!(stats.contains("\n  /: "), "{}", stats);
+    assert!(stats.contains("\n  /test_leaf/0xff00: "), "{}", stats);
+}
@@ -47,7 +47,7 @@ use crate::{
         tree::{execute, Child, Tree as ProofTree},
         Node, Op,
     },
-    tree::{combine_hash, kv::ValueDefinedCostType, RefWalker, TreeNode},
+    tree::{combine_hash, kv::ValueDefinedCostType, RefWalker, TreeNode, NULL_HASH},
     CryptoHash, Error,
     Error::{CostsError, StorageError},
     Link, Merk,
@@ -82,6 +82,31 @@ impl<'db, S: StorageContext<'db>> Restorer<S> {
         }
     }
 
+    /// Initializes a chunk restorer from previously persisted restoration
+    /// state (see [`Restorer::restoration_state`]), allowing an interrupted
+    /// restoration to continue where it stopped
+    pub fn new_with_state(
+        merk: Merk<S>,
+        chunk_id_to_root_hash: BTreeMap<Vec<u8>, CryptoHash>,
+        parent_key_value_hash: Option<CryptoHash>,
+        parent_keys: BTreeMap<Vec<u8>, Vec<u8>>,
+    ) -> Self {
+        Self {
+            merk,
+            chunk_id_to_root_hash,
+            parent_key_value_hash,
+            parent_keys,
+        }
+    }
+
+    /// Returns the current restoration state: the expected root hashes of
+    /// chunks that still need to be processed and the keys of their parents
+    pub fn restoration_state(
+        &self,
+    ) -> (&BTreeMap<Vec<u8>, CryptoHash>, &BTreeMap<Vec<u8>, Vec<u8>>) {
+        (&self.chunk_id_to_root_hash, &self.parent_keys)
+    }
+
     /// Processes a chunk at some chunk id, returns the chunks id's of chunks
     /// that can be requested
     pub fn process_chunk(
@@ -90,6 +115,15 @@ impl<'db, S: StorageContext<'db>> Restorer<S> {
         chunk: Vec<Op>,
         grove_version: &GroveVersion,
     ) -> Result<Vec<Vec<u8>>, Error> {
+        let verified_chunk = self.chunk_verifier(chunk_id)?.verify(chunk)?;
+        self.process_verified_chunk(verified_chunk, grove_version)
+    }
+
+    /// Returns a verifier for the chunk at some chunk id. Verification doesn't
+    /// need access to the storage, so it can run concurrently on other
+    /// threads; the resulting [`VerifiedChunk`] is then written with
+    /// [`Restorer::process_verified_chunk`].
+    pub fn chunk_verifier(&self, chunk_id: &[u8]) -> Result<ChunkVerifier, Error> {
         let expected_root_hash = self
             .chunk_id_to_root_hash
             .get(chunk_id)
@@ -99,7 +133,31 @@ impl<'db, S: StorageContext<'db>> Restorer<S> {
         if chunk_id.is_empty() {
             parent_key_value_hash = self.parent_key_value_hash;
         }
-        let chunk_tree = Self::verify_chunk(chunk, expected_root_hash, &parent_key_value_hash)?;
+        Ok(ChunkVerifier {
+            chunk_id: chunk_id.to_vec(),
+            expected_root_hash: *expected_root_hash,
+            parent_key_value_hash,
+        })
+    }
+
+    /// Writes a chunk verified by a verifier of this restorer, returns the
+    /// chunks id's of chunks that can be requested
+    pub fn process_verified_chunk(
+        &mut self,
+        verified_chunk: VerifiedChunk,
+        grove_version: &GroveVersion,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let VerifiedChunk {
+            verifier,
+            tree: chunk_tree,
+        } = verified_chunk;
+        let chunk_id = verifier.chunk_id.as_slice();
+
+        // the chunk must have been verified against the hashes this restorer
+        // currently expects
+        if verifier != self.chunk_verifier(chunk_id)? {
+            return Err(Error::ChunkRestoringError(ChunkError::UnexpectedChunk));
+        }
 
         let mut root_traversal_instruction = vec_bytes_as_traversal_instruction(chunk_id)?;
 
@@ -169,51 +227,7 @@ impl<'db, S: StorageContext<'db>> Restorer<S> {
         expected_root_hash: &CryptoHash,
         parent_key_value_hash_opt: &Option<CryptoHash>,
     ) -> Result<ProofTree, Error> {
-        let chunk_len = chunk.len();
-        let mut kv_count = 0;
-        let mut hash_count = 0;
-
-        // build tree from ops
-        // ensure only made of KvValueFeatureType and Hash nodes and count them
-        let tree = execute(chunk.clone().into_iter().map(Ok), false, |node| {
-            if matches!(node, Node::KVValueHashFeatureType(..)) {
-                kv_count += 1;
-                Ok(())
-            } else if matches!(node, Node::Hash(..)) {
-                hash_count += 1;
-                Ok(())
-            } else {
-                Err(Error::ChunkRestoringError(ChunkError::InvalidChunkProof(
-                    "expected chunk proof to contain only kvvaluefeaturetype or hash nodes",
-                )))
-            }
-        })
-        .unwrap()?;
-
-        // chunk len must be exactly equal to the kv_count + hash_count +
-        // parent_branch_count + child_branch_count
-        debug_assert_eq!(chunk_len, ((kv_count + hash_count) * 2) - 1);
-
-        // chunk structure verified, next verify root hash
-        match parent_key_value_hash_opt {
-            Some(val_hash) => {
-                let combined_hash = combine_hash(val_hash, &tree.hash().unwrap()).unwrap();
-                if &combined_hash != expected_root_hash {
-                    return Err(Error::ChunkRestoringError(ChunkError::InvalidChunkProof(
-                        "chunk doesn't match expected root hash",
-                    )));
-                }
-            }
-            None => {
-                if &tree.hash().unwrap() != expected_root_hash {
-                    return Err(Error::ChunkRestoringError(ChunkError::InvalidChunkProof(
-                        "chunk doesn't match expected root hash",
-                    )));
-                }
-            }
-        };
-
-        Ok(tree)
+        verify_chunk(chunk, expected_root_hash, parent_key_value_hash_opt)
     }
 
     /// Write the verified chunk to storage
@@ -543,6 +557,108 @@ impl<'db, S: StorageContext<'db>> Restorer<S> {
     }
 }
 
+/// Hashes a chunk has to match, as expected by a [`Restorer`] for some chunk id
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkVerifier {
+    chunk_id: Vec<u8>,
+    expected_root_hash: CryptoHash,
+    parent_key_value_hash: Option<CryptoHash>,
+}
+
+impl ChunkVerifier {
+    /// Returns the id of the chunk to verify
+    pub fn chunk_id(&self) -> &[u8] {
+        &self.chunk_id
+    }
+
+    /// Verifies the structure of a chunk and ensures the chunk matches the
+    /// expected root hash
+    pub fn verify(self, chunk: Vec<Op>) -> Result<VerifiedChunk, Error> {
+        let tree = verify_chunk(chunk, &self.expected_root_hash, &self.parent_key_value_hash)?;
+        Ok(VerifiedChunk {
+            verifier: self,
+            tree,
+        })
+    }
+
+    /// Verifies an empty chunk, which is only served as the root chunk of an
+    /// empty tree, so the expected root hash must be the hash of an empty tree
+    pub fn verify_empty(&self) -> Result<(), Error> {
+        let empty_tree_hash = match &self.parent_key_value_hash {
+            Some(val_hash) => combine_hash(val_hash, &NULL_HASH).unwrap(),
+            None => NULL_HASH,
+        };
+        if !self.chunk_id.is_empty() || self.expected_root_hash != empty_tree_hash {
+            return Err(Error::ChunkRestoringError(ChunkError::ChunkHashMismatch));
+        }
+        Ok(())
+    }
+}
+
+/// Chunk that passed the verification of a [`ChunkVerifier`], ready to be
+/// written by [`Restorer::process_verified_chunk`]
+pub struct VerifiedChunk {
+    verifier: ChunkVerifier,
+    tree: ProofTree,
+}
+
+impl VerifiedChunk {
+    /// Returns the id of the verified chunk
+    pub fn chunk_id(&self) -> &[u8] {
+        &self.verifier.chunk_id
+    }
+}
+
+/// Verifies the structure of a chunk and ensures the chunk matches the
+/// expected root hash
+fn verify_chunk(
+    chunk: Vec<Op>,
+    expected_root_hash: &CryptoHash,
+    parent_key_value_hash_opt: &Option<CryptoHash>,
+) -> Result<ProofTree, Error> {
+    let chunk_len = chunk.len();
+    let mut kv_count = 0;
+    let mut hash_count = 0;
+
+    // build tree from ops
+    // ensure only made of KvValueFeatureType and Hash nodes and count them
+    let tree = execute(chunk.clone().into_iter().map(Ok), false, |node| {
+        if matches!(node, Node::KVValueHashFeatureType(..)) {
+            kv_count += 1;
+            Ok(())
+        } else if matches!(node, Node::Hash(..)) {
+            hash_count += 1;
+            Ok(())
+        } else {
+            Err(Error::ChunkRestoringError(ChunkError::InvalidChunkProof(
+                "expected chunk proof to contain only kvvaluefeaturetype or hash nodes",
+            )))
+        }
+    })
+    .unwrap()?;
+
+    // chunk len must be exactly equal to the kv_count + hash_count +
+    // parent_branch_count + child_branch_count
+    debug_assert_eq!(chunk_len, ((kv_count + hash_count) * 2) - 1);
+
+    // chunk structure verified, next verify root hash
+    match parent_key_value_hash_opt {
+        Some(val_hash) => {
+            let combined_hash = combine_hash(val_hash, &tree.hash().unwrap()).unwrap();
+            if &combined_hash != expected_root_hash {
+                return Err(Error::ChunkRestoringError(ChunkError::ChunkHashMismatch));
+            }
+        }
+        None => {
+            if &tree.hash().unwrap() != expected_root_hash {
+                return Err(Error::ChunkRestoringError(ChunkError::ChunkHashMismatch));
+            }
+        }
+    };
+
+    Ok(tree)
+}
+
 #[cfg(test)]
 mod tests {
     use grovedb_path::SubtreePath;
@@ -816,9 +932,7 @@ mod tests {
         assert!(chunk_process_result.is_err());
         assert!(matches!(
             chunk_process_result,
-            Err(Error::ChunkRestoringError(ChunkError::InvalidChunkProof(
-                ..
-            )))
+            Err(Error::ChunkRestoringError(ChunkError::ChunkHashMismatch))
         ));
 
         // correctly apply chunk 5
@@ -1431,4 +1545,104 @@ mod tests {
         assert_eq!(old_chunk_id_to_root_hash, restorer.chunk_id_to_root_hash);
         assert_eq!(old_parent_keys, restorer.parent_keys);
     }
+
+    #[test]
+    fn test_process_chunks_verified_concurrently() {
+        let grove_version = GroveVersion::latest();
+        let mut merk = TempMerk::new(grove_version);
+        let batch = make_batch_seq(0..15);
+        merk.apply::<_, Vec<_>>(&batch, &[], None, grove_version)
+            .unwrap()
+            .expect("apply failed");
+
+        let storage = TempStorage::new();
+        let tx = storage.start_transaction();
+        let restoration_merk = Merk::open_base(
+            storage
+                .get_immediate_storage_context(SubtreePath::empty(), &tx)
+                .unwrap(),
+            false,
+            None::<&fn(&[u8], &GroveVersion) -> Option<ValueDefinedCostType>>,
+            grove_version,
+        )
+        .unwrap()
+        .unwrap();
+
+        let mut chunk_producer = ChunkProducer::new(&merk).expect("should create chunk producer");
+        let mut restorer = Restorer::new(restoration_merk, merk.root_hash().unwrap(), None);
+
+        let (chunk, _) = chunk_producer.chunk(&[], grove_version).unwrap();
+        let new_chunk_ids = restorer
+            .process_chunk(&[], chunk, grove_version)
+            .expect("should process chunk successfully");
+        assert_eq!(new_chunk_ids.len(), 4);
+
+        // verify the remaining chunks on separate threads
+        let jobs = new_chunk_ids
+            .iter()
+            .map(|chunk_id| {
+                let (chunk, _) = chunk_producer.chunk(chunk_id, grove_version).unwrap();
+                (restorer.chunk_verifier(chunk_id).unwrap(), chunk)
+            })
+            .collect::<Vec<_>>();
+        let stale_chunk = jobs[0].clone();
+        let verified_chunks = std::thread::scope(|scope| {
+            jobs.into_iter()
+                .map(|(verifier, chunk)| scope.spawn(move || verifier.verify(chunk)))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().unwrap().expect("should verify chunk"))
+                .collect::<Vec<_>>()
+        });
+
+        for verified_chunk in verified_chunks {
+            restorer
+                .process_verified_chunk(verified_chunk, grove_version)
+                .expect("should process verified chunk");
+        }
+
+        // a chunk that was already processed is no longer expected
+        let (verifier, chunk) = stale_chunk;
+        let verified_chunk = verifier.verify(chunk).expect("should verify chunk");
+        assert!(restorer
+            .process_verified_chunk(verified_chunk, grove_version)
+            .is_err());
+
+        let restored_merk = restorer.finalize(grove_version).expect("should finalize");
+        assert_eq!(
+            restored_merk.root_hash().unwrap(),
+            merk.root_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_verify_empty_chunk() {
+        let verifier =
+            |chunk_id: Vec<u8>, expected_root_hash, parent_key_value_hash| ChunkVerifier {
+                chunk_id,
+                expected_root_hash,
+                parent_key_value_hash,
+            };
+        assert!(verifier(vec![], NULL_HASH, None).verify_empty().is_ok());
+        let val_hash = [1; 32];
+        let empty_subtree_hash = combine_hash(&val_hash, &NULL_HASH).unwrap();
+        assert!(verifier(vec![], empty_subtree_hash, Some(val_hash))
+            .verify_empty()
+            .is_ok());
+
+        // an empty chunk can't stand for a non-empty tree, nor for a chunk
+        // other than the root chunk
+        assert!(matches!(
+            verifier(vec![], [2; 32], None).verify_empty(),
+            Err(ChunkRestoringError(ChunkError::ChunkHashMismatch))
+        ));
+        assert!(matches!(
+            verifier(vec![], NULL_HASH, Some(val_hash)).verify_empty(),
+            Err(ChunkRestoringError(ChunkError::ChunkHashMismatch))
+        ));
+        assert!(matches!(
+            verifier(vec![1], NULL_HASH, None).verify_empty(),
+            Err(ChunkRestoringError(ChunkError::ChunkHashMismatch))
+        ));
+    }
 }
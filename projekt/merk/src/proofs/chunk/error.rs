@@ -65,6 +65,10 @@ pub enum ChunkError {
     #[error("invalid chunk proof: {0}")]
     InvalidChunkProof(&'static str),
 
+    /// Chunk doesn't match the hash it was expected to have
+    #[error("chunk doesn't match expected root hash")]
+    ChunkHashMismatch,
+
     /// Invalid multi chunk
     #[error("invalid multi chunk: {0}")]
     InvalidMultiChunk(&'static str),
@@ -339,6 +339,66 @@ pub fn vec_bytes_as_traversal_instruction(
         .collect()
 }
 
+/// Convert traversal instruction to a compact bytes vec
+/// The instruction is packed into bits (most significant bit first, 1
+/// represents left and 0 represents right), preceded by a byte holding the
+/// number of bits used in the last byte. An empty instruction is represented
+/// by an empty vec.
+pub fn traversal_instruction_as_compact_bytes(instruction: &[bool]) -> Vec<u8> {
+    if instruction.is_empty() {
+        return vec![];
+    }
+    let used_bits_in_last_byte = (instruction.len() - 1) % 8 + 1;
+    let mut compact_bytes = Vec::with_capacity(1 + instruction.len().div_ceil(8));
+    compact_bytes.push(used_bits_in_last_byte as u8);
+    for bits in instruction.chunks(8) {
+        let byte = bits
+            .iter()
+            .enumerate()
+            .fold(0u8, |byte, (i, bit)| byte | ((*bit as u8) << (7 - i)));
+        compact_bytes.push(byte);
+    }
+    compact_bytes
+}
+
+/// Converts a compact bytes vec that represents a traversal instruction
+/// (see `traversal_instruction_as_compact_bytes`) to a vec of bool, true =
+/// left and false = right
+pub fn compact_bytes_as_traversal_instruction(compact_bytes: &[u8]) -> Result<Vec<bool>, Error> {
+    let Some((used_bits_in_last_byte, bytes)) = compact_bytes.split_first() else {
+        return Ok(vec![]);
+    };
+    let used_bits_in_last_byte = *used_bits_in_last_byte as usize;
+    if bytes.is_empty() || used_bits_in_last_byte == 0 || used_bits_in_last_byte > 8 {
+        return Err(Error::ChunkingError(ChunkError::BadTraversalInstruction(
+            "failed to parse instruction compact bytes",
+        )));
+    }
+    let instruction_len = (bytes.len() - 1) * 8 + used_bits_in_last_byte;
+    if bytes[bytes.len() - 1] & (u8::MAX >> used_bits_in_last_byte) != 0 {
+        return Err(Error::ChunkingError(ChunkError::BadTraversalInstruction(
+            "failed to parse instruction compact bytes",
+        )));
+    }
+    Ok((0..instruction_len)
+        .map(|i| bytes[i / 8] & (1 << (7 - i % 8)) != 0)
+        .collect())
+}
+
+/// Converts a vec bytes that represents a traversal instruction (see
+/// `traversal_instruction_as_vec_bytes`) to its compact bytes representation
+pub fn vec_bytes_as_compact_bytes(instruction_vec_bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    vec_bytes_as_traversal_instruction(instruction_vec_bytes)
+        .map(|instruction| traversal_instruction_as_compact_bytes(&instruction))
+}
+
+/// Converts a compact bytes vec that represents a traversal instruction to
+/// its vec bytes representation (see `traversal_instruction_as_vec_bytes`)
+pub fn compact_bytes_as_vec_bytes(compact_bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    compact_bytes_as_traversal_instruction(compact_bytes)
+        .map(|instruction| traversal_instruction_as_vec_bytes(&instruction))
+}
+
 pub fn write_to_vec<W: Write>(dest: &mut W, value: &[u8]) -> Result<(), Error> {
     dest.write_all(value)
         .map_err(|_e| InternalError("failed to write to vector"))
@@ -575,6 +635,77 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_traversal_instruction_as_compact_bytes() {
+        assert_eq!(
+            traversal_instruction_as_compact_bytes(&[]),
+            Vec::<u8>::new()
+        );
+        assert_eq!(
+            traversal_instruction_as_compact_bytes(&[LEFT]),
+            vec![1, 128]
+        );
+        assert_eq!(traversal_instruction_as_compact_bytes(&[RIGHT]), vec![1, 0]);
+        assert_eq!(
+            traversal_instruction_as_compact_bytes(&[RIGHT, LEFT, LEFT, RIGHT]),
+            vec![4, 0b0110_0000]
+        );
+        assert_eq!(
+            traversal_instruction_as_compact_bytes(&[LEFT; 8]),
+            vec![8, 0b1111_1111]
+        );
+        assert_eq!(
+            traversal_instruction_as_compact_bytes(&[LEFT; 9]),
+            vec![1, 0b1111_1111, 0b1000_0000]
+        );
+    }
+
+    #[test]
+    fn test_compact_bytes_to_traversal_instruction() {
+        assert_eq!(
+            compact_bytes_as_traversal_instruction(&[]).unwrap(),
+            Vec::<bool>::new()
+        );
+        assert_eq!(
+            compact_bytes_as_traversal_instruction(&[3, 0b0010_0000]).unwrap(),
+            vec![RIGHT, RIGHT, LEFT]
+        );
+        // missing instruction bytes
+        assert!(compact_bytes_as_traversal_instruction(&[3]).is_err());
+        // invalid number of used bits
+        assert!(compact_bytes_as_traversal_instruction(&[0, 0]).is_err());
+        assert!(compact_bytes_as_traversal_instruction(&[9, 0]).is_err());
+        // unused bits must be zero
+        assert!(compact_bytes_as_traversal_instruction(&[3, 0b0011_0000]).is_err());
+    }
+
+    #[test]
+    fn test_compact_bytes_round_trip() {
+        for len in 0..40usize {
+            let instruction: Vec<bool> = (0..len).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+            let compact_bytes = traversal_instruction_as_compact_bytes(&instruction);
+            assert_eq!(
+                compact_bytes.len(),
+                if len == 0 { 0 } else { 1 + len.div_ceil(8) }
+            );
+            assert_eq!(
+                compact_bytes_as_traversal_instruction(&compact_bytes).unwrap(),
+                instruction
+            );
+
+            // compatibility with the vec bytes representation
+            let vec_bytes = traversal_instruction_as_vec_bytes(&instruction);
+            assert_eq!(
+                vec_bytes_as_compact_bytes(&vec_bytes).unwrap(),
+                compact_bytes
+            );
+            assert_eq!(
+                compact_bytes_as_vec_bytes(&compact_bytes).unwrap(),
+                vec_bytes
+            );
+        }
+    }
+
     #[test]
     fn test_chunk_id_from_traversal_instruction() {
         // tree of height 4
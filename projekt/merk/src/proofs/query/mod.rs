@@ -12,6 +12,8 @@ mod merge;
 #[cfg(any(feature = "full", feature = "verify"))]
 pub mod query_item;
 #[cfg(any(feature = "full", feature = "verify"))]
+mod value_filter;
+#[cfg(any(feature = "full", feature = "verify"))]
 mod verify;
 
 #[cfg(feature = "full")]
@@ -36,6 +38,8 @@ pub use map::*;
 pub use query_item::intersect::QueryItemIntersectionResult;
 #[cfg(any(feature = "full", feature = "verify"))]
 pub use query_item::QueryItem;
+#[cfg(any(feature = "full", feature = "verify"))]
+pub use value_filter::{NumericComparison, ValueFilter};
 #[cfg(feature = "full")]
 use verify::ProofAbsenceLimit;
 #[cfg(any(feature = "full", feature = "verify"))]
@@ -120,6 +124,33 @@ pub struct Query {
     pub conditional_subquery_branches: Option<IndexMap<QueryItem, SubqueryBranch>>,
     /// Left to right?
     pub left_to_right: bool,
+    /// Filter on the values of the elements returned at this level
+    pub value_filter: Option<ValueFilter>,
+}
+
+#[cfg(any(feature = "full", feature = "verify"))]
+// Bits of the flag written before the conditional subquery branches. Queries
+// encoded before value filters existed only have the first bit, so they are
+// decoded without reading a value filter.
+const CONDITIONAL_SUBQUERY_BRANCHES_FLAG: u8 = 1;
+#[cfg(any(feature = "full", feature = "verify"))]
+const VALUE_FILTER_FLAG: u8 = 2;
+
+#[cfg(any(feature = "full", feature = "verify"))]
+// Checks the flag written before the conditional subquery branches, returning
+// whether there are conditional subquery branches and a value filter
+fn decode_query_flags(flags: u8) -> Result<(bool, bool), DecodeError> {
+    if flags & !(CONDITIONAL_SUBQUERY_BRANCHES_FLAG | VALUE_FILTER_FLAG) != 0 {
+        return Err(DecodeError::UnexpectedVariant {
+            type_name: "Query flags",
+            allowed: &bincode::error::AllowedEnumVariants::Range { min: 0, max: 3 },
+            found: flags as u32,
+        });
+    }
+    Ok((
+        flags & CONDITIONAL_SUBQUERY_BRANCHES_FLAG != 0,
+        flags & VALUE_FILTER_FLAG != 0,
+    ))
 }
 
 #[cfg(any(feature = "full", feature = "verify"))]
@@ -131,11 +162,22 @@ impl Encode for Query {
         // Encode the default subquery branch
         self.default_subquery_branch.encode(encoder)?;
 
+        // The value filter is only flagged when present, keeping the encoding
+        // of queries without one unchanged
+        let value_filter_flag = if self.value_filter.is_some() {
+            VALUE_FILTER_FLAG
+        } else {
+            0
+        };
+
         // Encode the conditional subquery branches
         match &self.conditional_subquery_branches {
             Some(conditional_subquery_branches) => {
-                encoder.writer().write(&[1])?; // Write a flag indicating presence of data
-                                               // Encode the length of the map
+                // Write a flag indicating presence of data
+                encoder
+                    .writer()
+                    .write(&[CONDITIONAL_SUBQUERY_BRANCHES_FLAG | value_filter_flag])?;
+                // Encode the length of the map
                 (conditional_subquery_branches.len() as u64).encode(encoder)?;
                 // Encode each key-value pair in the IndexMap
                 for (key, value) in conditional_subquery_branches {
@@ -144,14 +186,19 @@ impl Encode for Query {
                 }
             }
             None => {
-                encoder.writer().write(&[0])?; // Write a flag indicating
-                                               // absence of data
+                // Write a flag indicating absence of data
+                encoder.writer().write(&[value_filter_flag])?;
             }
         }
 
         // Encode the left_to_right boolean
         self.left_to_right.encode(encoder)?;
 
+        // Encode the value filter
+        if let Some(value_filter) = &self.value_filter {
+            value_filter.encode(encoder)?;
+        }
+
         Ok(())
     }
 }
@@ -166,7 +213,9 @@ impl Decode for Query {
         let default_subquery_branch = SubqueryBranch::decode(decoder)?;
 
         // Decode the conditional subquery branches
-        let conditional_subquery_branches = if u8::decode(decoder)? == 1 {
+        let (has_conditional_subquery_branches, has_value_filter) =
+            decode_query_flags(u8::decode(decoder)?)?;
+        let conditional_subquery_branches = if has_conditional_subquery_branches {
             let len = u64::decode(decoder)? as usize;
             let mut map = IndexMap::with_capacity(len);
             for _ in 0..len {
@@ -182,11 +231,19 @@ impl Decode for Query {
         // Decode the left_to_right boolean
         let left_to_right = bool::decode(decoder)?;
 
+        // Decode the value filter
+        let value_filter = if has_value_filter {
+            Some(ValueFilter::decode(decoder)?)
+        } else {
+            None
+        };
+
         Ok(Query {
             items,
             default_subquery_branch,
             conditional_subquery_branches,
             left_to_right,
+            value_filter,
         })
     }
 }
@@ -203,7 +260,9 @@ impl<'de> BorrowDecode<'de> for Query {
         let default_subquery_branch = SubqueryBranch::borrow_decode(decoder)?;
 
         // Borrow-decode the conditional subquery branches
-        let conditional_subquery_branches = if u8::borrow_decode(decoder)? == 1 {
+        let (has_conditional_subquery_branches, has_value_filter) =
+            decode_query_flags(u8::borrow_decode(decoder)?)?;
+        let conditional_subquery_branches = if has_conditional_subquery_branches {
             let len = u64::borrow_decode(decoder)? as usize;
             let mut map = IndexMap::with_capacity(len);
             for _ in 0..len {
@@ -219,11 +278,19 @@ impl<'de> BorrowDecode<'de> for Query {
         // Borrow-decode the left_to_right boolean
         let left_to_right = bool::borrow_decode(decoder)?;
 
+        // Borrow-decode the value filter
+        let value_filter = if has_value_filter {
+            Some(ValueFilter::borrow_decode(decoder)?)
+        } else {
+            None
+        };
+
         Ok(Query {
             items,
             default_subquery_branch,
             conditional_subquery_branches,
             left_to_right,
+            value_filter,
         })
     }
 }
@@ -275,6 +342,9 @@ impl fmt::Display for Query {
             writeln!(f, "  }},")?;
         }
         writeln!(f, "  left_to_right: {},", self.left_to_right)?;
+        if let Some(value_filter) = &self.value_filter {
+            writeln!(f, "  value_filter: {},", value_filter)?;
+        }
         write!(f, "}}")
     }
 }
@@ -571,6 +641,14 @@ impl Query {
         self.default_subquery_branch.subquery = Some(Box::new(subquery));
     }
 
+    /// Sets the filter on the values of the elements returned by the query.
+    /// Elements of subqueries are filtered by the subqueries' own filters.
+    /// An element reached through a subquery path without a subquery is
+    /// returned at this level, and is thus filtered by this filter.
+    pub fn set_value_filter(&mut self, value_filter: ValueFilter) {
+        self.value_filter = Some(value_filter);
+    }
+
     /// Adds a conditional subquery. A conditional subquery replaces the default
     /// subquery and subquery_path if the item matches for the key. If
     /// multiple conditional subquery items match, then the first one that
@@ -614,6 +692,23 @@ impl Query {
         false
     }
 
+    /// Check if the query or any of its subqueries has a value filter
+    pub fn has_value_filter(&self) -> bool {
+        let subquery_has_value_filter = |subquery_branch: &SubqueryBranch| {
+            subquery_branch
+                .subquery
+                .as_ref()
+                .is_some_and(|subquery| subquery.has_value_filter())
+        };
+        self.value_filter.is_some()
+            || subquery_has_value_filter(&self.default_subquery_branch)
+            || self
+                .conditional_subquery_branches
+                .iter()
+                .flatten()
+                .any(|(_, subquery_branch)| subquery_has_value_filter(subquery_branch))
+    }
+
     /// Check if there are only keys
     pub fn has_only_keys(&self) -> bool {
         // checks if all searched for items are keys
@@ -660,6 +755,7 @@ impl<Q: Into<QueryItem>> From<Vec<Q>> for Query {
             },
             conditional_subquery_branches: None,
             left_to_right: true,
+            value_filter: None,
         }
     }
 }
@@ -4748,4 +4844,65 @@ mod test {
             .unwrap()
             .expect("verify failed");
     }
+
+    fn encoding_config() -> impl bincode::config::Config {
+        bincode::config::standard()
+            .with_big_endian()
+            .with_no_limit()
+    }
+
+    #[test]
+    fn test_decode_query_encoded_without_value_filters() {
+        let mut subquery = Query::new();
+        subquery.insert_all();
+        let mut query = Query::new_with_direction(false);
+        query.insert_key(b"a".to_vec());
+        query.insert_range_from(b"b".to_vec()..);
+        query.set_subquery_path(vec![b"c".to_vec()]);
+        query.set_subquery(subquery);
+
+        // Encoded before queries had value filters
+        let bytes = [
+            0x02, 0x00, 0x01, b'a', 0x04, 0x01, b'b', 0x01, 0x01, 0x01, b'c', 0x01, 0x01, 0x03,
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        ];
+        let (decoded, read): (Query, _) =
+            bincode::decode_from_slice(&bytes, encoding_config()).expect("expected to decode");
+        assert_eq!(read, bytes.len());
+        assert_eq!(decoded, query);
+        assert_eq!(
+            bincode::encode_to_vec(&query, encoding_config()).expect("expected to encode"),
+            bytes
+        );
+    }
+
+    #[test]
+    fn test_encode_query_with_value_filters() {
+        let mut subquery = Query::new();
+        subquery.insert_all();
+        subquery.set_value_filter(ValueFilter::Prefix(b"x".to_vec()));
+        let mut conditional_subquery = Query::new();
+        conditional_subquery.insert_all();
+        conditional_subquery
+            .set_value_filter(ValueFilter::Compare(NumericComparison::GreaterThan, -3));
+        let mut query = Query::new();
+        query.insert_all();
+        query.set_subquery(subquery);
+        query.add_conditional_subquery(
+            QueryItem::Key(b"a".to_vec()),
+            None,
+            Some(conditional_subquery),
+        );
+        query.set_value_filter(ValueFilter::Equal(b"y".to_vec()));
+
+        let bytes = bincode::encode_to_vec(&query, encoding_config()).expect("expected to encode");
+        let (decoded, read): (Query, _) =
+            bincode::decode_from_slice(&bytes, encoding_config()).expect("expected to decode");
+        assert_eq!(read, bytes.len());
+        assert_eq!(decoded, query);
+        let (borrow_decoded, _): (Query, _) =
+            bincode::borrow_decode_from_slice(&bytes, encoding_config())
+                .expect("expected to borrow-decode");
+        assert_eq!(borrow_decoded, query);
+    }
 }
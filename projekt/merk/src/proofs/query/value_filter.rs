@@ -0,0 +1,134 @@
+//! Value filters of queries
+
+use std::fmt;
+
+use bincode::{Decode, Encode};
+
+use crate::proofs::hex_to_ascii;
+
+/// Comparison of a numeric value against an integer
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Encode, Decode)]
+pub enum NumericComparison {
+    /// Equal to
+    Equal,
+    /// Less than
+    LessThan,
+    /// Less than or equal to
+    LessThanOrEqual,
+    /// Greater than
+    GreaterThan,
+    /// Greater than or equal to
+    GreaterThanOrEqual,
+}
+
+impl NumericComparison {
+    /// Compares the value against the operand
+    pub fn compare(&self, value: i128, operand: i128) -> bool {
+        match self {
+            NumericComparison::Equal => value == operand,
+            NumericComparison::LessThan => value < operand,
+            NumericComparison::LessThanOrEqual => value <= operand,
+            NumericComparison::GreaterThan => value > operand,
+            NumericComparison::GreaterThanOrEqual => value >= operand,
+        }
+    }
+}
+
+impl fmt::Display for NumericComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = match self {
+            NumericComparison::Equal => "==",
+            NumericComparison::LessThan => "<",
+            NumericComparison::LessThanOrEqual => "<=",
+            NumericComparison::GreaterThan => ">",
+            NumericComparison::GreaterThanOrEqual => ">=",
+        };
+        write!(f, "{}", operator)
+    }
+}
+
+/// Filter on the values of the elements a query returns
+///
+/// Byte filters match item values. Numeric filters match sum item values and
+/// item values holding a big-endian unsigned integer of 1 to 8 bytes. Elements
+/// that are not filtered out are returned, the others are skipped without
+/// counting toward the limit.
+///
+/// Skipped elements leave no trace in a proof, so path queries with a value
+/// filter at any level can't be proved: `GroveDb::prove_query` rejects them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Encode, Decode)]
+pub enum ValueFilter {
+    /// Item values equal to the bytes
+    Equal(Vec<u8>),
+    /// Item values starting with the bytes
+    Prefix(Vec<u8>),
+    /// Numeric values comparing to the integer
+    Compare(NumericComparison, i128),
+}
+
+impl ValueFilter {
+    /// Whether an item value matches the filter
+    pub fn matches_item_value(&self, value: &[u8]) -> bool {
+        match self {
+            ValueFilter::Equal(bytes) => value == bytes.as_slice(),
+            ValueFilter::Prefix(prefix) => value.starts_with(prefix),
+            ValueFilter::Compare(comparison, operand) => {
+                if value.is_empty() || value.len() > 8 {
+                    return false;
+                }
+                let mut integer_bytes = [0u8; 8];
+                integer_bytes[8 - value.len()..].copy_from_slice(value);
+                comparison.compare(u64::from_be_bytes(integer_bytes) as i128, *operand)
+            }
+        }
+    }
+
+    /// Whether a sum value matches the filter
+    pub fn matches_sum_value(&self, value: i64) -> bool {
+        match self {
+            ValueFilter::Compare(comparison, operand) => {
+                comparison.compare(value as i128, *operand)
+            }
+            ValueFilter::Equal(_) | ValueFilter::Prefix(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for ValueFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueFilter::Equal(bytes) => write!(f, "Equal({})", hex_to_ascii(bytes)),
+            ValueFilter::Prefix(prefix) => write!(f, "Prefix({})", hex_to_ascii(prefix)),
+            ValueFilter::Compare(comparison, operand) => write!(f, "{} {}", comparison, operand),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_filter_matches_item_values() {
+        assert!(ValueFilter::Equal(b"abc".to_vec()).matches_item_value(b"abc"));
+        assert!(!ValueFilter::Equal(b"abc".to_vec()).matches_item_value(b"abcd"));
+        assert!(ValueFilter::Prefix(b"ab".to_vec()).matches_item_value(b"abcd"));
+        assert!(!ValueFilter::Prefix(b"ab".to_vec()).matches_item_value(b"a"));
+
+        let greater_than_255 = ValueFilter::Compare(NumericComparison::GreaterThan, 255);
+        assert!(greater_than_255.matches_item_value(&256u16.to_be_bytes()));
+        assert!(greater_than_255.matches_item_value(&u64::MAX.to_be_bytes()));
+        assert!(!greater_than_255.matches_item_value(&[255]));
+        assert!(!greater_than_255.matches_item_value(&[]));
+        assert!(!greater_than_255.matches_item_value(&[1; 9]));
+    }
+
+    #[test]
+    fn test_value_filter_matches_sum_values() {
+        let at_most_minus_one = ValueFilter::Compare(NumericComparison::LessThanOrEqual, -1);
+        assert!(at_most_minus_one.matches_sum_value(-1));
+        assert!(at_most_minus_one.matches_sum_value(i64::MIN));
+        assert!(!at_most_minus_one.matches_sum_value(0));
+        assert!(!ValueFilter::Equal(vec![0]).matches_sum_value(0));
+    }
+}
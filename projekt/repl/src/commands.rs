@@ -0,0 +1,476 @@
+//! Commands of the command-line tool
+
+use std::{collections::VecDeque, fs, path::Path, sync::Arc};
+
+use grovedb::{
+    operations::{delete::DeleteOptions, insert::InsertOptions},
+    query_result_type::QueryResultType,
+    replication::{
+        ChunkSource, DirectoryChunkSource, MultiStateSyncInfo, StateSyncEvent, TcpChunkSource,
+    },
+    GroveDb, PathQuery, Query, SizedQuery, TransactionArg,
+};
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    shell,
+    syntax::{
+        format_element, format_path, format_path_key, parse_element, parse_path, parse_path_key,
+        parse_query_item,
+    },
+    CliError, USAGE,
+};
+
+// Options taking a value
+const VALUE_OPTIONS: &[&str] = &["--limit", "--offset", "--out", "--root-hash"];
+
+/// Options of the query, prove and verify commands
+pub const QUERY_OPTIONS: &[&str] = &["--limit", "--offset", "--desc"];
+const PROVE_OPTIONS: &[&str] = &["--limit", "--offset", "--desc", "--out"];
+const VERIFY_OPTIONS: &[&str] = &["--limit", "--offset", "--desc", "--root-hash"];
+
+/// Arguments of a command: its positional arguments and options
+pub struct Args {
+    positional: VecDeque<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    /// Splits the arguments into positional arguments and the options
+    /// allowed for the command
+    pub fn parse(args: &[String], allowed_options: &[&str]) -> Result<Self, CliError> {
+        let mut positional = VecDeque::new();
+        let mut options = vec![];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                positional.push_back(arg.clone());
+                continue;
+            }
+            if !allowed_options.contains(&arg.as_str()) {
+                return Err(CliError::Usage(format!("unknown option `{}`", arg)));
+            }
+            let value = if VALUE_OPTIONS.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("missing value of `{}`", arg)))?;
+                Some(value.clone())
+            } else {
+                None
+            };
+            options.push((arg.clone(), value));
+        }
+        Ok(Args {
+            positional,
+            options,
+        })
+    }
+
+    /// Takes the next positional argument
+    pub fn next(&mut self, name: &str) -> Result<String, CliError> {
+        self.positional
+            .pop_front()
+            .ok_or_else(|| CliError::Usage(format!("missing argument <{}>", name)))
+    }
+
+    /// Takes the next positional argument, if any
+    pub fn next_optional(&mut self) -> Option<String> {
+        self.positional.pop_front()
+    }
+
+    /// Takes the remaining positional arguments
+    pub fn rest(&mut self) -> Vec<String> {
+        self.positional.drain(..).collect()
+    }
+
+    /// Returns true if the option was given
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    /// Returns the value of the option, if given
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Returns the numeric value of the option, if given
+    pub fn number(&self, name: &str) -> Result<Option<u16>, CliError> {
+        self.value(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e| CliError::Usage(format!("invalid {} `{}`: {}", name, value, e)))
+            })
+            .transpose()
+    }
+
+    /// Fails if positional arguments remain
+    pub fn finish(self) -> Result<(), CliError> {
+        match self.positional.front() {
+            Some(arg) => Err(CliError::Usage(format!("unexpected argument `{}`", arg))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Runs the command given by the command-line arguments
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    let Some((command, args)) = args.split_first() else {
+        return Err(CliError::Usage("missing command".to_string()));
+    };
+    let grove_version = GroveVersion::latest();
+    match command.as_str() {
+        "open" => open(Args::parse(args, &[])?, grove_version),
+        "root-hash" => root_hash(Args::parse(args, &[])?, grove_version),
+        "get" => get(Args::parse(args, &["--raw"])?, grove_version),
+        "insert" => insert(Args::parse(args, &[])?, grove_version),
+        "delete" => delete(Args::parse(args, &["--recursive"])?, grove_version),
+        "query" => query(Args::parse(args, QUERY_OPTIONS)?, grove_version),
+        "prove" => prove(Args::parse(args, PROVE_OPTIONS)?, grove_version),
+        "verify" => verify(Args::parse(args, VERIFY_OPTIONS)?, grove_version),
+        "sync" => sync(Args::parse(args, &["--validate"])?, grove_version),
+        "checkpoint" => checkpoint(Args::parse(args, &[])?),
+        "shell" => shell::run(Args::parse(args, &[])?, grove_version),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        command => Err(CliError::Usage(format!("unknown command `{}`", command))),
+    }
+}
+
+/// Opens the database at the given path. Only the open command creates
+/// databases, other commands fail on a mistyped path.
+pub fn open_db(path: &str, create: bool) -> Result<GroveDb, CliError> {
+    if !create && !Path::new(path).exists() {
+        return Err(CliError::Failed(format!("no database at {}", path)));
+    }
+    Ok(GroveDb::open(path)?)
+}
+
+fn print_root_hash(db: &GroveDb, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let root_hash = db.root_hash(None, grove_version).unwrap()?;
+    println!("root hash: {}", hex::encode(root_hash));
+    Ok(())
+}
+
+fn open(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, true)?;
+    args.finish()?;
+    print_root_hash(&db, grove_version)?;
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new_unsized(vec![], query);
+    print_query_results(&db, &path_query, None, grove_version)
+}
+
+fn root_hash(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, false)?;
+    args.finish()?;
+    let root_hash = db.root_hash(None, grove_version).unwrap()?;
+    println!("{}", hex::encode(root_hash));
+    Ok(())
+}
+
+fn get(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, false)?;
+    let (path, key) = parse_path_key(&args.next("path/key")?)?;
+    let raw = args.flag("--raw");
+    args.finish()?;
+    let element = if raw {
+        db.get_raw(path.as_slice().into(), &key, None, grove_version)
+            .unwrap()?
+    } else {
+        db.get(path.as_slice(), &key, None, grove_version)
+            .unwrap()?
+    };
+    println!("{}", format_element(&element));
+    Ok(())
+}
+
+fn insert(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, false)?;
+    let (path, key) = parse_path_key(&args.next("path/key")?)?;
+    let element_type = args.next("type")?;
+    let value = args.next_optional();
+    args.finish()?;
+    let element = parse_element(&element_type, value.as_deref())?;
+    db.insert(
+        path.as_slice(),
+        &key,
+        element,
+        Some(InsertOptions::default()),
+        None,
+        grove_version,
+    )
+    .unwrap()?;
+    println!("inserted {}", format_path_key(&path, &key));
+    print_root_hash(&db, grove_version)
+}
+
+fn delete(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, false)?;
+    let (path, key) = parse_path_key(&args.next("path/key")?)?;
+    let options = DeleteOptions {
+        allow_deleting_non_empty_trees: args.flag("--recursive"),
+        ..Default::default()
+    };
+    args.finish()?;
+    db.delete(path.as_slice(), &key, Some(options), None, grove_version)
+        .unwrap()?;
+    println!("deleted {}", format_path_key(&path, &key));
+    print_root_hash(&db, grove_version)
+}
+
+// Builds the path query of the query, prove and verify commands from the
+// remaining arguments: either a query string, or the path, the query items and
+// the query options
+fn path_query(args: &mut Args) -> Result<PathQuery, CliError> {
+    let path = args.next("path")?;
+    if is_query_text(&path) {
+        if let Some(option) = QUERY_OPTIONS.iter().find(|option| args.flag(option)) {
+            return Err(CliError::Usage(format!(
+                "`{}` can not be used with a query string",
+                option
+            )));
+        }
+        return parse_query_text(&path);
+    }
+    items_query(parse_path(&path)?, args)
+}
+
+/// Returns true if the text is a query string, starting with `SELECT`
+pub fn is_query_text(text: &str) -> bool {
+    text.split_whitespace()
+        .next()
+        .is_some_and(|word| word.eq_ignore_ascii_case("select"))
+}
+
+/// Parses a query string such as
+/// `SELECT * FROM /student WHERE KEY BETWEEN 0x01 AND 0x10 LIMIT 10`
+pub fn parse_query_text(text: &str) -> Result<PathQuery, CliError> {
+    text.parse()
+        .map_err(|e: grovedb::Error| CliError::Usage(e.to_string()))
+}
+
+/// Builds a query of the subtree at the given path from the remaining
+/// arguments: the query items (all keys if none) and the query options
+pub fn items_query(path: Vec<Vec<u8>>, args: &mut Args) -> Result<PathQuery, CliError> {
+    let items = args.rest();
+    let mut query = Query::new_with_direction(!args.flag("--desc"));
+    if items.is_empty() {
+        query.insert_all();
+    }
+    for item in items {
+        query.insert_item(parse_query_item(&item)?);
+    }
+    let limit = args.number("--limit")?;
+    let offset = args.number("--offset")?;
+    Ok(PathQuery::new(path, SizedQuery::new(query, limit, offset)))
+}
+
+/// Prints the elements matching the path query
+pub fn print_query_results(
+    db: &GroveDb,
+    path_query: &PathQuery,
+    transaction: TransactionArg,
+    grove_version: &GroveVersion,
+) -> Result<(), CliError> {
+    let (results, _) = db
+        .query_raw(
+            path_query,
+            true,
+            true,
+            true,
+            QueryResultType::QueryPathKeyElementTrioResultType,
+            transaction,
+            grove_version,
+        )
+        .unwrap()?;
+    let results = results.to_path_key_elements();
+    for (path, key, element) in results.iter() {
+        println!(
+            "{}: {}",
+            format_path_key(path, key),
+            format_element(element)
+        );
+    }
+    println!("({} elements)", results.len());
+    Ok(())
+}
+
+fn query(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, false)?;
+    let path_query = path_query(&mut args)?;
+    args.finish()?;
+    print_query_results(&db, &path_query, None, grove_version)
+}
+
+fn prove(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, false)?;
+    let path_query = path_query(&mut args)?;
+    let out = args.value("--out").map(str::to_string);
+    args.finish()?;
+    let proof = db.prove_query(&path_query, None, grove_version).unwrap()?;
+    match out {
+        Some(out) => {
+            fs::write(&out, &proof)?;
+            println!("wrote {} bytes of proof to {}", proof.len(), out);
+        }
+        None => println!("0x{}", hex::encode(&proof)),
+    }
+    print_root_hash(&db, grove_version)
+}
+
+fn verify(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let proof = args.next("proof")?;
+    let path_query = path_query(&mut args)?;
+    let expected_root_hash = args.value("--root-hash").map(str::to_string);
+    args.finish()?;
+    let proof = match proof.strip_prefix("0x") {
+        Some(proof) => {
+            hex::decode(proof).map_err(|e| CliError::Usage(format!("invalid hex proof: {}", e)))?
+        }
+        None => fs::read(&proof)?,
+    };
+    let (root_hash, results) = GroveDb::verify_query(&proof, &path_query, grove_version)?;
+    for (path, key, element) in results.iter() {
+        let element = element
+            .as_ref()
+            .map_or("absent".to_string(), format_element);
+        println!("{}: {}", format_path_key(path, key), element);
+    }
+    println!("root hash: {}", hex::encode(root_hash));
+    if let Some(expected_root_hash) = expected_root_hash {
+        if hex::encode(root_hash) != expected_root_hash.trim_start_matches("0x") {
+            return Err(CliError::Failed(format!(
+                "proof doesn't match root hash {}",
+                expected_root_hash
+            )));
+        }
+        println!("proof verified");
+    }
+    Ok(())
+}
+
+// Opens a chunk source: a chunk directory (dir:<path>), a chunk server
+// (tcp:<address>) or another database
+fn open_chunk_source(source: &str) -> Result<Box<dyn ChunkSource>, CliError> {
+    if let Some(dir) = source.strip_prefix("dir:") {
+        Ok(Box::new(DirectoryChunkSource::open(dir)?))
+    } else if let Some(address) = source.strip_prefix("tcp:") {
+        Ok(Box::new(TcpChunkSource::connect(address)?))
+    } else {
+        Ok(Box::new(open_db(source, false)?))
+    }
+}
+
+fn print_sync_event(event: StateSyncEvent) {
+    match event {
+        StateSyncEvent::SubtreeFinalized {
+            path,
+            num_processed_chunks,
+        } => {
+            println!(
+                "  {} done ({} chunks)",
+                format_path(path),
+                num_processed_chunks
+            );
+        }
+        StateSyncEvent::Error {
+            global_chunk_id,
+            error,
+        } => {
+            println!(
+                "  error (chunk {:?}): {}",
+                global_chunk_id.map(hex::encode),
+                error
+            );
+        }
+        _ => {}
+    }
+}
+
+fn sync(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, false)?;
+    let source = open_chunk_source(&args.next("source")?)?;
+    let validate = args.flag("--validate");
+    args.finish()?;
+    let mut state_sync_info =
+        MultiStateSyncInfo::default().with_observer(Arc::new(print_sync_event));
+    if validate {
+        state_sync_info = state_sync_info.with_validation();
+    }
+    let tx = db.start_transaction();
+    let state_sync_info = db.sync_from_source(&*source, state_sync_info, &tx, grove_version)?;
+    println!("{}", state_sync_info.stats());
+    drop(state_sync_info);
+    db.commit_transaction(tx).unwrap()?;
+    print_root_hash(&db, grove_version)
+}
+
+fn checkpoint(mut args: Args) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, false)?;
+    let dir = args.next("dir")?;
+    args.finish()?;
+    db.create_checkpoint(&dir)?;
+    println!("created checkpoint in {}", dir);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let mut parsed = Args::parse(
+            &args(&["db", "--desc", "/student", "--limit", "5", "a..b"]),
+            QUERY_OPTIONS,
+        )
+        .expect("expected to parse arguments");
+        assert!(parsed.flag("--desc"));
+        assert!(!parsed.flag("--offset"));
+        assert_eq!(parsed.value("--limit"), Some("5"));
+        assert_eq!(parsed.number("--limit").unwrap(), Some(5));
+        assert_eq!(parsed.number("--offset").unwrap(), None);
+        assert_eq!(parsed.next("db").unwrap(), "db");
+        assert_eq!(parsed.next_optional().as_deref(), Some("/student"));
+        assert_eq!(parsed.rest(), args(&["a..b"]));
+        assert!(matches!(parsed.next("path"), Err(CliError::Usage(_))));
+        assert!(parsed.finish().is_ok());
+    }
+
+    #[test]
+    fn test_parse_args_last_option_value_wins() {
+        let parsed = Args::parse(&args(&["--limit", "1", "--limit", "2"]), QUERY_OPTIONS)
+            .expect("expected to parse arguments");
+        assert_eq!(parsed.value("--limit"), Some("2"));
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(matches!(
+            Args::parse(&args(&["db", "--desc"]), &[]),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(
+            Args::parse(&args(&["db", "--limit"]), QUERY_OPTIONS),
+            Err(CliError::Usage(_))
+        ));
+        let parsed = Args::parse(&args(&["--limit", "-1"]), QUERY_OPTIONS)
+            .expect("expected to parse arguments");
+        assert!(matches!(parsed.number("--limit"), Err(CliError::Usage(_))));
+        let parsed =
+            Args::parse(&args(&["db", "extra"]), &[]).expect("expected to parse arguments");
+        assert!(matches!(parsed.finish(), Err(CliError::Usage(_))));
+    }
+}
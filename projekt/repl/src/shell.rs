@@ -0,0 +1,504 @@
+//! Interactive shell
+//!
+//! `zas shell <db>` reads commands line by line from the standard input.
+//! Paths are relative to the current subtree unless they start with `/`, and
+//! `..` stands for the parent subtree. Between `begin` and `commit` (or
+//! `rollback`) all commands read and write through a transaction. Lines
+//! starting with `SELECT` are query strings, with absolute paths.
+
+use std::io::{self, BufRead, Write};
+
+use grovedb::{
+    operations::{delete::DeleteOptions, insert::InsertOptions},
+    query_result_type::QueryResultType,
+    Element, GroveDb, PathQuery, Query, Transaction, TransactionArg,
+};
+use grovedb_path::SubtreePath;
+use grovedb_version::version::GroveVersion;
+
+use crate::{
+    commands::{
+        is_query_text, items_query, open_db, parse_query_text, print_query_results, Args,
+        QUERY_OPTIONS,
+    },
+    syntax::{
+        element_type, format_bytes, format_element, format_path, format_path_key, parse_bytes,
+        parse_element,
+    },
+    CliError,
+};
+
+const HELP: &str = "\
+commands:
+  pwd                               print the current subtree
+  cd [path]                         change the current subtree (/ if no path)
+  ls [path]                         list the keys of a subtree with their element types
+  cat <key>                         print an element, following references
+  query [items...]                  query the current subtree, with [--limit <n>]
+                                    [--offset <n>] [--desc]
+  SELECT * FROM <path> ...          run a query string, see `zas help`
+  find [path]                       list the subtrees below a subtree
+  insert <key> <type> [value]       insert an element: item <value>, sum_item <integer>,
+                                    ref <path/key>, tree or sum_tree
+  rm <key> [--recursive]            delete an element (--recursive: also non-empty trees)
+  root-hash                         print the root hash
+  begin / commit / rollback         start, commit or roll back a transaction
+  help                              print this message
+  exit                              leave the shell (rolling back an open transaction)
+
+keys may be paths, e.g. ../grade/1_2 or /student/1";
+
+// State of the shell: the current subtree and the open transaction
+struct Shell<'db> {
+    db: &'db GroveDb,
+    current_path: Vec<Vec<u8>>,
+    transaction: Option<Transaction<'db>>,
+    grove_version: &'db GroveVersion,
+}
+
+/// Runs the interactive shell on the database given by the arguments
+pub fn run(mut args: Args, grove_version: &GroveVersion) -> Result<(), CliError> {
+    let db = open_db(&args.next("db")?, false)?;
+    args.finish()?;
+    let mut shell = Shell {
+        db: &db,
+        current_path: vec![],
+        transaction: None,
+        grove_version,
+    };
+
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("{}", shell.prompt());
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            println!();
+            break;
+        };
+        let line = line?;
+        if is_query_text(&line) {
+            let result = parse_query_text(&line).and_then(|path_query| {
+                print_query_results(
+                    shell.db,
+                    &path_query,
+                    shell.transaction(),
+                    shell.grove_version,
+                )
+            });
+            if let Err(error) = result {
+                eprintln!("error: {}", error);
+            }
+            continue;
+        }
+        let words = match split_words(&line) {
+            Ok(words) => words,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                continue;
+            }
+        };
+        let Some((command, args)) = words.split_first() else {
+            continue;
+        };
+        if command == "exit" || command == "quit" {
+            break;
+        }
+        if let Err(error) = shell.execute(command, args) {
+            eprintln!("error: {}", error);
+        }
+    }
+
+    if shell.transaction.take().is_some() {
+        eprintln!("rolled back the open transaction");
+    }
+    Ok(())
+}
+
+impl<'db> Shell<'db> {
+    fn prompt(&self) -> String {
+        let transaction = if self.transaction.is_some() {
+            " (tx)"
+        } else {
+            ""
+        };
+        format!("{}{}> ", format_path(&self.current_path), transaction)
+    }
+
+    fn transaction(&self) -> TransactionArg<'db, '_> {
+        self.transaction.as_ref()
+    }
+
+    fn execute(&mut self, command: &str, args: &[String]) -> Result<(), CliError> {
+        match command {
+            "pwd" => self.pwd(Args::parse(args, &[])?),
+            "cd" => self.cd(Args::parse(args, &[])?),
+            "ls" => self.ls(Args::parse(args, &[])?),
+            "cat" => self.cat(Args::parse(args, &[])?),
+            "query" => self.query(Args::parse(args, QUERY_OPTIONS)?),
+            "find" => self.find(Args::parse(args, &[])?),
+            "insert" => self.insert(Args::parse(args, &[])?),
+            "rm" => self.rm(Args::parse(args, &["--recursive"])?),
+            "root-hash" => self.root_hash(Args::parse(args, &[])?),
+            "begin" => self.begin(Args::parse(args, &[])?),
+            "commit" => self.commit(Args::parse(args, &[])?),
+            "rollback" => self.rollback(Args::parse(args, &[])?),
+            "help" => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            command => Err(CliError::Usage(format!(
+                "unknown command `{}`, run `help` for the list of commands",
+                command
+            ))),
+        }
+    }
+
+    // Resolves a path relative to the current subtree
+    fn resolve_path(&self, text: &str) -> Result<Vec<Vec<u8>>, CliError> {
+        resolve_path(&self.current_path, text)
+    }
+
+    // Resolves the path of an element relative to the current subtree into
+    // the path of its subtree and its key
+    fn resolve_path_key(&self, text: &str) -> Result<(Vec<Vec<u8>>, Vec<u8>), CliError> {
+        let mut path = self.resolve_path(text)?;
+        let key = path.pop().ok_or_else(|| {
+            CliError::Usage(format!("expected a path ending with a key, got `{}`", text))
+        })?;
+        Ok((path, key))
+    }
+
+    // Resolves a path relative to the current subtree, failing if it isn't a
+    // subtree
+    fn resolve_subtree(&self, text: Option<String>) -> Result<Vec<Vec<u8>>, CliError> {
+        let Some(text) = text else {
+            return Ok(self.current_path.clone());
+        };
+        let path = self.resolve_path(&text)?;
+        if let Some((key, parent_path)) = path.split_last() {
+            let element = self
+                .db
+                .get_raw(
+                    parent_path.into(),
+                    key,
+                    self.transaction(),
+                    self.grove_version,
+                )
+                .unwrap()?;
+            if !element.is_any_tree() {
+                return Err(CliError::Failed(format!(
+                    "{} is not a subtree",
+                    format_path(&path)
+                )));
+            }
+        }
+        Ok(path)
+    }
+
+    fn pwd(&self, args: Args) -> Result<(), CliError> {
+        args.finish()?;
+        println!("{}", format_path(&self.current_path));
+        Ok(())
+    }
+
+    fn cd(&mut self, mut args: Args) -> Result<(), CliError> {
+        let path = args.next_optional().unwrap_or_else(|| "/".to_string());
+        args.finish()?;
+        self.current_path = self.resolve_subtree(Some(path))?;
+        Ok(())
+    }
+
+    fn ls(&self, mut args: Args) -> Result<(), CliError> {
+        let path = self.resolve_subtree(args.next_optional())?;
+        args.finish()?;
+        let mut query = Query::new();
+        query.insert_all();
+        let (results, _) = self
+            .db
+            .query_raw(
+                &PathQuery::new_unsized(path, query),
+                true,
+                true,
+                true,
+                QueryResultType::QueryKeyElementPairResultType,
+                self.transaction(),
+                self.grove_version,
+            )
+            .unwrap()?;
+        let entries: Vec<(String, &'static str)> = results
+            .to_key_elements()
+            .iter()
+            .map(|(key, element)| {
+                let suffix = if element.is_any_tree() { "/" } else { "" };
+                (
+                    format!("{}{}", format_bytes(key), suffix),
+                    element_type(element),
+                )
+            })
+            .collect();
+        let width = entries.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+        for (key, type_name) in entries {
+            println!("{:<width$}  {}", key, type_name, width = width);
+        }
+        Ok(())
+    }
+
+    fn cat(&self, mut args: Args) -> Result<(), CliError> {
+        let (path, key) = self.resolve_path_key(&args.next("key")?)?;
+        args.finish()?;
+        let element = self
+            .db
+            .get(
+                path.as_slice(),
+                &key,
+                self.transaction(),
+                self.grove_version,
+            )
+            .unwrap()?;
+        match element {
+            Element::Item(value, _) => match String::from_utf8(value) {
+                Ok(text) => println!("{}", text),
+                Err(error) => println!("0x{}", hex::encode(error.as_bytes())),
+            },
+            element => println!("{}", format_element(&element)),
+        }
+        Ok(())
+    }
+
+    fn query(&self, mut args: Args) -> Result<(), CliError> {
+        let path_query = items_query(self.current_path.clone(), &mut args)?;
+        args.finish()?;
+        print_query_results(self.db, &path_query, self.transaction(), self.grove_version)
+    }
+
+    fn find(&self, mut args: Args) -> Result<(), CliError> {
+        let path = self.resolve_subtree(args.next_optional())?;
+        args.finish()?;
+        let mut subtrees = self
+            .db
+            .find_subtrees(
+                &SubtreePath::from(path.as_slice()),
+                self.transaction(),
+                self.grove_version,
+            )
+            .unwrap()?;
+        subtrees.sort();
+        for subtree in subtrees.iter().filter(|subtree| **subtree != path) {
+            println!("{}", format_path(subtree));
+        }
+        Ok(())
+    }
+
+    fn insert(&self, mut args: Args) -> Result<(), CliError> {
+        let (path, key) = self.resolve_path_key(&args.next("key")?)?;
+        let element_type = args.next("type")?;
+        let value = args.next_optional();
+        args.finish()?;
+        let element = parse_element(&element_type, value.as_deref())?;
+        self.db
+            .insert(
+                path.as_slice(),
+                &key,
+                element,
+                Some(InsertOptions::default()),
+                self.transaction(),
+                self.grove_version,
+            )
+            .unwrap()?;
+        println!("inserted {}", format_path_key(&path, &key));
+        Ok(())
+    }
+
+    fn rm(&self, mut args: Args) -> Result<(), CliError> {
+        let (path, key) = self.resolve_path_key(&args.next("key")?)?;
+        let options = DeleteOptions {
+            allow_deleting_non_empty_trees: args.flag("--recursive"),
+            ..Default::default()
+        };
+        args.finish()?;
+        self.db
+            .delete(
+                path.as_slice(),
+                &key,
+                Some(options),
+                self.transaction(),
+                self.grove_version,
+            )
+            .unwrap()?;
+        println!("deleted {}", format_path_key(&path, &key));
+        Ok(())
+    }
+
+    fn root_hash(&self, args: Args) -> Result<(), CliError> {
+        args.finish()?;
+        let root_hash = self
+            .db
+            .root_hash(self.transaction(), self.grove_version)
+            .unwrap()?;
+        println!("{}", hex::encode(root_hash));
+        Ok(())
+    }
+
+    fn begin(&mut self, args: Args) -> Result<(), CliError> {
+        args.finish()?;
+        if self.transaction.is_some() {
+            return Err(CliError::Failed(
+                "a transaction is already open".to_string(),
+            ));
+        }
+        self.transaction = Some(self.db.start_transaction());
+        Ok(())
+    }
+
+    fn commit(&mut self, args: Args) -> Result<(), CliError> {
+        args.finish()?;
+        let transaction = self
+            .transaction
+            .take()
+            .ok_or_else(|| CliError::Failed("no open transaction".to_string()))?;
+        self.db.commit_transaction(transaction).unwrap()?;
+        println!("committed");
+        Ok(())
+    }
+
+    fn rollback(&mut self, args: Args) -> Result<(), CliError> {
+        args.finish()?;
+        let transaction = self
+            .transaction
+            .take()
+            .ok_or_else(|| CliError::Failed("no open transaction".to_string()))?;
+        self.db.rollback_transaction(&transaction)?;
+        println!("rolled back");
+        Ok(())
+    }
+}
+
+// Resolves a path relative to `current_path`. `..` above the root stays at
+// the root.
+fn resolve_path(current_path: &[Vec<u8>], text: &str) -> Result<Vec<Vec<u8>>, CliError> {
+    let mut path = if text.starts_with('/') {
+        vec![]
+    } else {
+        current_path.to_vec()
+    };
+    for segment in text.split('/').filter(|segment| !segment.is_empty()) {
+        match segment {
+            "." => {}
+            ".." => {
+                path.pop();
+            }
+            segment => path.push(parse_bytes(segment)?),
+        }
+    }
+    Ok(path)
+}
+
+// Splits a command line into words separated by whitespace. Double quotes
+// group words, a backslash escapes the next character.
+fn split_words(line: &str) -> Result<Vec<String>, CliError> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| CliError::Usage("trailing backslash".to_string()))?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err(CliError::Usage("unterminated quote".to_string()));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words("  insert key  item value ").unwrap(),
+            words(&["insert", "key", "item", "value"])
+        );
+        assert_eq!(split_words("").unwrap(), words(&[]));
+        assert_eq!(
+            split_words("insert \"two words\" item a\"b\"c").unwrap(),
+            words(&["insert", "two words", "item", "abc"])
+        );
+        assert_eq!(split_words("cat \"\"").unwrap(), words(&["cat", ""]));
+        assert_eq!(
+            split_words("insert two\\ words item \\\"quoted\\\" a\\\\b").unwrap(),
+            words(&["insert", "two words", "item", "\"quoted\"", "a\\b"])
+        );
+        assert_eq!(
+            split_words("cat \"a \\\" b\"").unwrap(),
+            words(&["cat", "a \" b"])
+        );
+        assert!(matches!(split_words("cat \"key"), Err(CliError::Usage(_))));
+        assert!(matches!(split_words("cat key\\"), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let current_path = vec![b"student".to_vec(), b"1".to_vec()];
+        assert_eq!(
+            resolve_path(&current_path, "name").unwrap(),
+            vec![b"student".to_vec(), b"1".to_vec(), b"name".to_vec()]
+        );
+        assert_eq!(
+            resolve_path(&current_path, "/grade/0x01").unwrap(),
+            vec![b"grade".to_vec(), vec![1]]
+        );
+        assert_eq!(
+            resolve_path(&current_path, "../2").unwrap(),
+            vec![b"student".to_vec(), b"2".to_vec()]
+        );
+        assert_eq!(
+            resolve_path(&current_path, "./name/.").unwrap(),
+            vec![b"student".to_vec(), b"1".to_vec(), b"name".to_vec()]
+        );
+        assert_eq!(resolve_path(&current_path, ".").unwrap(), current_path);
+        assert_eq!(
+            resolve_path(&current_path, "../..").unwrap(),
+            Vec::<Vec<u8>>::new()
+        );
+        assert_eq!(
+            resolve_path(&current_path, "/").unwrap(),
+            Vec::<Vec<u8>>::new()
+        );
+        assert!(matches!(
+            resolve_path(&current_path, "0xzz"),
+            Err(CliError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_path_above_root() {
+        assert_eq!(resolve_path(&[], "..").unwrap(), Vec::<Vec<u8>>::new());
+        assert_eq!(
+            resolve_path(&[], "../../key").unwrap(),
+            vec![b"key".to_vec()]
+        );
+        assert_eq!(
+            resolve_path(&[b"student".to_vec()], "/..").unwrap(),
+            Vec::<Vec<u8>>::new()
+        );
+    }
+}
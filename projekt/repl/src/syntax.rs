@@ -0,0 +1,328 @@
+//! Human-readable syntax of paths, keys, elements and query items
+//!
+//! A path is written as `/`-separated segments, e.g. `/student/1`, and `/`
+//! alone is the root path. Every path segment, key and item value is written
+//! as one of:
+//! - plain text, taken as its UTF-8 bytes: `student`
+//! - `0x` followed by hex digits: `0x0a1b`
+//! - `u64:` or `i64:` followed by an integer, taken as its 8 big-endian bytes
+//! - `str:` followed by text, for text that would otherwise be read as one of
+//!   the forms above
+
+use grovedb::{reference_path::ReferencePathType, Element, QueryItem};
+
+use crate::CliError;
+
+/// Parses bytes written in the syntax of a path segment, key or item value
+pub fn parse_bytes(text: &str) -> Result<Vec<u8>, CliError> {
+    if let Some(digits) = text.strip_prefix("0x") {
+        hex::decode(digits).map_err(|e| CliError::Usage(format!("invalid hex `{}`: {}", text, e)))
+    } else if let Some(number) = text.strip_prefix("u64:") {
+        number
+            .parse::<u64>()
+            .map(|number| number.to_be_bytes().to_vec())
+            .map_err(|e| CliError::Usage(format!("invalid u64 `{}`: {}", text, e)))
+    } else if let Some(number) = text.strip_prefix("i64:") {
+        number
+            .parse::<i64>()
+            .map(|number| number.to_be_bytes().to_vec())
+            .map_err(|e| CliError::Usage(format!("invalid i64 `{}`: {}", text, e)))
+    } else {
+        let text = text.strip_prefix("str:").unwrap_or(text);
+        Ok(text.as_bytes().to_vec())
+    }
+}
+
+/// Parses a path such as `/student/1`
+pub fn parse_path(text: &str) -> Result<Vec<Vec<u8>>, CliError> {
+    let text = text.strip_prefix('/').unwrap_or(text);
+    if text.is_empty() {
+        return Ok(vec![]);
+    }
+    text.split('/')
+        .map(|segment| {
+            if segment.is_empty() {
+                Err(CliError::Usage(format!(
+                    "empty segment in path `/{}`",
+                    text
+                )))
+            } else {
+                parse_bytes(segment)
+            }
+        })
+        .collect()
+}
+
+/// Parses a path whose last segment is the key of an element, such as
+/// `/student/1`, into the path of its subtree and the key
+pub fn parse_path_key(text: &str) -> Result<(Vec<Vec<u8>>, Vec<u8>), CliError> {
+    let mut path = parse_path(text)?;
+    let key = path.pop().ok_or_else(|| {
+        CliError::Usage(format!("expected a path ending with a key, got `{}`", text))
+    })?;
+    Ok((path, key))
+}
+
+/// Parses an element from its type and value: `item <value>`,
+/// `sum_item <integer>`, `ref <path/key>`, `tree` or `sum_tree`
+pub fn parse_element(element_type: &str, value: Option<&str>) -> Result<Element, CliError> {
+    let required_value =
+        || value.ok_or_else(|| CliError::Usage(format!("missing value of {}", element_type)));
+    match element_type {
+        "item" => Ok(Element::new_item(parse_bytes(required_value()?)?)),
+        "sum_item" => {
+            let value = required_value()?;
+            value
+                .parse::<i64>()
+                .map(Element::new_sum_item)
+                .map_err(|e| CliError::Usage(format!("invalid sum `{}`: {}", value, e)))
+        }
+        "ref" => {
+            let (mut path, key) = parse_path_key(required_value()?)?;
+            path.push(key);
+            Ok(Element::new_reference(
+                ReferencePathType::AbsolutePathReference(path),
+            ))
+        }
+        "tree" => Ok(Element::empty_tree()),
+        "sum_tree" => Ok(Element::empty_sum_tree()),
+        _ => Err(CliError::Usage(format!(
+            "unknown element type `{}`, expected item, sum_item, ref, tree or sum_tree",
+            element_type
+        ))),
+    }
+}
+
+/// Parses a query item: a key, or a range of keys written as `a..b`, `a..=b`,
+/// `a..`, `..b`, `..=b` or `..`
+pub fn parse_query_item(text: &str) -> Result<QueryItem, CliError> {
+    let Some((start, end)) = text.split_once("..") else {
+        return Ok(QueryItem::Key(parse_bytes(text)?));
+    };
+    let bound = |text: &str| {
+        if text.is_empty() {
+            Ok(None)
+        } else {
+            parse_bytes(text).map(Some)
+        }
+    };
+    if let Some(end) = end.strip_prefix('=') {
+        match (bound(start)?, bound(end)?) {
+            (Some(start), Some(end)) => Ok(QueryItem::RangeInclusive(start..=end)),
+            (None, Some(end)) => Ok(QueryItem::RangeToInclusive(..=end)),
+            (_, None) => Err(CliError::Usage(format!(
+                "inclusive range `{}` has no end",
+                text
+            ))),
+        }
+    } else {
+        match (bound(start)?, bound(end)?) {
+            (Some(start), Some(end)) => Ok(QueryItem::Range(start..end)),
+            (Some(start), None) => Ok(QueryItem::RangeFrom(start..)),
+            (None, Some(end)) => Ok(QueryItem::RangeTo(..end)),
+            (None, None) => Ok(QueryItem::RangeFull(..)),
+        }
+    }
+}
+
+/// Formats a path segment or key so that `parse_bytes` reads it back
+pub fn format_bytes(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text)
+            if !text.is_empty()
+                && !text.contains('/')
+                && !text.chars().any(|c| c.is_control() || c.is_whitespace()) =>
+        {
+            if parse_bytes(text).ok().as_deref() == Some(bytes) {
+                text.to_string()
+            } else {
+                format!("str:{}", text)
+            }
+        }
+        _ => format!("0x{}", hex::encode(bytes)),
+    }
+}
+
+/// Formats a path, `/` for the root path
+pub fn format_path(path: &[Vec<u8>]) -> String {
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    path.iter()
+        .map(|segment| format!("/{}", format_bytes(segment)))
+        .collect()
+}
+
+/// Formats the path of an element from the path of its subtree and its key
+pub fn format_path_key(path: &[Vec<u8>], key: &[u8]) -> String {
+    format!(
+        "{}/{}",
+        format_path(path).trim_end_matches('/'),
+        format_bytes(key)
+    )
+}
+
+/// Formats an element by type, in the syntax of `parse_element` where it
+/// applies
+pub fn format_element(element: &Element) -> String {
+    let (description, flags) = match element {
+        Element::Item(value, flags) => (format!("item {}", format_value(value)), flags),
+        Element::SumItem(value, flags) => (format!("sum_item {}", value), flags),
+        Element::Reference(reference_path, max_hop, flags) => {
+            let target = match reference_path {
+                ReferencePathType::AbsolutePathReference(path) => format_path(path),
+                reference_path => reference_path.to_string(),
+            };
+            let max_hop =
+                max_hop.map_or(String::new(), |max_hop| format!(" (max hop {})", max_hop));
+            (format!("ref {}{}", target, max_hop), flags)
+        }
+        Element::Tree(root_key, flags) => (format!("tree{}", empty_marker(root_key)), flags),
+        Element::SumTree(root_key, sum, flags) => {
+            (format!("sum_tree {}{}", sum, empty_marker(root_key)), flags)
+        }
+    };
+    match flags {
+        Some(flags) => format!("{} [flags 0x{}]", description, hex::encode(flags)),
+        None => description,
+    }
+}
+
+/// Returns the name of the type of an element, as taken by `parse_element`
+pub fn element_type(element: &Element) -> &'static str {
+    match element {
+        Element::Item(..) => "item",
+        Element::SumItem(..) => "sum_item",
+        Element::Reference(..) => "ref",
+        Element::Tree(..) => "tree",
+        Element::SumTree(..) => "sum_tree",
+    }
+}
+
+// Formats an item value as a quoted string if it is text, in hex otherwise
+fn format_value(value: &[u8]) -> String {
+    match std::str::from_utf8(value) {
+        Ok(text) if !text.chars().any(char::is_control) => format!("{:?}", text),
+        _ => format!("0x{}", hex::encode(value)),
+    }
+}
+
+fn empty_marker(root_key: &Option<Vec<u8>>) -> &'static str {
+    if root_key.is_none() {
+        " (empty)"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("student").unwrap(), b"student");
+        assert_eq!(parse_bytes("0x0a1b").unwrap(), [0x0a, 0x1b]);
+        assert_eq!(parse_bytes("u64:1").unwrap(), 1u64.to_be_bytes());
+        assert_eq!(parse_bytes("i64:-1").unwrap(), (-1i64).to_be_bytes());
+        assert_eq!(parse_bytes("str:0x0a").unwrap(), b"0x0a");
+        assert_eq!(parse_bytes("").unwrap(), b"");
+        assert!(matches!(parse_bytes("0xzz"), Err(CliError::Usage(_))));
+        assert!(matches!(parse_bytes("u64:-1"), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn test_format_bytes_round_trip() {
+        let cases: [&[u8]; 9] = [
+            b"student",
+            b"0x0a",
+            b"u64:1",
+            b"str:text",
+            b"a/b",
+            b"two words",
+            b"",
+            &[0, 0xff],
+            &[0xc3, 0x28],
+        ];
+        for bytes in cases {
+            let text = format_bytes(bytes);
+            assert_eq!(
+                parse_bytes(&text).unwrap(),
+                bytes,
+                "formatted as `{}`",
+                text
+            );
+        }
+        assert_eq!(format_bytes(b"student"), "student");
+        assert_eq!(format_bytes(b"0x0a"), "str:0x0a");
+        assert_eq!(format_bytes(b"a/b"), "0x612f62");
+    }
+
+    #[test]
+    fn test_parse_path_key() {
+        assert_eq!(
+            parse_path_key("/student/1").unwrap(),
+            (vec![b"student".to_vec()], b"1".to_vec())
+        );
+        assert_eq!(
+            parse_path_key("student/0x01").unwrap(),
+            (vec![b"student".to_vec()], vec![1])
+        );
+        assert_eq!(parse_path_key("/key").unwrap(), (vec![], b"key".to_vec()));
+        assert!(matches!(parse_path_key("/"), Err(CliError::Usage(_))));
+        assert!(matches!(parse_path_key("/a//b"), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn test_format_path_round_trip() {
+        let path = vec![b"student".to_vec(), 7u64.to_be_bytes().to_vec()];
+        assert_eq!(parse_path(&format_path(&path)).unwrap(), path);
+        assert_eq!(format_path(&[]), "/");
+        assert_eq!(parse_path("/").unwrap(), Vec::<Vec<u8>>::new());
+        assert_eq!(
+            parse_path_key(&format_path_key(&path, b"name")).unwrap(),
+            (path, b"name".to_vec())
+        );
+        assert_eq!(format_path_key(&[], b"key"), "/key");
+    }
+
+    #[test]
+    fn test_parse_query_item() {
+        let a = || b"a".to_vec();
+        let b = || b"b".to_vec();
+        assert!(matches!(parse_query_item("a"), Ok(QueryItem::Key(key)) if key == a()));
+        assert!(matches!(
+            parse_query_item("a..b"),
+            Ok(QueryItem::Range(range)) if range == (a()..b())
+        ));
+        assert!(matches!(
+            parse_query_item("a..=b"),
+            Ok(QueryItem::RangeInclusive(range)) if range == (a()..=b())
+        ));
+        assert!(matches!(
+            parse_query_item("a.."),
+            Ok(QueryItem::RangeFrom(range)) if range == (a()..)
+        ));
+        assert!(matches!(
+            parse_query_item("..b"),
+            Ok(QueryItem::RangeTo(range)) if range == (..b())
+        ));
+        assert!(matches!(
+            parse_query_item("..=b"),
+            Ok(QueryItem::RangeToInclusive(range)) if range == (..=b())
+        ));
+        assert!(matches!(
+            parse_query_item(".."),
+            Ok(QueryItem::RangeFull(..))
+        ));
+        assert!(matches!(
+            parse_query_item("0x00..u64:1"),
+            Ok(QueryItem::Range(range)) if range == (vec![0]..1u64.to_be_bytes().to_vec())
+        ));
+        assert!(matches!(parse_query_item("a..="), Err(CliError::Usage(_))));
+        assert!(matches!(
+            parse_query_item("0xz..b"),
+            Err(CliError::Usage(_))
+        ));
+    }
+}